@@ -0,0 +1,43 @@
+//! 比较忽略大小写搜索的新旧实现: `cargo bench --bench case_fold`
+mod common;
+
+use std::hint::black_box;
+use common::Lcg;
+use minigrep::search_case_insensitive;
+
+const LINES: usize = 200_000;
+const WORDS: [&str; 12] = [
+    "request", "Handler", "TIMEOUT", "connection", "Ошибка", "повтор", "σύνδεση", "ΧΡΌΝΟΣ", "Straße", "worker",
+    "queue", "latency",
+];
+
+/// 之前的实现:每一行都转换成小写的新字符串再查找
+fn lowercase_per_line(query: &str, content: &str) -> Vec<usize> {
+    let lower_query = query.to_lowercase();
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.to_lowercase().contains(&lower_query))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+fn main() {
+    // ASCII和非ASCII混合的伪随机文本
+    let content = common::log_lines(&mut Lcg::new(), &WORDS, LINES);
+    println!("corpus: {} lines, {:.1} MB", LINES, content.len() as f64 / 1_000_000.0);
+
+    for query in ["timeout worker", "ОШИБКА повтор", "χρόνος", "STRASSE"] {
+        let (old, old_found) = common::fastest(5, || black_box(lowercase_per_line(query, &content)).len());
+        let (new, new_found) = common::fastest(5, || black_box(search_case_insensitive(query, &content)).len());
+        println!(
+            "{:<16} to_lowercase: {:>8.1?} ({} lines)  case fold: {:>8.1?} ({} lines)  speedup {:.2}x",
+            format!("{:?}", query),
+            old,
+            old_found,
+            new,
+            new_found,
+            old.as_secs_f64() / new.as_secs_f64()
+        );
+    }
+}
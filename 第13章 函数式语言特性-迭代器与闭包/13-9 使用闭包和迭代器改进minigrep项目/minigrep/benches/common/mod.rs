@@ -0,0 +1,43 @@
+//! 各个基准测试共用的语料生成和计时
+use std::time::{Duration, Instant};
+
+/// 线性同余生成器,种子固定,每次运行得到相同的语料
+pub struct Lcg(u64);
+
+impl Lcg {
+    pub fn new() -> Lcg {
+        Lcg(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// `0..len`中的一个伪随机数
+    pub fn below(&mut self, len: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (self.0 >> 33) as usize % len
+    }
+}
+
+/// 生成`lines`行伪随机的日志,每行是行号和从`words`中随机选出的8个单词
+pub fn log_lines(rng: &mut Lcg, words: &[&str], lines: usize) -> String {
+    let mut content = String::new();
+    for line in 0..lines {
+        content.push_str(&format!("{:06} ", line));
+        for _ in 0..8 {
+            content.push_str(words[rng.below(words.len())]);
+            content.push(' ');
+        }
+        content.push('\n');
+    }
+    content
+}
+
+/// 运行`runs`次取最快的一次,返回耗时和那一次的结果
+pub fn fastest<T: Ord>(runs: usize, mut run: impl FnMut() -> T) -> (Duration, T) {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            let result = run();
+            (start.elapsed(), result)
+        })
+        .min()
+        .unwrap()
+}
@@ -0,0 +1,60 @@
+//! 在生成的语料上比较单线程和多线程搜索的耗时: `cargo bench --bench parallel`
+mod common;
+
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+use common::Lcg;
+use minigrep::{run_to, Config};
+
+const FILES: usize = 256;
+const LINES_PER_FILE: usize = 4_000;
+const WORDS: [&str; 12] = [
+    "request", "handler", "timeout", "connection", "Error", "retry", "socket", "buffer", "Needle", "worker", "queue",
+    "latency",
+];
+
+/// 生成伪随机的日志文件,分散在16个子目录中,返回语料的总字节数
+fn generate(root: &Path) -> usize {
+    let mut rng = Lcg::new();
+    let mut total = 0;
+    for file in 0..FILES {
+        let dir = root.join(format!("dir{:02}", file % 16));
+        fs::create_dir_all(&dir).unwrap();
+        let content = common::log_lines(&mut rng, &WORDS, LINES_PER_FILE);
+        total += content.len();
+        fs::write(dir.join(format!("file{:03}.log", file)), content).unwrap();
+    }
+    total
+}
+
+/// 用`threads`个线程搜索整个语料,运行若干次取最快的一次
+fn measure(root: &Path, threads: usize) -> Duration {
+    let args = ["minigrep", "-i", "-j", &threads.to_string(), "needle error", &root.display().to_string()]
+        .map(String::from);
+    let config = Config::new(args).unwrap();
+    common::fastest(3, || run_to(&config, &mut io::sink()).unwrap()).0
+}
+
+fn main() {
+    let dir = tempfile::tempdir().unwrap();
+    let bytes = generate(dir.path());
+    println!("corpus: {} files, {:.1} MB", FILES, bytes as f64 / 1_000_000.0);
+
+    let single = measure(dir.path(), 1);
+    println!("{:>2} thread : {:>8.1?}", 1, single);
+    let cpus = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let mut threads = 2;
+    while threads <= cpus.max(2) {
+        let elapsed = measure(dir.path(), threads);
+        println!(
+            "{:>2} threads: {:>8.1?}  speedup {:.2}x",
+            threads,
+            elapsed,
+            single.as_secs_f64() / elapsed.as_secs_f64()
+        );
+        threads *= 2;
+    }
+}
@@ -0,0 +1,175 @@
+use std::iter;
+use std::ops::Range;
+use memchr::memchr3;
+use table::SIMPLE_FOLD;
+
+mod table;
+
+/// Unicode简单大小写折叠(CaseFolding.txt中状态为C和S的映射),一个字符总是折叠成一个字符.
+/// 不做`ß`到`ss`这样改变长度的完全折叠,也不做土耳其语的特殊映射,和正则表达式的`(?i)`一致
+pub fn fold(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+    match SIMPLE_FOLD.binary_search_by_key(&c, |&(from, _)| from) {
+        Ok(index) => SIMPLE_FOLD[index].1,
+        Err(_) => c,
+    }
+}
+
+/// 忽略大小写的子串查找,查询字符串只在创建时折叠一次,查找时逐个字符折叠比较,不分配内存
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseInsensitive {
+    folded: Vec<char>,
+    /// 折叠后等于查询字符串第一个字符的所有字符的UTF-8首字节,不超过3个时用memchr跳到可能匹配的位置
+    first_bytes: Option<[u8; 3]>,
+}
+
+impl CaseInsensitive {
+    pub fn new(query: &str) -> CaseInsensitive {
+        let folded: Vec<char> = query.chars().map(fold).collect();
+        let first_bytes = folded.first().and_then(|&first| {
+            let mut bytes: Vec<u8> = SIMPLE_FOLD
+                .iter()
+                .filter(|&&(_, to)| to == first)
+                .map(|&(from, _)| from)
+                .chain([first])
+                .map(|c| c.encode_utf8(&mut [0; 4]).as_bytes()[0])
+                .collect();
+            bytes.sort_unstable();
+            bytes.dedup();
+            match bytes[..] {
+                [a] => Some([a, a, a]),
+                [a, b] => Some([a, b, b]),
+                [a, b, c] => Some([a, b, c]),
+                _ => None,
+            }
+        });
+        CaseInsensitive { folded, first_bytes }
+    }
+
+    pub fn is_match(&self, haystack: &str) -> bool {
+        self.find_iter(haystack).next().is_some()
+    }
+
+    /// 在可能不是合法UTF-8的一行中查找,匹配不会跨过非法的字节
+    pub fn is_match_bytes(&self, haystack: &[u8]) -> bool {
+        if self.folded.is_empty() {
+            return true;
+        }
+        haystack.utf8_chunks().any(|chunk| self.is_match(chunk.valid()))
+    }
+
+    /// 所有不重叠的匹配在`haystack`中的字节范围,折叠前后长度可能不同,范围按原文计算
+    pub fn find_iter<'a>(&'a self, haystack: &'a str) -> impl Iterator<Item = Range<usize>> + 'a {
+        let mut start = 0;
+        iter::from_fn(move || {
+            while start <= haystack.len() {
+                let at = match self.first_bytes {
+                    // UTF-8的首字节不会出现在字符中间,找到的位置一定是字符边界
+                    Some([a, b, c]) => start + memchr3(a, b, c, &haystack.as_bytes()[start..])?,
+                    None => start,
+                };
+                let next_char = at + haystack[at..].chars().next().map_or(1, char::len_utf8);
+                match self.match_len(&haystack[at..]) {
+                    Some(len) => {
+                        // 空的查询字符串在每个字符边界都匹配一次
+                        start = if len == 0 { next_char } else { at + len };
+                        return Some(at..at + len);
+                    }
+                    None => start = next_char,
+                }
+            }
+            None
+        })
+    }
+
+    /// 和`find_iter`相同,`haystack`可以不是合法的UTF-8
+    pub fn find_iter_bytes<'a>(&'a self, haystack: &'a [u8]) -> impl Iterator<Item = Range<usize>> + 'a {
+        valid_chunks(haystack)
+            .flat_map(move |(start, valid)| self.find_iter(valid).map(move |span| span.start + start..span.end + start))
+    }
+
+    /// `haystack`以查询字符串开头时返回匹配部分的字节数
+    fn match_len(&self, haystack: &str) -> Option<usize> {
+        let mut chars = haystack.chars();
+        for &expected in &self.folded {
+            chars.next().filter(|&c| fold(c) == expected)?;
+        }
+        Some(haystack.len() - chars.as_str().len())
+    }
+}
+
+/// 被非法字节隔开的每一段合法的UTF-8,以及它在`haystack`中的起始位置
+pub(crate) fn valid_chunks(haystack: &[u8]) -> impl Iterator<Item = (usize, &str)> {
+    haystack.utf8_chunks().scan(0, |offset, chunk| {
+        let start = *offset;
+        *offset += chunk.valid().len() + chunk.invalid().len();
+        Some((start, chunk.valid()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // 折叠表按原字符排序,才能二分查找
+    fn table_is_sorted() {
+        assert!(SIMPLE_FOLD.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!('a', fold('A'));
+        assert_eq!('1', fold('1'));
+        assert_eq!('中', fold('中'));
+    }
+
+    #[test]
+    // 非ASCII字符的大小写
+    fn non_ascii() {
+        let matches = |query: &str, haystack: &str| CaseInsensitive::new(query).is_match(haystack);
+        // 希腊字母的大写Σ和词尾的ς都折叠成σ
+        assert!(matches("ΟΔΥΣΣΕΥΣ", "ο οδυσσευς"));
+        assert!(matches("σοφος", "ΣΟΦΟΣ"));
+        assert!(matches("ΣΟΦΟΣ", "σοφος"));
+        assert!(matches("Ελλάς", "ΕΛΛΆΣ"));
+        assert!(matches("МОСКВА", "город москва"));
+        assert!(matches("straße", "STRAẞE"));
+        assert!(matches("Äpfel", "ÄPFEL und birnen"));
+        // 开尔文符号K和长s折叠成ASCII字母
+        assert!(matches("kelvin", "300 \u{212A}ELVIN"));
+        assert!(matches("KISS", "ki\u{17F}s"));
+        assert!(matches("\u{212A}", "k"));
+
+        // 简单折叠不改变长度,ß和SS不相等
+        assert!(!matches("strasse", "straße"));
+        // 带点的大写I没有简单折叠
+        assert!(!matches("i", "İ"));
+        assert!(!matches("москва", "moskva"));
+        assert!(matches("", "anything"));
+    }
+
+    #[test]
+    // 非法的字节把一行分成几段,匹配只在各段内进行
+    fn invalid_bytes() {
+        let query = CaseInsensitive::new("café");
+        assert!(query.is_match_bytes(b"\xff CAF\xc3\x89 \xfe"));
+        assert!(!query.is_match_bytes(b"CA\xffF\xc3\x89"));
+        assert!(!query.is_match_bytes(b"caf\xc9"));
+        assert!(CaseInsensitive::new("").is_match_bytes(b"\xff"));
+    }
+
+    #[test]
+    // 匹配的范围按原文的字节计算
+    fn spans() {
+        let spans = |query: &str, haystack: &str| CaseInsensitive::new(query).find_iter(haystack).collect::<Vec<_>>();
+        assert_eq!(vec![0..4, 5..9], spans("RUST", "rust Rust"));
+        assert_eq!(vec![0..4, 5..9, 11..15], spans("rust", "rust RUST trust"));
+        // 开尔文符号占3个字节,折叠后的k只占1个字节
+        assert_eq!(vec![2..10], spans("kelvin", "0 \u{212A}ELVIN"));
+        assert_eq!(vec![3..9], spans("σοφ", "ab ΣΟΦ"));
+        assert_eq!(vec![0..2, 2..4], spans("aa", "aAAa"));
+        assert_eq!(vec![0..0, 1..1, 3..3], spans("", "aé"));
+
+        let query = CaseInsensitive::new("café");
+        assert_eq!(vec![2..7, 9..14], query.find_iter_bytes(b"\xff CAF\xc3\x89\xfe\xfecaf\xc3\xa9").collect::<Vec<_>>());
+    }
+}
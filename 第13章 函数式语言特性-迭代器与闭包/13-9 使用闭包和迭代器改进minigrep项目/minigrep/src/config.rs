@@ -0,0 +1,557 @@
+use std::env;
+use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::thread;
+use encoding_rs::Encoding;
+use regex::bytes::{Regex, RegexBuilder};
+
+pub const USAGE: &str = "Usage: minigrep [OPTIONS] PATTERN [PATH...]";
+
+const HELP: &str = "\
+Search for PATTERN in each PATH and print the matching lines.
+Directories are searched recursively, skipping hidden, ignored and binary files.
+With no PATH, or when PATH is -, standard input is searched.
+
+Usage: minigrep [OPTIONS] PATTERN [PATH...]
+
+Options:
+  -E, --regex               treat PATTERN as a regular expression
+  -i, --ignore-case         ignore case distinctions (also enabled by CASE_INSENSITIVE)
+  -w, --word-regexp         only match whole words
+      --fuzzy K             match substrings within Levenshtein distance K of PATTERN;
+                            the matching lines of each file are printed closest first,
+                            so nothing is printed until the whole file (or stdin) is read
+  -v, --invert-match        print the lines that do not match
+  -o, --only-matching       print only the matched parts of each line
+  -n, --line-number         prefix each line with its line number
+  -c, --count               print only the number of matching lines
+  -A, --after-context N     print N lines of context after each match
+  -B, --before-context N    print N lines of context before each match
+  -C, --context N           print N lines of context before and after each match
+  -l, --files-with-matches  print only the name of the file if it matches
+  -r, --replace REPL        print the matching lines with every match replaced by REPL;
+                            with -E, $1 or ${name} in REPL refer to capture groups
+      --in-place            with --replace, rewrite the files instead of printing them
+      --dry-run             with --in-place, print a unified diff instead of writing
+      --json                print the results as JSON Lines (begin, match, context, end
+                            and summary records), for scripts
+      --color[=WHEN]        color matches, file names and line numbers;
+                            WHEN is auto (the default, honours NO_COLOR), always or never
+  -g, --glob GLOB           only search files matching GLOB, or exclude them with !GLOB
+  -t, --type TYPE           only search files of TYPE, e.g. rust or py
+  -T, --type-not TYPE       do not search files of TYPE
+      --hidden              search hidden files and directories
+      --no-ignore           do not respect .gitignore and .ignore files
+  -a, --text                search binary files as if they were text
+      --encoding ENC        transcode input from ENC, e.g. utf-16le, utf-16be or latin1
+  -j, --threads N           number of threads to search with (default: number of CPUs)
+  -h, --help                print this help and exit
+  -V, --version             print the version and exit";
+
+/// 何时为输出着色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// 标准输出是终端并且没有设置`NO_COLOR`环境变量时着色
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => auto_color(io::stdout().is_terminal(), env::var_os("NO_COLOR").as_deref()),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config {
+    pub query: String,
+    /// 要搜索的文件或目录,目录会被递归搜索,`-`表示标准输入
+    pub paths: Vec<String>,
+    pub case_sensitive: bool,
+    /// `-E`: 把查询字符串当作正则表达式
+    pub regex: bool,
+    /// `-o`: 只输出匹配的部分而不是整行
+    pub only_matching: bool,
+    /// `-v`: 输出不匹配的行
+    pub invert_match: bool,
+    /// `-n`: 在每行前输出行号
+    pub line_number: bool,
+    /// `-c`: 只输出匹配的行数
+    pub count: bool,
+    /// `-l`: 只输出有匹配的文件名
+    pub files_with_matches: bool,
+    /// `-B`: 每个匹配行之前输出的上下文行数
+    pub before_context: usize,
+    /// `-A`: 每个匹配行之后输出的上下文行数
+    pub after_context: usize,
+    /// `-w`: 匹配的前后不能紧挨着字母、数字或下划线
+    pub word_regexp: bool,
+    /// `--fuzzy`: 近似匹配允许的最大编辑距离.
+    /// 每个文件匹配的行读完之后才按距离排序输出,所以搜索`tail -f`之类不会结束的标准输入时什么也不会输出
+    pub fuzzy: Option<usize>,
+    pub color: ColorChoice,
+    /// `--json`: 每行输出一个JSON对象,格式见`json`模块
+    pub json: bool,
+    /// `-r`: 把匹配的部分替换成这个字符串后输出
+    pub replace: Option<String>,
+    /// `--in-place`: 把替换的结果写回文件
+    pub in_place: bool,
+    /// `--dry-run`: 不写回文件,只输出改动的unified diff
+    pub dry_run: bool,
+    /// `-g`: 文件名匹配的glob,以`!`开头时表示排除
+    pub globs: Vec<String>,
+    /// `-t`: 只搜索这些类型的文件,类型的定义见`ignore::types`
+    pub types: Vec<String>,
+    /// `-T`: 不搜索这些类型的文件
+    pub types_not: Vec<String>,
+    /// `--hidden`: 搜索隐藏的文件和目录
+    pub hidden: bool,
+    /// `--no-ignore`: 不读取`.gitignore`和`.ignore`文件
+    pub no_ignore: bool,
+    /// `-a`: 把二进制文件当作文本搜索
+    pub text: bool,
+    /// `--encoding`: 输入的编码,没有指定时按原始字节搜索,只识别UTF-16的BOM
+    pub encoding: Option<&'static Encoding>,
+    /// `-j`: 搜索使用的线程数,0表示和CPU核数相同
+    pub threads: usize,
+}
+
+/// 解析命令行参数失败,或者参数要求输出帮助、版本信息后直接退出
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    Help,
+    Version,
+    Usage(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "{}", HELP),
+            ArgsError::Version => write!(f, "minigrep {}", env!("CARGO_PKG_VERSION")),
+            ArgsError::Usage(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ArgsError {}
+
+impl Config {
+    /// 解析命令行参数,第1个参数是程序名
+    /// 选项可以写在任意位置,短选项可以合并(`-in`),`--`之后的参数都当作位置参数.
+    /// 没有`-i`时,设置了环境变量`CASE_INSENSITIVE`也会忽略大小写
+    pub fn new<I: IntoIterator<Item = String>>(args: I) -> Result<Config, ArgsError> {
+        let mut config = Config {
+            query: String::new(),
+            paths: Vec::new(),
+            case_sensitive: env::var("CASE_INSENSITIVE").is_err(),
+            regex: false,
+            only_matching: false,
+            invert_match: false,
+            line_number: false,
+            count: false,
+            files_with_matches: false,
+            before_context: 0,
+            after_context: 0,
+            word_regexp: false,
+            fuzzy: None,
+            color: ColorChoice::Auto,
+            json: false,
+            replace: None,
+            in_place: false,
+            dry_run: false,
+            globs: Vec::new(),
+            types: Vec::new(),
+            types_not: Vec::new(),
+            hidden: false,
+            no_ignore: false,
+            text: false,
+            encoding: None,
+            threads: 0,
+        };
+
+        // 跳过第1个参数
+        let mut args = args.into_iter().skip(1);
+        let mut positional = Vec::new();
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                positional.extend(args.by_ref());
+            } else if let Some(long) = arg.strip_prefix("--") {
+                config.long_option(long, &mut args)?;
+            } else if arg.len() > 1 && arg.starts_with('-') {
+                config.short_options(&arg[1..], &mut args)?;
+            } else {
+                positional.push(arg);
+            }
+        }
+
+        // 取查询字符串和要搜索的路径
+        let mut positional = positional.into_iter();
+        config.query = positional
+            .next()
+            .ok_or_else(|| ArgsError::Usage("Didn't get a query string".to_string()))?;
+        config.paths = positional.collect();
+        // 没有给出路径时搜索标准输入
+        if config.paths.is_empty() {
+            config.paths.push("-".to_string());
+        }
+
+        config.check_conflicts()?;
+
+        Ok(config)
+    }
+
+    /// 处理合并在一起的短选项,带值的选项之后的部分(或者下一个参数)是它的值
+    fn short_options<I: Iterator<Item = String>>(&mut self, flags: &str, args: &mut I) -> Result<(), ArgsError> {
+        for (i, flag) in flags.char_indices() {
+            if takes_value(flag) {
+                let rest = &flags[i + flag.len_utf8()..];
+                let value = if rest.is_empty() {
+                    args.next().ok_or_else(|| missing_value(&format!("-{}", flag)))?
+                } else {
+                    rest.to_string()
+                };
+                return self.value(flag, value);
+            }
+            self.flag(flag)?;
+        }
+        Ok(())
+    }
+
+    fn flag(&mut self, flag: char) -> Result<(), ArgsError> {
+        match flag {
+            'E' => self.regex = true,
+            'i' => self.case_sensitive = false,
+            'w' => self.word_regexp = true,
+            'v' => self.invert_match = true,
+            'o' => self.only_matching = true,
+            'n' => self.line_number = true,
+            'c' => self.count = true,
+            'l' => self.files_with_matches = true,
+            'a' => self.text = true,
+            'h' => return Err(ArgsError::Help),
+            'V' => return Err(ArgsError::Version),
+            _ => return Err(ArgsError::Usage(format!("unknown option '-{}'", flag))),
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, flag: char, value: String) -> Result<(), ArgsError> {
+        match flag {
+            'g' => self.globs.push(value),
+            't' => self.types.push(value),
+            'T' => self.types_not.push(value),
+            'r' => self.replace = Some(value),
+            'A' => self.after_context = parse_context(&value)?,
+            'B' => self.before_context = parse_context(&value)?,
+            'C' => {
+                self.before_context = parse_context(&value)?;
+                self.after_context = self.before_context;
+            }
+            'j' => {
+                self.threads = value
+                    .parse()
+                    .ok()
+                    .filter(|&threads| threads > 0)
+                    .ok_or_else(|| ArgsError::Usage(format!("invalid number of threads '{}'", value)))?
+            }
+            _ => unreachable!("-{} doesn't take a value", flag),
+        }
+        Ok(())
+    }
+
+    /// 检查不能同时使用的选项
+    fn check_conflicts(&self) -> Result<(), ArgsError> {
+        // JSON输出总是包括整行和匹配的位置
+        if self.json {
+            conflict(
+                "--json",
+                &[
+                    (self.count, "--count"),
+                    (self.files_with_matches, "--files-with-matches"),
+                    (self.only_matching, "--only-matching"),
+                    (self.replace.is_some(), "--replace"),
+                ],
+            )?;
+        }
+        // 近似匹配按编辑距离排序输出,没有正则表达式的捕获组和单词边界
+        if self.fuzzy.is_some() {
+            conflict(
+                "--fuzzy",
+                &[
+                    (self.regex, "--regex"),
+                    (self.word_regexp, "--word-regexp"),
+                    (self.invert_match, "--invert-match"),
+                    (self.only_matching, "--only-matching"),
+                    (self.replace.is_some(), "--replace"),
+                    (self.before_context > 0 || self.after_context > 0, "--context"),
+                ],
+            )?;
+        }
+        if self.in_place && self.replace.is_none() {
+            return Err(ArgsError::Usage("option '--in-place' requires '--replace'".to_string()));
+        }
+        if self.dry_run && !self.in_place {
+            return Err(ArgsError::Usage("option '--dry-run' requires '--in-place'".to_string()));
+        }
+        // 写回文件时按原始的字节替换,并且替换所有匹配的行
+        if self.in_place {
+            conflict(
+                "--in-place",
+                &[
+                    (self.count, "--count"),
+                    (self.files_with_matches, "--files-with-matches"),
+                    (self.only_matching, "--only-matching"),
+                    (self.invert_match, "--invert-match"),
+                    (self.encoding.is_some(), "--encoding"),
+                    (self.paths.iter().any(|path| path == "-"), "standard input"),
+                ],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 实际使用的线程数
+    pub fn threads(&self) -> usize {
+        if self.threads > 0 {
+            return self.threads;
+        }
+        thread::available_parallelism().map_or(1, |threads| threads.get())
+    }
+
+    /// 处理去掉`--`前缀的长选项,带值的选项可以写成`--glob=值`或`--glob 值`
+    fn long_option<I: Iterator<Item = String>>(&mut self, option: &str, args: &mut I) -> Result<(), ArgsError> {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (option, None),
+        };
+        if name == "color" || name == "colour" {
+            self.color = match value {
+                None | Some("auto") => ColorChoice::Auto,
+                Some("always") => ColorChoice::Always,
+                Some("never") => ColorChoice::Never,
+                Some(other) => return Err(ArgsError::Usage(format!("invalid color choice '{}'", other))),
+            };
+            return Ok(());
+        }
+        if name == "fuzzy" {
+            let distance = match value {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or_else(|| missing_value("--fuzzy"))?,
+            };
+            self.fuzzy = Some(
+                distance
+                    .parse()
+                    .map_err(|_| ArgsError::Usage(format!("invalid edit distance '{}'", distance)))?,
+            );
+            return Ok(());
+        }
+        if name == "encoding" {
+            let label = match value {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or_else(|| missing_value("--encoding"))?,
+            };
+            self.encoding = Some(
+                Encoding::for_label(label.as_bytes())
+                    .ok_or_else(|| ArgsError::Usage(format!("unknown encoding '{}'", label)))?,
+            );
+            return Ok(());
+        }
+        if matches!(name, "hidden" | "no-ignore" | "json" | "in-place" | "dry-run") {
+            if value.is_some() {
+                return Err(ArgsError::Usage(format!("option '--{}' doesn't take a value", name)));
+            }
+            match name {
+                "hidden" => self.hidden = true,
+                "no-ignore" => self.no_ignore = true,
+                "json" => self.json = true,
+                "in-place" => self.in_place = true,
+                _ => self.dry_run = true,
+            }
+            return Ok(());
+        }
+
+        let flag = match name {
+            "regex" => 'E',
+            "ignore-case" => 'i',
+            "word-regexp" => 'w',
+            "invert-match" => 'v',
+            "only-matching" => 'o',
+            "line-number" => 'n',
+            "count" => 'c',
+            "files-with-matches" => 'l',
+            "text" => 'a',
+            "help" => 'h',
+            "version" => 'V',
+            "glob" => 'g',
+            "type" => 't',
+            "type-not" => 'T',
+            "replace" => 'r',
+            "threads" => 'j',
+            "after-context" => 'A',
+            "before-context" => 'B',
+            "context" => 'C',
+            _ => return Err(ArgsError::Usage(format!("unknown option '--{}'", name))),
+        };
+        if takes_value(flag) {
+            let value = match value {
+                Some(value) => value.to_string(),
+                None => args.next().ok_or_else(|| missing_value(&format!("--{}", name)))?,
+            };
+            return self.value(flag, value);
+        }
+        if value.is_some() {
+            return Err(ArgsError::Usage(format!("option '--{}' doesn't take a value", name)));
+        }
+        self.flag(flag)
+    }
+
+    /// 普通的子串查找无法满足要求时改用正则表达式,例如需要捕获组、判断单词边界或者替换
+    pub fn needs_regex(&self) -> bool {
+        self.regex || self.only_matching || self.word_regexp || self.replace.is_some()
+    }
+
+    /// 根据配置编译正则表达式,非正则模式下查询字符串会被转义,按字面匹配
+    pub fn build_regex(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+    }
+}
+
+/// 按照<https://no-color.org>的约定,`NO_COLOR`设置为非空的值时不着色
+fn auto_color(terminal: bool, no_color: Option<&OsStr>) -> bool {
+    terminal && no_color.is_none_or(OsStr::is_empty)
+}
+
+fn takes_value(flag: char) -> bool {
+    matches!(flag, 'g' | 't' | 'T' | 'j' | 'A' | 'B' | 'C' | 'r')
+}
+
+/// `option`和`others`中设置了的选项不能同时使用
+fn conflict(option: &str, others: &[(bool, &str)]) -> Result<(), ArgsError> {
+    match others.iter().find(|(set, _)| *set) {
+        Some((_, other)) => Err(ArgsError::Usage(format!("option '{}' can't be used with '{}'", option, other))),
+        None => Ok(()),
+    }
+}
+
+fn parse_context(value: &str) -> Result<usize, ArgsError> {
+    value
+        .parse()
+        .map_err(|_| ArgsError::Usage(format!("invalid context length '{}'", value)))
+}
+
+fn missing_value(option: &str) -> ArgsError {
+    ArgsError::Usage(format!("option '{}' requires a value", option))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, ArgsError> {
+        Config::new(["minigrep"].iter().chain(args).map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    // 短选项可以合并,选项可以写在位置参数之后
+    fn flags_anywhere() {
+        let config = parse(&["-in", "rust", "poem.txt", "--count", "--color=never"]).unwrap();
+        assert_eq!("rust", config.query);
+        assert_eq!(vec!["poem.txt"], config.paths);
+        assert!(!config.case_sensitive);
+        assert!(config.line_number && config.count);
+        assert!(!config.invert_match);
+        assert_eq!(ColorChoice::Never, config.color);
+
+        let config = parse(&["--", "-v", "poem.txt"]).unwrap();
+        assert_eq!("-v", config.query);
+        assert!(!config.invert_match);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(Err(ArgsError::Usage("unknown option '-x'".to_string())), parse(&["-x", "a", "b"]));
+        assert_eq!(Err(ArgsError::Usage("unknown option '--nope'".to_string())), parse(&["--nope", "a", "b"]));
+        assert_eq!(
+            Err(ArgsError::Usage("option '--count' doesn't take a value".to_string())),
+            parse(&["--count=3", "a", "b"])
+        );
+        assert_eq!(Err(ArgsError::Usage("invalid color choice 'red'".to_string())), parse(&["--color=red", "a", "b"]));
+        assert_eq!(
+            Err(ArgsError::Usage("option '--json' can't be used with '--count'".to_string())),
+            parse(&["-c", "--json", "a"])
+        );
+        assert_eq!(
+            Err(ArgsError::Usage("option '--in-place' requires '--replace'".to_string())),
+            parse(&["--in-place", "a", "b"])
+        );
+        assert_eq!(
+            Err(ArgsError::Usage("option '--dry-run' requires '--in-place'".to_string())),
+            parse(&["-r", "x", "--dry-run", "a", "b"])
+        );
+        assert_eq!(
+            Err(ArgsError::Usage("option '--in-place' can't be used with 'standard input'".to_string())),
+            parse(&["-rx", "--in-place", "a"])
+        );
+        assert_eq!(Err(ArgsError::Usage("Didn't get a query string".to_string())), parse(&["-n"]));
+        assert_eq!(Err(ArgsError::Usage("option '--glob' requires a value".to_string())), parse(&["a", "b", "--glob"]));
+        assert_eq!(Err(ArgsError::Usage("invalid number of threads '0'".to_string())), parse(&["-j0", "a", "b"]));
+        assert_eq!(Err(ArgsError::Usage("invalid context length 'x'".to_string())), parse(&["-Cx", "a"]));
+        assert_eq!(Err(ArgsError::Usage("unknown encoding 'klingon'".to_string())), parse(&["--encoding=klingon", "a"]));
+        assert_eq!(Err(ArgsError::Usage("invalid edit distance '-1'".to_string())), parse(&["--fuzzy=-1", "a"]));
+        assert_eq!(
+            Err(ArgsError::Usage("option '--fuzzy' can't be used with '--regex'".to_string())),
+            parse(&["--fuzzy", "1", "-E", "a"])
+        );
+        assert_eq!(Err(ArgsError::Help), parse(&["a", "--help"]));
+        assert_eq!(Err(ArgsError::Version), parse(&["-V"]));
+    }
+
+    #[test]
+    // 带值的选项:值可以紧跟在短选项后面、用`=`连接或者作为下一个参数
+    fn options_with_values() {
+        let config = parse(&["-ng*.rs", "--glob", "!target/**", "--type=py", "-T", "js", "main", "src", "tests"]).unwrap();
+        assert!(config.line_number);
+        assert_eq!(vec!["*.rs", "!target/**"], config.globs);
+        assert_eq!(vec!["py"], config.types);
+        assert_eq!(vec!["js"], config.types_not);
+        assert_eq!("main", config.query);
+        assert_eq!(vec!["src", "tests"], config.paths);
+        assert!(!config.hidden && !config.no_ignore);
+
+        assert_eq!(vec!["-"], parse(&["main"]).unwrap().paths);
+
+        let config = parse(&["-C3", "-A1", "main"]).unwrap();
+        assert_eq!((3, 1), (config.before_context, config.after_context));
+
+        let config = parse(&["-Er", "$1", "(a+)", "--in-place", "--dry-run", "src"]).unwrap();
+        assert_eq!(Some("$1".to_string()), config.replace);
+        assert_eq!("(a+)", config.query);
+        assert!(config.in_place && config.dry_run);
+        assert_eq!(Some(String::new()), parse(&["--replace=", "a"]).unwrap().replace);
+        assert_eq!(Some(2), parse(&["--fuzzy", "2", "recieve"]).unwrap().fuzzy);
+    }
+
+    #[test]
+    // auto只在输出到终端并且NO_COLOR没有设置或为空时着色
+    fn auto_color_choice() {
+        assert!(auto_color(true, None));
+        assert!(auto_color(true, Some(OsStr::new(""))));
+        assert!(!auto_color(true, Some(OsStr::new("1"))));
+        assert!(!auto_color(false, None));
+    }
+}
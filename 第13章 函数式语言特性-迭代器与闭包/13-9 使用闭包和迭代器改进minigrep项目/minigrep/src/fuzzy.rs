@@ -0,0 +1,279 @@
+//! 近似匹配:查找和查询字符串的编辑距离(Levenshtein距离)不超过K的子串.
+//! 查询字符串不超过64个字符时使用Myers的位并行算法,每读入一个字符只需要常数次位运算;
+//! 更长时退回到按列计算的动态规划(Sellers算法),结果相同
+use std::collections::HashMap;
+use std::ops::Range;
+use crate::casefold::{fold, valid_chunks};
+
+/// 位并行算法支持的最大查询长度
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// 近似匹配的查询字符串
+#[derive(Debug, Clone)]
+pub struct Fuzzy {
+    forward: Pattern,
+    /// 反转的查询字符串,从匹配的结尾向前扫描,找出匹配的开头
+    backward: Pattern,
+    max_distance: usize,
+    case_sensitive: bool,
+}
+
+impl Fuzzy {
+    pub fn new(query: &str, max_distance: usize, case_sensitive: bool) -> Fuzzy {
+        let chars: Vec<char> = query.chars().map(|c| if case_sensitive { c } else { fold(c) }).collect();
+        let reversed: Vec<char> = chars.iter().rev().copied().collect();
+        Fuzzy { forward: Pattern::new(chars), backward: Pattern::new(reversed), max_distance, case_sensitive }
+    }
+
+    /// `line`中的子串和查询字符串的最小编辑距离,超过`max_distance`时返回`None`
+    pub fn distance(&self, line: &str) -> Option<usize> {
+        // 空串和查询字符串的距离是查询字符串的长度
+        let mut best = self.forward.chars.len();
+        let mut scanner = self.forward.scanner(false);
+        for c in line.chars() {
+            if best == 0 {
+                break;
+            }
+            best = best.min(scanner.step(self.fold(c)));
+        }
+        (best <= self.max_distance).then_some(best)
+    }
+
+    /// 和`distance`相同,`line`可以不是合法的UTF-8,匹配不会跨过非法的字节
+    pub fn distance_bytes(&self, line: &[u8]) -> Option<usize> {
+        // 空行没有任何一段,但查询字符串足够短时也算匹配
+        if line.is_empty() {
+            return self.distance("");
+        }
+        valid_chunks(line).filter_map(|(_, valid)| self.distance(valid)).min()
+    }
+
+    /// 所有不重叠的近似匹配的字节范围.连续几个位置结尾的子串都足够接近时只取距离最小的一个,
+    /// 再从它的结尾向前找出距离相同的最短的子串
+    pub fn find_spans(&self, line: &str) -> Vec<Range<usize>> {
+        let mut spans = Vec::new();
+        let mut scanner = self.forward.scanner(false);
+        // 当前这一段足够接近的结尾中距离最小的一个
+        let mut best: Option<(usize, usize)> = None;
+        let mut lower = 0;
+        let empty = self.forward.chars.len();
+        if empty <= self.max_distance {
+            best = Some((empty, 0));
+        }
+        for (i, c) in line.char_indices() {
+            let score = scanner.step(self.fold(c));
+            if score <= self.max_distance {
+                if best.is_none_or(|(best, _)| score < best) {
+                    best = Some((score, i + c.len_utf8()));
+                }
+            } else if let Some((score, end)) = best.take() {
+                spans.extend(self.span_ending_at(line, lower, end, score));
+                lower = end;
+            }
+        }
+        if let Some((score, end)) = best {
+            spans.extend(self.span_ending_at(line, lower, end, score));
+        }
+        spans
+    }
+
+    /// 和`find_spans`相同,`line`可以不是合法的UTF-8
+    pub fn find_spans_bytes(&self, line: &[u8]) -> Vec<Range<usize>> {
+        if line.is_empty() {
+            return self.find_spans("");
+        }
+        valid_chunks(line)
+            .flat_map(|(start, valid)| self.find_spans(valid).into_iter().map(move |span| span.start + start..span.end + start))
+            .collect()
+    }
+
+    /// 以`end`结尾、不早于`lower`开始、和查询字符串的距离不超过`score`的最短的子串
+    fn span_ending_at(&self, line: &str, lower: usize, end: usize, score: usize) -> Option<Range<usize>> {
+        // 从`end`开始,每向前读入一个字符,得到`line[start..end]`和查询字符串的距离
+        let mut scanner = self.backward.scanner(true);
+        let mut best = (self.backward.chars.len(), end);
+        for (start, c) in line[lower..end].char_indices().rev() {
+            if best.0 <= score {
+                break;
+            }
+            let distance = scanner.step(self.fold(c));
+            if distance < best.0 {
+                best = (distance, lower + start);
+            }
+        }
+        (best.0 <= self.max_distance).then_some(best.1..end)
+    }
+
+    fn fold(&self, c: char) -> char {
+        if self.case_sensitive {
+            c
+        } else {
+            fold(c)
+        }
+    }
+}
+
+/// 查询字符串,以及位并行算法使用的每个字符在其中出现的位置的位图
+#[derive(Debug, Clone)]
+struct Pattern {
+    chars: Vec<char>,
+    peq: Option<Peq>,
+}
+
+#[derive(Debug, Clone)]
+struct Peq {
+    ascii: [u64; 128],
+    other: HashMap<char, u64>,
+}
+
+impl Peq {
+    fn get(&self, c: char) -> u64 {
+        if c.is_ascii() {
+            return self.ascii[c as usize];
+        }
+        self.other.get(&c).copied().unwrap_or(0)
+    }
+}
+
+impl Pattern {
+    fn new(chars: Vec<char>) -> Pattern {
+        let peq = (!chars.is_empty() && chars.len() <= WORD_BITS).then(|| {
+            let mut peq = Peq { ascii: [0; 128], other: HashMap::new() };
+            for (i, &c) in chars.iter().enumerate() {
+                if c.is_ascii() {
+                    peq.ascii[c as usize] |= 1 << i;
+                } else {
+                    *peq.other.entry(c).or_default() |= 1 << i;
+                }
+            }
+            peq
+        });
+        Pattern { chars, peq }
+    }
+
+    /// `anchored`为假时匹配可以从文本的任意位置开始,为真时必须从文本的开头开始
+    fn scanner(&self, anchored: bool) -> Scanner<'_> {
+        let len = self.chars.len();
+        match &self.peq {
+            Some(peq) => Scanner::Bits { peq, high: 1 << (len - 1), pv: !0, mv: 0, score: len, anchored },
+            None => Scanner::Table { chars: &self.chars, column: (0..=len).collect(), anchored },
+        }
+    }
+}
+
+/// 逐个字符读入文本,给出以刚读入的字符结尾的文本和查询字符串的最小编辑距离
+enum Scanner<'p> {
+    /// Myers算法:用位向量保存动态规划表中一列相邻两格的差(只可能是-1、0、1)
+    Bits { peq: &'p Peq, high: u64, pv: u64, mv: u64, score: usize, anchored: bool },
+    /// 动态规划表的当前一列,`column[i]`是查询字符串的前`i`个字符和文本的编辑距离
+    Table { chars: &'p [char], column: Vec<usize>, anchored: bool },
+}
+
+impl Scanner<'_> {
+    fn step(&mut self, c: char) -> usize {
+        match self {
+            Scanner::Bits { peq, high, pv, mv, score, anchored } => {
+                let eq = peq.get(c);
+                let xv = eq | *mv;
+                let xh = ((eq & *pv).wrapping_add(*pv) ^ *pv) | eq;
+                let mut ph = *mv | !(xh | *pv);
+                let mut mh = *pv & xh;
+                if ph & *high != 0 {
+                    *score += 1;
+                } else if mh & *high != 0 {
+                    *score -= 1;
+                }
+                // 第0行:从任意位置开始时都是0,从开头开始时每列加1
+                ph = (ph << 1) | *anchored as u64;
+                mh <<= 1;
+                *pv = mh | !(xv | ph);
+                *mv = ph & xv;
+                *score
+            }
+            Scanner::Table { chars, column, anchored } => {
+                let mut diagonal = column[0];
+                if *anchored {
+                    column[0] += 1;
+                }
+                for i in 1..column.len() {
+                    let above = column[i];
+                    column[i] = (above + 1).min(column[i - 1] + 1).min(diagonal + (chars[i - 1] != c) as usize);
+                    diagonal = above;
+                }
+                column[column.len() - 1]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::hash::{DefaultHasher, Hash, Hasher};
+
+    /// 用动态规划逐个位置计算的结果
+    fn table_scores(pattern: &[char], text: &str, anchored: bool) -> Vec<usize> {
+        let mut scanner = Scanner::Table { chars: pattern, column: (0..=pattern.len()).collect(), anchored };
+        text.chars().map(|c| scanner.step(c)).collect()
+    }
+
+    #[test]
+    // 位并行算法和动态规划的结果相同
+    fn bits_match_table() {
+        let alphabet: Vec<char> = "abcé中".chars().collect();
+        // 固定键的SipHash对`(种子, 位置)`的哈希值决定每个字符,每次运行得到相同的字符串
+        let random_string = |seed: (usize, usize, &str), len: usize| -> String {
+            (0..len)
+                .map(|i| {
+                    let mut hasher = DefaultHasher::new();
+                    (seed, i).hash(&mut hasher);
+                    alphabet[hasher.finish() as usize % alphabet.len()]
+                })
+                .collect()
+        };
+        for len in [1, 2, 5, 17, 63, 64] {
+            for round in 0..20 {
+                let pattern = Pattern::new(random_string((len, round, "pattern"), len).chars().collect());
+                let text = random_string((len, round, "text"), 100);
+                for anchored in [false, true] {
+                    let mut scanner = pattern.scanner(anchored);
+                    let bits: Vec<usize> = text.chars().map(|c| scanner.step(c)).collect();
+                    assert_eq!(table_scores(&pattern.chars, &text, anchored), bits);
+                }
+            }
+        }
+    }
+
+    #[test]
+    // 最小编辑距离,替换、插入、删除各算一次,交换相邻字符算两次
+    fn distances() {
+        let fuzzy = Fuzzy::new("receive", 2, true);
+        assert_eq!(Some(0), fuzzy.distance("fn receive()"));
+        assert_eq!(Some(1), fuzzy.distance("fn receve()"));
+        assert_eq!(Some(2), fuzzy.distance("fn recieve()"));
+        assert_eq!(None, fuzzy.distance("fn reply()"));
+        assert_eq!(Some(1), Fuzzy::new("straße", 1, true).distance("STRASSE strase"));
+        assert_eq!(Some(0), Fuzzy::new("STRAẞE", 0, false).distance("straße"));
+
+        // 超过64个字符时用动态规划
+        let long = "a".repeat(70);
+        let text = format!("x{}y{}x", "a".repeat(30), "a".repeat(39));
+        assert_eq!(Some(1), Fuzzy::new(&long, 1, true).distance(&text));
+        assert_eq!(None, Fuzzy::new(&long, 0, true).distance(&text));
+    }
+
+    #[test]
+    // 匹配的范围取距离最小、最短的子串
+    fn spans() {
+        let fuzzy = Fuzzy::new("needle", 1, true);
+        assert_eq!(vec![4..10, 15..20], fuzzy.find_spans("hay needle hay nedle hay"));
+        assert_eq!(vec![0..6], fuzzy.find_spans("needles"));
+        assert!(fuzzy.find_spans("haystack").is_empty());
+        assert_eq!(vec![3..9], Fuzzy::new("naïve", 1, false).find_spans("so NAÏVE!"));
+        assert_eq!(vec![1..7], fuzzy.find_spans_bytes(b"\xffneedle\xfe"));
+        // 查询字符串的长度不超过最大距离时空行也匹配
+        assert_eq!(Some(2), Fuzzy::new("xy", 2, true).distance_bytes(b""));
+        assert_eq!(vec![0..0], Fuzzy::new("xy", 2, true).find_spans_bytes(b""));
+        assert_eq!(None, fuzzy.distance_bytes(b""));
+    }
+}
@@ -0,0 +1,136 @@
+//! `--json`的输出格式,和ripgrep的JSON Lines格式相同:每行一个对象,`type`是记录的类型,`data`是内容.
+//! 每个有匹配的文件依次输出`begin`、若干`match`和`context`、`end`,所有文件搜索完后输出一个`summary`.
+//! 路径和行的内容是合法的UTF-8时写成`{"text": ...}`,否则写成`{"bytes": base64}`
+use std::ops::{AddAssign, Range};
+use std::time::Duration;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+use crate::walk::FileEntry;
+
+/// 搜索的统计数据,`end`记录中是一个文件的,`summary`记录中是所有文件的总和
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Stats {
+    pub(crate) searches: u64,
+    pub(crate) searches_with_match: u64,
+    pub(crate) bytes_searched: u64,
+    pub(crate) matched_lines: u64,
+    pub(crate) matches: u64,
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Stats) {
+        self.searches += other.searches;
+        self.searches_with_match += other.searches_with_match;
+        self.bytes_searched += other.bytes_searched;
+        self.matched_lines += other.matched_lines;
+        self.matches += other.matches;
+    }
+}
+
+/// 一个文件的第一个匹配之前输出
+pub(crate) fn begin(path: &Value) -> Value {
+    json!({ "type": "begin", "data": { "path": path } })
+}
+
+/// 匹配行(`selected`为真)或上下文行.`raw`包括行尾的换行符,`spans`是去掉换行符之后这一行中匹配的范围,
+/// `offset`是这一行在文件中的字节偏移量
+pub(crate) fn line(path: &Value, selected: bool, number: usize, offset: u64, raw: &[u8], spans: &[Range<usize>]) -> Value {
+    let submatches: Vec<Value> = spans
+        .iter()
+        .map(|span| json!({ "match": data(&raw[span.clone()]), "start": span.start, "end": span.end }))
+        .collect();
+    json!({
+        "type": if selected { "match" } else { "context" },
+        "data": {
+            "path": path,
+            "lines": data(raw),
+            "line_number": number,
+            "absolute_offset": offset,
+            "submatches": submatches,
+        },
+    })
+}
+
+/// 一个文件搜索结束,`binary_offset`是发现NUL字节的位置
+pub(crate) fn end(path: &Value, binary_offset: Option<u64>, stats: &Stats, elapsed: Duration) -> Value {
+    json!({
+        "type": "end",
+        "data": { "path": path, "binary_offset": binary_offset, "stats": stats_value(stats, elapsed) },
+    })
+}
+
+/// 所有文件搜索结束
+pub(crate) fn summary(stats: &Stats, elapsed_total: Duration) -> Value {
+    json!({
+        "type": "summary",
+        "data": { "elapsed_total": duration(elapsed_total), "stats": stats_value(stats, elapsed_total) },
+    })
+}
+
+/// 文件的路径,标准输入写成`<stdin>`
+pub(crate) fn path(file: &FileEntry) -> Value {
+    if file.is_stdin() {
+        return data(b"<stdin>");
+    }
+    data(file.path.as_os_str().as_encoded_bytes())
+}
+
+fn data(bytes: &[u8]) -> Value {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => json!({ "text": text }),
+        Err(_) => json!({ "bytes": STANDARD.encode(bytes) }),
+    }
+}
+
+fn stats_value(stats: &Stats, elapsed: Duration) -> Value {
+    json!({
+        "elapsed": duration(elapsed),
+        "searches": stats.searches,
+        "searches_with_match": stats.searches_with_match,
+        "bytes_searched": stats.bytes_searched,
+        "matched_lines": stats.matched_lines,
+        "matches": stats.matches,
+    })
+}
+
+fn duration(elapsed: Duration) -> Value {
+    json!({
+        "secs": elapsed.as_secs(),
+        "nanos": elapsed.subsec_nanos(),
+        "human": format!("{:.6}s", elapsed.as_secs_f64()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // 匹配的范围按字节计算,不是合法UTF-8的内容用base64表示
+    fn line_records() {
+        let path = data(b"log");
+        let record = line(&path, true, 3, 42, "ERROR é ERROR\n".as_bytes(), &[0..5, 9..14]);
+        assert_eq!(
+            json!({
+                "type": "match",
+                "data": {
+                    "path": { "text": "log" },
+                    "lines": { "text": "ERROR é ERROR\n" },
+                    "line_number": 3,
+                    "absolute_offset": 42,
+                    "submatches": [
+                        { "match": { "text": "ERROR" }, "start": 0, "end": 5 },
+                        { "match": { "text": "ERROR" }, "start": 9, "end": 14 },
+                    ],
+                },
+            }),
+            record
+        );
+
+        let record = line(&path, false, 1, 0, b"caf\xe9\n", &[]);
+        assert_eq!("context", record["type"]);
+        assert_eq!(json!({ "bytes": "Y2Fm6Qo=" }), record["data"]["lines"]);
+        assert_eq!(json!([]), record["data"]["submatches"]);
+    }
+}
@@ -1,83 +1,297 @@
+use std::collections::HashMap;
 use std::error::Error;
-use std::{env, fs};
-use std::env::Args;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+use memchr::memmem::{self, Finder};
+use regex::bytes::{Captures, Regex};
+use casefold::CaseInsensitive;
+use fuzzy::Fuzzy;
+use searcher::{SearchError, Searcher};
+use walk::FileEntry;
+
+pub mod casefold;
+pub mod config;
+pub mod fuzzy;
+mod json;
+mod replace;
+mod searcher;
+pub mod walk;
+pub use config::{ArgsError, ColorChoice, Config};
 
 pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    let content :String = fs::read_to_string(config.filename)?;
+    match run_to(&config, &mut io::stdout().lock()) {
+        // 下游提前关闭了管道(例如`| head`),不算错误
+        Err(err) if err.downcast_ref::<io::Error>().is_some_and(|err| err.kind() == io::ErrorKind::BrokenPipe) => Ok(()),
+        result => result,
+    }
+}
 
-    let results: Vec<&str> = if config.case_sensitive {
-        search(&config.query, &content)
-    } else {
-        search_case_insensitive(&config.query, &content)
-    };
+/// 按配置搜索并把结果写入`out`
+/// 文件按行读取.单线程搜索时匹配的行一边找到一边输出,内存占用和文件大小无关(`--fuzzy`和`--in-place`除外).
+/// 有多个文件时由多个线程并行搜索,每个文件的全部输出先写入各自的缓冲区,再按遍历的顺序输出,所以输出和单线程时完全相同;
+/// 代价是内存占用和单个文件的输出大小成正比,而且排在后面的文件先搜索完时要一直暂存到前面的文件输出之后.
+/// 匹配的行非常多时可以用`--threads=1`逐行输出
+/// `--json`时最后输出所有文件的统计数据
+pub fn run_to<W: Write>(config: &Config, out: &mut W) -> Result<(), Box<dyn Error>> {
+    let started = Instant::now();
+    let searcher = Searcher::new(config)?;
 
-    for line in results {
-        println!("{}", line);
+    // 某个文件出错时继续搜索其余的文件,最后再报告
+    let walked = walk::files(config)?;
+    for err in &walked.errors {
+        eprintln!("minigrep: {}", err);
     }
+    let mut failures = walked.errors.len();
 
+    if config.threads() == 1 || walked.files.len() <= 1 {
+        let mut separate = false;
+        for file in &walked.files {
+            match searcher.search_file(file, separate, out) {
+                Ok(printed) => separate |= printed,
+                Err(SearchError::Read(message)) => {
+                    eprintln!("minigrep: {}", message);
+                    failures += 1;
+                }
+                Err(SearchError::Write(err)) => return Err(err.into()),
+            }
+        }
+    } else {
+        failures += search_parallel(config, &searcher, &walked.files, out)?;
+    }
+    searcher.write_summary(out, started.elapsed())?;
+    out.flush()?;
+
+    if failures > 0 {
+        return Err(format!("{} path(s) could not be searched", failures).into());
+    }
     Ok(())
 }
 
-pub struct Config {
-    pub query: String,
-    pub filename: String,
-    pub case_sensitive: bool,
-}
+/// 多个线程并行搜索,按文件的顺序输出,返回出错的文件数
+fn search_parallel<W: Write>(config: &Config, searcher: &Searcher, files: &[FileEntry], out: &mut W) -> io::Result<usize> {
+    let next = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        // 接收端在这个闭包返回时被丢弃,输出出错时工作线程随之停止
+        let (sender, receiver) = mpsc::channel();
+        for _ in 0..config.threads().min(files.len()) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(file) = files.get(index) else { break };
+                let mut output = Vec::new();
+                let result = searcher.search_file(file, false, &mut output).map(|printed| (printed, output));
+                if sender.send((index, result)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
 
-impl Config {
-    // 此处使用迭代器 由于需要在函数体内调用next()方法 所以需要使用mut
-    pub fn new(mut args: Args) -> Result<Config, &'static str> {
-        if args.len() < 3 {
-            return Err("not enough arguments");
+        // 先完成的文件暂存起来,等前面的文件都输出之后再输出
+        let mut pending = HashMap::new();
+        let mut next_output = 0;
+        let mut failures = 0;
+        let mut separate = false;
+        for (index, result) in receiver {
+            pending.insert(index, result);
+            while let Some(result) = pending.remove(&next_output) {
+                next_output += 1;
+                match result {
+                    Ok((printed, output)) => {
+                        // 不同文件的上下文组之间也要分隔
+                        if printed && separate {
+                            searcher.write_group_separator(out)?;
+                        }
+                        separate |= printed;
+                        out.write_all(&output)?;
+                    }
+                    Err(err) => {
+                        eprintln!("minigrep: {}", err);
+                        failures += 1;
+                    }
+                }
+            }
         }
+        Ok(failures)
+    })
+}
 
-        // 跳过第1个参数
-        args.next();
+/// 匹配的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// 行的下标(从0开始)
+    pub line: usize,
+    /// 每一处匹配在这一行中的字节范围,按位置排列,互不重叠
+    pub spans: Vec<Range<usize>>,
+    /// 和查询字符串的编辑距离,只有近似匹配时才可能不是0
+    pub distance: usize,
+}
 
-        // 取第2和第3个参数
-        let query = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
-        };
+/// 在一行中查找匹配的方式,`search_with`和命令行的搜索都用它逐行搜索.
+/// 行是原始的字节,不要求是合法的UTF-8
+pub trait Matcher {
+    /// 一行中所有不重叠的匹配的字节范围,按位置排列,没有匹配时返回空的`Vec`
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>>;
 
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a file name"),
-        };
+    /// 一行中是否有匹配,默认实现会找出所有匹配,能在找到第一处时就返回的实现应该覆盖它
+    fn is_match(&self, line: &[u8]) -> bool {
+        !self.find_spans(line).is_empty()
+    }
 
-        let case_sensitive = env::var("CASE_INSENSITIVE").is_err();
+    /// 有匹配的一行和查询字符串的编辑距离,精确匹配总是0
+    fn edit_distance(&self, _line: &[u8]) -> usize {
+        0
+    }
+}
 
-        let config: Config = Config {
-            query,
-            filename,
-            case_sensitive,
-        };
+/// 按字面查找子串
+impl Matcher for str {
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>> {
+        Finder::new(self).find_spans(line)
+    }
 
-        Ok(config)
+    fn is_match(&self, line: &[u8]) -> bool {
+        memmem::find(line, self.as_bytes()).is_some()
     }
 }
 
-pub fn search<'a>(query: &str, content: &'a str) -> Vec<&'a str> {
-    let filter_closure = |line: &&str| -> bool {
-        line.contains(query)
-    };
+impl Matcher for Finder<'_> {
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>> {
+        self.find_iter(line).map(|start| start..start + self.needle().len()).collect()
+    }
 
-    content.lines().filter(filter_closure).collect()
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.find(line).is_some()
+    }
 }
 
-pub fn search_case_insensitive<'a>(query: &str, content: &'a str) -> Vec<&'a str> {
-    let lower_query: String = query.to_lowercase();
+impl Matcher for CaseInsensitive {
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>> {
+        self.find_iter_bytes(line).collect()
+    }
 
-    let filter_closure = |line: &&str| -> bool {
-        line.to_lowercase().contains(&lower_query)
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.is_match_bytes(line)
+    }
+}
+
+impl Matcher for Regex {
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>> {
+        self.find_iter(line).map(|found| found.range()).collect()
+    }
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        Regex::is_match(self, line)
+    }
+}
+
+impl Matcher for Fuzzy {
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>> {
+        self.find_spans_bytes(line)
+    }
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        self.distance_bytes(line).is_some()
+    }
+
+    fn edit_distance(&self, line: &[u8]) -> usize {
+        self.distance_bytes(line).unwrap_or(0)
+    }
+}
+
+/// 返回所有匹配的行以及匹配的位置
+pub fn search(query: &str, content: &str) -> Vec<LineMatch> {
+    search_with(query, content)
+}
+
+/// 忽略大小写搜索,按Unicode简单大小写折叠比较,不会为每一行分配内存
+pub fn search_case_insensitive(query: &str, content: &str) -> Vec<LineMatch> {
+    search_with(&CaseInsensitive::new(query), content)
+}
+
+pub fn search_regex(regex: &Regex, content: &str) -> Vec<LineMatch> {
+    search_with(regex, content)
+}
+
+/// 近似搜索:返回含有和`query`的编辑距离不超过`max_distance`的子串的行,
+/// 按编辑距离从小到大排列,距离相同时保持行的顺序
+pub fn search_fuzzy(query: &str, max_distance: usize, content: &str) -> Vec<LineMatch> {
+    let mut matches = search_with(&Fuzzy::new(query, max_distance, true), content);
+    matches.sort_by_key(|found| found.distance);
+    matches
+}
+
+/// 用`matcher`逐行搜索,保留至少有一处匹配的行.没有匹配时`spans`是空的`Vec`,不会分配内存
+pub fn search_with<M: Matcher + ?Sized>(matcher: &M, content: &str) -> Vec<LineMatch> {
+    let matched_closure = |(line, text): (usize, &str)| -> Option<LineMatch> {
+        let spans = matcher.find_spans(text.as_bytes());
+        if spans.is_empty() {
+            return None;
+        }
+        Some(LineMatch { line, spans, distance: matcher.edit_distance(text.as_bytes()) })
     };
 
-    content.lines().filter(filter_closure).collect()
+    content.lines().enumerate().filter_map(matched_closure).collect()
+}
+
+/// 取出每一行中所有匹配的部分
+/// 正则表达式中有捕获组时只取出捕获组的内容,多个捕获组之间以制表符分隔,没有参与匹配的捕获组为空字符串
+pub fn only_matching(regex: &Regex, content: &str) -> Vec<String> {
+    content
+        .lines()
+        .flat_map(|line| matches(regex, line.as_bytes(), false))
+        .map(|captures| String::from_utf8_lossy(&matched_part(&captures)).into_owned())
+        .collect()
+}
+
+/// `--only-matching`输出的一处匹配:有捕获组时是以制表符分隔的各个捕获组,否则是整个匹配
+pub(crate) fn matched_part(captures: &Captures) -> Vec<u8> {
+    if captures.len() == 1 {
+        return captures[0].to_vec();
+    }
+    let groups: Vec<&[u8]> = captures.iter().skip(1).map(|group| group.map_or(&b""[..], |group| group.as_bytes())).collect();
+    groups.join(&b'\t')
+}
+
+/// 一行中所有的匹配,`word`为真时只保留前后都不是单词字符的匹配.行是原始的字节,不要求是合法的UTF-8
+pub fn matches<'r, 'a>(regex: &'r Regex, line: &'a [u8], word: bool) -> impl Iterator<Item = Captures<'a>> + 'r
+where
+    'a: 'r,
+{
+    regex.captures_iter(line).filter(move |captures| {
+        let whole = captures.get(0).unwrap();
+        !word || is_word_bounded(line, whole.start(), whole.end())
+    })
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// `line[start..end]`前后的字符都不是单词字符,不是合法UTF-8的字节也不算单词字符
+fn is_word_bounded(line: &[u8], start: usize, end: usize) -> bool {
+    // 一个字符最多占4个字节
+    let before = (1..=start.min(4)).find_map(|len| std::str::from_utf8(&line[start - len..start]).ok());
+    let after = (1..=(line.len() - end).min(4)).find_map(|len| std::str::from_utf8(&line[end..end + len]).ok());
+    let is_word = |s: Option<&str>| s.and_then(|s| s.chars().next()).is_some_and(is_word_char);
+    !is_word(before) && !is_word(after)
 }
 
 #[cfg(test)]
+// 一行中只有一处匹配时`spans`是只有一个范围的`Vec`
+#[allow(clippy::single_range_in_vec_init)]
 mod tests {
     use super::*;
+    use std::fs;
+
+    fn lines(matches: &[LineMatch]) -> Vec<usize> {
+        matches.iter().map(|found| found.line).collect()
+    }
 
     #[test]
     // 大小写敏感的测试用例
@@ -88,7 +302,7 @@ Rust:
 safe, fast, productive.
 Pick three.
 Duct tape.";
-        assert_eq!(vec!["safe, fast, productive."], search(query, content));
+        assert_eq!(vec![LineMatch { line: 1, spans: vec![15..19], distance: 0 }], search(query, content));
     }
 
     #[test]
@@ -100,6 +314,150 @@ Rust:
 safe, fast, productive.
 Pick three.
 Trust me.";
-        assert_eq!(vec!["Rust:", "Trust me."], search_case_insensitive(query, content));
+        assert_eq!(
+            vec![
+                LineMatch { line: 0, spans: vec![0..4], distance: 0 },
+                LineMatch { line: 3, spans: vec![1..5], distance: 0 },
+            ],
+            search_case_insensitive(query, content)
+        );
+    }
+
+    #[test]
+    // 非ASCII文本忽略大小写
+    fn case_insensitive_unicode() {
+        let content = "\
+ΣΊΣΥΦΟΣ
+Straße und STRASSE
+ПРИВЕТ, мир
+\u{212A}ELVIN";
+        assert_eq!(vec![LineMatch { line: 0, spans: vec![0..14], distance: 0 }], search_case_insensitive("σίσυφος", content));
+        assert_eq!(vec![LineMatch { line: 1, spans: vec![0..7], distance: 0 }], search_case_insensitive("STRAẞE", content));
+        assert_eq!(vec![2], lines(&search_case_insensitive("привет, МИР", content)));
+        // 开尔文符号占3个字节
+        assert_eq!(vec![LineMatch { line: 3, spans: vec![0..8], distance: 0 }], search_case_insensitive("kelvin", content));
+    }
+
+    #[test]
+    // 近似搜索的结果按编辑距离排序
+    fn fuzzy_ranked() {
+        let content = "\
+fn recieve(buf: &mut [u8])
+fn receive(buf: &mut [u8])
+fn reply()
+let received = recv();";
+        let matches = search_fuzzy("receive", 2, content);
+        assert_eq!(vec![(1, 0), (3, 0), (0, 2)], matches.iter().map(|found| (found.line, found.distance)).collect::<Vec<_>>());
+        assert_eq!(vec![3..10], matches[0].spans);
+        assert!(search_fuzzy("receive", 1, content).iter().all(|found| found.distance == 0));
+    }
+
+    #[test]
+    // 正则表达式支持锚点和字符类
+    fn regex_anchors_and_classes() {
+        let content = "\
+ERROR 500 internal
+WARN 404 missing
+ERROR 5xx unknown
+  ERROR 503 indented";
+        let regex = Regex::new(r"^ERROR \d{3}").unwrap();
+        assert_eq!(vec![LineMatch { line: 0, spans: vec![0..9], distance: 0 }], search_regex(&regex, content));
+
+        let regex = Regex::new(r"[A-Z]+ 4\d\d").unwrap();
+        assert_eq!(vec![1], lines(&search_regex(&regex, content)));
+    }
+
+    #[test]
+    // 有捕获组时只输出捕获组的内容
+    fn only_matching_with_captures() {
+        let content = "\
+GET /index.html 200
+POST /login 302 GET /home 200";
+        let regex = Regex::new(r"\d{3}").unwrap();
+        assert_eq!(vec!["200", "302", "200"], only_matching(&regex, content));
+
+        let regex = Regex::new(r"(GET|POST) (/\S+)").unwrap();
+        assert_eq!(
+            vec!["GET\t/index.html", "POST\t/login", "GET\t/home"],
+            only_matching(&regex, content)
+        );
+    }
+
+    #[test]
+    // 整词匹配时前后紧挨着单词字符的匹配被忽略
+    fn word_matches() {
+        let regex = Regex::new("rust").unwrap();
+        let line = "trust rust rusty _rust rust.";
+        let starts: Vec<usize> = matches(&regex, line.as_bytes(), true)
+            .map(|captures| captures.get(0).unwrap().start())
+            .collect();
+        assert_eq!(vec![6, 23], starts);
+        assert_eq!(5, matches(&regex, line.as_bytes(), false).count());
+
+        // 非ASCII字母也是单词字符
+        assert_eq!(0, matches(&regex, "érust".as_bytes(), true).count());
+    }
+
+    #[test]
+    // 多线程搜索的输出和单线程相同,按文件分组并保持遍历的顺序
+    fn parallel_output_is_ordered() {
+        let dir = tempfile::tempdir().unwrap();
+        for i in 0..40 {
+            // 前面的文件更大,搜索完成的顺序和遍历顺序不一致
+            let content: String = (0..(40 - i) * 50).map(|line| format!("line {} of file {}\n", line, i)).collect();
+            fs::write(dir.path().join(format!("{:02}.txt", i)), content).unwrap();
+        }
+        let search = |threads: &str| {
+            let root = dir.path().display().to_string();
+            let args = ["minigrep", threads, "-E", r"line \d*7 ", &root].map(String::from);
+            let mut out = Vec::new();
+            run_to(&Config::new(args).unwrap(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let single = search("-j1");
+        assert_eq!(single, search("-j8"));
+        let files: Vec<&str> = single.lines().map(|line| line.split(':').next().unwrap()).collect();
+        assert!(files.first().unwrap().ends_with("00.txt"));
+        assert!(files.is_sorted());
+    }
+
+    #[test]
+    // --in-place改写有匹配的文件,--dry-run只输出diff而不修改文件
+    fn replace_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let changed = dir.path().join("a.txt");
+        let unchanged = dir.path().join("b.txt");
+        fs::write(&changed, "x = old(1)\nkeep\ny = old(2)").unwrap();
+        fs::write(&unchanged, "nothing here\n").unwrap();
+        let replace = |extra: &[&str]| {
+            let root = dir.path().display().to_string();
+            let mut args: Vec<&str> = vec!["minigrep", "-E", "-r", "new($1)", r"old\((\d)\)", "--in-place"];
+            args.extend(extra);
+            args.push(&root);
+            let mut out = Vec::new();
+            run_to(&Config::new(args.into_iter().map(String::from)).unwrap(), &mut out).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let diff = replace(&["--dry-run"]);
+        let name = changed.display();
+        let hunk = concat!(
+            "@@ -1,3 +1,3 @@\n",
+            "-x = old(1)\n",
+            "+x = new(1)\n",
+            " keep\n",
+            "-y = old(2)\n",
+            "\\ No newline at end of file\n",
+            "+y = new(2)\n",
+            "\\ No newline at end of file\n",
+        );
+        assert_eq!(format!("--- {name}\n+++ {name}\n{hunk}"), diff);
+        assert_eq!("x = old(1)\nkeep\ny = old(2)", fs::read_to_string(&changed).unwrap());
+
+        assert_eq!("", replace(&[]));
+        assert_eq!("x = new(1)\nkeep\ny = new(2)", fs::read_to_string(&changed).unwrap());
+        assert_eq!("nothing here\n", fs::read_to_string(&unchanged).unwrap());
+        assert_eq!(2, fs::read_dir(dir.path()).unwrap().count());
     }
-}
\ No newline at end of file
+}
@@ -1,20 +1,26 @@
-use minigrep::{Config, run};
+use minigrep::{ArgsError, Config, run};
+use minigrep::config::USAGE;
 use std::{env, process};
-use std::env::Args;
 
 fn main() {
-    let args: Args = env::args();
-
-    let config: Config = Config::new(args).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {}", err);
-        process::exit(1);
+    let config: Config = Config::new(env::args()).unwrap_or_else(|err| {
+        match err {
+            // 帮助和版本信息输出到标准输出,正常退出
+            ArgsError::Help | ArgsError::Version => {
+                println!("{}", err);
+                process::exit(0);
+            }
+            ArgsError::Usage(_) => {
+                eprintln!("Problem parsing arguments: {}", err);
+                eprintln!("{}", USAGE);
+                eprintln!("Try 'minigrep --help' for more information.");
+                process::exit(2);
+            }
+        }
     });
 
-    println!("Search for {}", config.query);
-    println!("In file {}", config.filename);
-
     if let Err(e) = run(config) {
         eprintln!("Application error: {}", e);
         process::exit(1);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,192 @@
+//! `--in-place`: 把替换后的内容写回文件,以及`--dry-run`时输出的unified diff
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use tempfile::NamedTempFile;
+
+/// diff中每处改动前后保留的上下文行数,和`diff -u`相同
+const CONTEXT: usize = 3;
+
+/// 文件中的一行:包括换行符在内的原始内容,以及被替换时替换后的内容
+/// 替换的内容中可以有换行符,所以替换后可能变成多行
+pub(crate) type ChangedLine<'a> = (&'a [u8], Option<Vec<u8>>);
+
+/// 替换后的文件内容
+pub(crate) fn new_content(lines: &[ChangedLine]) -> Vec<u8> {
+    lines.iter().flat_map(|(old, new)| new.as_deref().unwrap_or(old)).copied().collect()
+}
+
+/// 先写入同一目录下的临时文件,再改名覆盖原文件,写入过程中出错时原文件保持不变.
+/// `path`是符号链接时改写它指向的文件,新文件沿用原文件的权限
+pub(crate) fn write_atomically(path: &Path, content: &[u8]) -> io::Result<()> {
+    let path = fs::canonicalize(path)?;
+    let dir = path.parent().expect("a file always has a parent directory");
+    let mut temp = NamedTempFile::new_in(dir)?;
+    temp.write_all(content)?;
+    temp.as_file().sync_all()?;
+    fs::set_permissions(temp.path(), fs::metadata(&path)?.permissions())?;
+    temp.persist(&path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+/// 和`diff -u`相同格式的改动,相距不超过`2 * CONTEXT`行的改动合并在一个hunk中
+pub(crate) fn unified_diff(name: &str, lines: &[ChangedLine]) -> Vec<u8> {
+    let changed: Vec<usize> = (0..lines.len()).filter(|&i| lines[i].1.is_some()).collect();
+    let mut diff = Vec::new();
+    if changed.is_empty() {
+        return diff;
+    }
+    diff.extend_from_slice(format!("--- {}\n+++ {}\n", name, name).as_bytes());
+
+    // 之前的hunk中增加的行数,可能是负数
+    let mut delta = 0isize;
+    let mut first = 0;
+    while first < changed.len() {
+        let mut last = first;
+        while last + 1 < changed.len() && changed[last + 1] - changed[last] <= 2 * CONTEXT {
+            last += 1;
+        }
+        let start = changed[first].saturating_sub(CONTEXT);
+        let end = (changed[last] + CONTEXT + 1).min(lines.len());
+        let hunk = &lines[start..end];
+        let old_count = hunk.len();
+        let new_count: usize = hunk.iter().map(|(_, new)| new.as_deref().map_or(1, line_count)).sum();
+        let new_start = start as isize + delta;
+        diff.extend_from_slice(
+            format!("@@ -{} +{} @@\n", range(start, old_count), range(new_start as usize, new_count)).as_bytes(),
+        );
+
+        // 连续改动的行先输出所有删除的行,再输出所有增加的行
+        let mut i = 0;
+        while i < hunk.len() {
+            let run = hunk[i..].iter().take_while(|(_, new)| new.is_some()).count();
+            if run == 0 {
+                push_line(&mut diff, b' ', hunk[i].0);
+                i += 1;
+                continue;
+            }
+            for (old, _) in &hunk[i..i + run] {
+                push_line(&mut diff, b'-', old);
+            }
+            for new in hunk[i..i + run].iter().filter_map(|(_, new)| new.as_deref()) {
+                for line in new.split_inclusive(|&byte| byte == b'\n') {
+                    push_line(&mut diff, b'+', line);
+                }
+            }
+            i += run;
+        }
+
+        delta += new_count as isize - old_count as isize;
+        first = last + 1;
+    }
+    diff
+}
+
+/// hunk头部的行范围,`start`从0开始;只有一行时省略行数,没有行时起始行号是前一行
+fn range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+fn line_count(content: &[u8]) -> usize {
+    content.split_inclusive(|&byte| byte == b'\n').count()
+}
+
+fn push_line(diff: &mut Vec<u8>, marker: u8, line: &[u8]) {
+    diff.push(marker);
+    diff.extend_from_slice(line);
+    if !line.ends_with(b"\n") {
+        diff.extend_from_slice(b"\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changed_lines<'a>(content: &'a str, changes: &[(usize, &str)]) -> Vec<ChangedLine<'a>> {
+        content
+            .as_bytes()
+            .split_inclusive(|&byte| byte == b'\n')
+            .enumerate()
+            .map(|(i, line)| {
+                let new = changes.iter().find(|(index, _)| *index == i).map(|(_, new)| new.as_bytes().to_vec());
+                (line, new)
+            })
+            .collect()
+    }
+
+    #[test]
+    // 相近的改动合并成一个hunk,相距较远的分成两个,后面hunk的新行号计入前面增加的行
+    fn hunks() {
+        let content: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+        let lines = changed_lines(&content, &[(1, "LINE 2\n"), (4, "LINE 5a\nLINE 5b\n"), (16, "LINE 17\n")]);
+        let expected = "\
+--- f.txt
++++ f.txt
+@@ -1,8 +1,9 @@
+ line 1
+-line 2
++LINE 2
+ line 3
+ line 4
+-line 5
++LINE 5a
++LINE 5b
+ line 6
+ line 7
+ line 8
+@@ -14,7 +15,7 @@
+ line 14
+ line 15
+ line 16
+-line 17
++LINE 17
+ line 18
+ line 19
+ line 20
+";
+        assert_eq!(expected, String::from_utf8(unified_diff("f.txt", &lines)).unwrap());
+        assert!(new_content(&lines).starts_with(b"line 1\nLINE 2\nline 3\nline 4\nLINE 5a\nLINE 5b\nline 6\n"));
+
+        assert!(unified_diff("f.txt", &changed_lines(&content, &[])).is_empty());
+    }
+
+    #[test]
+    // 最后一行没有换行符
+    fn no_newline_at_end() {
+        let lines = changed_lines("a\nb", &[(1, "c")]);
+        assert_eq!(
+            "--- f\n+++ f\n@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n",
+            String::from_utf8(unified_diff("f", &lines)).unwrap()
+        );
+    }
+
+    #[test]
+    // 改名覆盖原文件,保留权限,符号链接仍然指向原来的文件
+    fn atomic_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file.txt");
+        fs::write(&path, "old\n").unwrap();
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+
+        write_atomically(&path, b"new\n").unwrap();
+        assert_eq!("new\n", fs::read_to_string(&path).unwrap());
+        assert!(fs::metadata(&path).unwrap().permissions().readonly());
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.txt");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            write_atomically(&link, b"newer\n").unwrap();
+            assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+            assert_eq!("newer\n", fs::read_to_string(&path).unwrap());
+        }
+    }
+}
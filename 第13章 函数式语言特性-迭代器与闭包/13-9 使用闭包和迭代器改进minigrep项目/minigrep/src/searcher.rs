@@ -0,0 +1,632 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use memchr::memchr;
+use memchr::memmem::Finder;
+use regex::bytes::{Captures, Regex};
+use serde_json::Value;
+use crate::json::{self, Stats};
+use crate::replace::{self, ChangedLine};
+use crate::casefold::CaseInsensitive;
+use crate::fuzzy::Fuzzy;
+use crate::walk::{self, FileEntry};
+use crate::{matched_part, matches, Config, Matcher};
+
+/// 分隔不相邻的上下文组
+const GROUP_SEPARATOR: &[u8] = b"--";
+
+/// 着色使用的ANSI转义序列,和grep的默认配色相同:匹配部分红色加粗,文件名紫色,行号绿色,分隔符青色
+const MATCH_COLOR: &[u8] = b"\x1b[1;31m";
+const PATH_COLOR: &[u8] = b"\x1b[35m";
+const LINE_NUMBER_COLOR: &[u8] = b"\x1b[32m";
+const SEPARATOR_COLOR: &[u8] = b"\x1b[36m";
+const RESET: &[u8] = b"\x1b[0m";
+
+/// 按配置选择查找方式:`--fuzzy`时近似匹配;正则表达式、只输出匹配部分、整词匹配和替换时用正则表达式查找;
+/// 否则按子串查找,不区分大小写时逐个字符做Unicode简单大小写折叠后比较
+fn line_matcher(config: &Config, regex: Option<&Regex>) -> Box<dyn Matcher + Send + Sync> {
+    if let Some(distance) = config.fuzzy {
+        return Box::new(Fuzzy::new(&config.query, distance, config.case_sensitive));
+    }
+    match regex {
+        Some(regex) if config.word_regexp => Box::new(WholeWord(regex.clone())),
+        Some(regex) => Box::new(regex.clone()),
+        None if config.case_sensitive => Box::new(Finder::new(config.query.as_bytes()).into_owned()),
+        None => Box::new(CaseInsensitive::new(&config.query)),
+    }
+}
+
+/// 整词匹配:只保留前后都不是单词字符的匹配
+struct WholeWord(Regex);
+
+impl Matcher for WholeWord {
+    fn find_spans(&self, line: &[u8]) -> Vec<Range<usize>> {
+        matches(&self.0, line, true).map(|captures| captures.get(0).unwrap().range()).collect()
+    }
+
+    fn is_match(&self, line: &[u8]) -> bool {
+        matches(&self.0, line, true).next().is_some()
+    }
+}
+
+/// 搜索一个文件时的错误:读取(或者`--in-place`改写)文件出错只影响这个文件,写入输出出错则停止整个搜索
+#[derive(Debug)]
+pub(crate) enum SearchError {
+    Read(String),
+    Write(io::Error),
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::Read(message) => write!(f, "{}", message),
+            SearchError::Write(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for SearchError {
+    fn from(err: io::Error) -> SearchError {
+        SearchError::Write(err)
+    }
+}
+
+/// 按配置搜索单个文件并输出结果,可以在多个线程间共享
+pub(crate) struct Searcher<'a> {
+    config: &'a Config,
+    matcher: Box<dyn Matcher + Send + Sync>,
+    /// 需要正则表达式时(见`Config::needs_regex`)编译好的正则表达式,`--replace`和`--only-matching`直接使用它
+    regex: Option<Regex>,
+    /// 每行前是否输出文件名、行号,以及是否高亮
+    with_filename: bool,
+    line_number: bool,
+    color: bool,
+    /// 是否输出上下文,只输出匹配部分、行数或文件名时不输出
+    context: bool,
+    /// `--json`: 输出JSON记录,所有文件的统计数据累加到`totals`
+    json: bool,
+    totals: Mutex<Stats>,
+}
+
+impl<'a> Searcher<'a> {
+    pub(crate) fn new(config: &'a Config) -> Result<Searcher<'a>, regex::Error> {
+        // 搜索目录或多个文件时和grep -rn一样,每行前输出文件名和行号
+        let recursive = config.paths.iter().any(|path| Path::new(path).is_dir());
+        let regex = if config.needs_regex() { Some(config.build_regex()?) } else { None };
+        Ok(Searcher {
+            config,
+            matcher: line_matcher(config, regex.as_ref()),
+            regex,
+            with_filename: recursive || config.paths.len() > 1,
+            line_number: config.line_number || recursive,
+            color: config.color.enabled() && !config.json,
+            context: (config.before_context > 0 || config.after_context > 0)
+                && !(config.only_matching || config.count || config.files_with_matches),
+            json: config.json,
+            totals: Mutex::new(Stats::default()),
+        })
+    }
+
+    /// 搜索一个文件或标准输入,结果写入`out`
+    /// `separate`表示之前已经输出过上下文组,这个文件的第一组之前也要输出分隔符;返回这个文件是否输出了上下文组
+    pub(crate) fn search_file<W: Write>(&self, file: &FileEntry, separate: bool, out: &mut W) -> Result<bool, SearchError> {
+        if self.config.in_place {
+            return self.replace_file(file, out).map(|_| false);
+        }
+        if file.is_stdin() {
+            return self.search_reader(file, self.decode(io::stdin().lock()), separate, out);
+        }
+        let reader = File::open(&file.path).map_err(|err| SearchError::Read(format!("{}: {}", file.name(), err)))?;
+        self.search_reader(file, self.decode(reader), separate, out)
+    }
+
+    /// `--in-place`: 替换文件中所有的匹配后写回,原子地替换原文件;`--dry-run`时不修改文件,而是把改动以unified diff的格式写入`out`.
+    /// 整个文件读入内存,二进制文件不做修改.返回文件是否有改动
+    fn replace_file<W: Write>(&self, file: &FileEntry, out: &mut W) -> Result<bool, SearchError> {
+        let name = file.name();
+        let file_error = |err: io::Error| SearchError::Read(format!("{}: {}", name, err));
+        let content = fs::read(&file.path).map_err(file_error)?;
+        if !self.config.text && walk::is_binary(&content) {
+            return Ok(false);
+        }
+
+        let lines: Vec<ChangedLine> = content
+            .split_inclusive(|&byte| byte == b'\n')
+            .map(|raw| {
+                let (text, terminator) = split_terminator(raw);
+                let new = self.replace(text).map(|(mut replaced, _)| {
+                    replaced.extend_from_slice(terminator);
+                    replaced
+                });
+                (raw, new)
+            })
+            .collect();
+        if lines.iter().all(|(_, new)| new.is_none()) {
+            return Ok(false);
+        }
+        if self.config.dry_run {
+            out.write_all(&replace::unified_diff(&name, &lines))?;
+        } else {
+            replace::write_atomically(&file.path, &replace::new_content(&lines)).map_err(file_error)?;
+        }
+        Ok(true)
+    }
+
+    /// `--replace`: 一行中所有匹配替换后的内容,以及替换进去的部分在结果中的范围(用于高亮),没有匹配时返回`None`.
+    /// 正则表达式模式下替换内容中的`$1`、`${name}`引用捕获组,否则按字面替换
+    fn replace(&self, line: &[u8]) -> Option<(Vec<u8>, Vec<Range<usize>>)> {
+        let replacement = self.config.replace.as_ref()?;
+        let regex = self.regex.as_ref()?;
+        let mut replaced = Vec::with_capacity(line.len());
+        let mut inserted = Vec::new();
+        let mut last = 0;
+        for captures in matches(regex, line, self.config.word_regexp) {
+            let whole = captures.get(0).unwrap();
+            replaced.extend_from_slice(&line[last..whole.start()]);
+            let start = replaced.len();
+            self.expand(&captures, replacement, &mut replaced);
+            inserted.push(start..replaced.len());
+            last = whole.end();
+        }
+        if inserted.is_empty() {
+            return None;
+        }
+        replaced.extend_from_slice(&line[last..]);
+        Some((replaced, inserted))
+    }
+
+    /// 把一处匹配替换后的内容追加到`dst`
+    fn expand(&self, captures: &Captures, replacement: &str, dst: &mut Vec<u8>) {
+        if self.config.regex {
+            captures.expand(replacement.as_bytes(), dst);
+        } else {
+            dst.extend_from_slice(replacement.as_bytes());
+        }
+    }
+
+    /// 按`--encoding`把输入转码成UTF-8;没有指定编码时只在开头有UTF-16的BOM时转码,其余字节原样保留
+    fn decode<R: Read>(&self, reader: R) -> impl BufRead {
+        let decoder = DecodeReaderBytesBuilder::new()
+            .encoding(self.config.encoding)
+            .utf8_passthru(true)
+            .strip_bom(true)
+            .build(reader);
+        BufReader::new(decoder)
+    }
+
+    /// 逐行搜索`reader`,找到匹配的行就写入`out`
+    /// 上下文只保留最近的`before_context`行,重叠或相邻的上下文合并成一组
+    fn search_reader<R: BufRead, W: Write>(
+        &self,
+        file: &FileEntry,
+        mut reader: R,
+        separate: bool,
+        out: &mut W,
+    ) -> Result<bool, SearchError> {
+        let started = Instant::now();
+        let config = self.config;
+        let source = Source { name: file.name(), path: self.json.then(|| json::path(file)) };
+        let read_error = |err: io::Error| SearchError::Read(format!("{}: {}", source.name, err));
+        // 开头或者之后的某一行含有NUL时当作二进制文件.遍历目录时跳过二进制文件,
+        // 直接给出的二进制文件照常搜索,但只输出一句提示(JSON输出中是`end`记录的`binary_offset`)而不输出匹配的行
+        let head = reader.fill_buf().map_err(read_error)?;
+        let mut binary_offset = if !config.text && walk::is_binary(head) {
+            memchr(0, head).map(|at| at as u64)
+        } else {
+            None
+        };
+        if binary_offset.is_some() && !file.explicit {
+            return Ok(false);
+        }
+
+        let mut stats = Stats { searches: 1, ..Stats::default() };
+        let mut buf = Vec::new();
+        let mut line_number = 0;
+        let mut offset = 0;
+        // 还没输出的前文,以及匹配行之后还要输出的后文行数
+        let mut before: VecDeque<(usize, u64, Vec<u8>)> = VecDeque::with_capacity(config.before_context);
+        let mut after = 0;
+        let mut last_printed = None;
+        // `--fuzzy`: 匹配的行先暂存起来,读完文件后按编辑距离排序输出
+        let mut ranked: Vec<(usize, usize, u64, Vec<u8>)> = Vec::new();
+        loop {
+            buf.clear();
+            let read = reader.read_until(b'\n', &mut buf).map_err(read_error)?;
+            if read == 0 {
+                break;
+            }
+            line_number += 1;
+            let line = Line { number: line_number, offset, raw: &buf };
+            offset += read as u64;
+            let text = line.text();
+            if binary_offset.is_none() && !config.text {
+                if let Some(at) = memchr(0, text) {
+                    binary_offset = Some(line.offset + at as u64);
+                    if !file.explicit {
+                        break;
+                    }
+                }
+            }
+
+            if self.matcher.is_match(text) == config.invert_match {
+                if !self.context {
+                    continue;
+                }
+                if after > 0 {
+                    after -= 1;
+                    self.write_line(out, &source, line, false)?;
+                    last_printed = Some(line_number);
+                } else if config.before_context > 0 {
+                    if before.len() == config.before_context {
+                        before.pop_front();
+                    }
+                    before.push_back((line.number, line.offset, line.raw.to_vec()));
+                }
+                continue;
+            }
+
+            stats.matched_lines += 1;
+            if config.files_with_matches {
+                self.write_colored(out, PATH_COLOR, source.name.as_bytes())?;
+                out.write_all(b"\n")?;
+                return Ok(false);
+            }
+            if config.count {
+                continue;
+            }
+            if stats.matched_lines == 1 {
+                if let Some(path) = &source.path {
+                    write_json(out, &json::begin(path))?;
+                }
+            }
+            if binary_offset.is_some() {
+                if source.path.is_none() {
+                    writeln!(out, "Binary file {} matches", source.name)?;
+                    return Ok(false);
+                }
+                break;
+            }
+            if config.fuzzy.is_some() {
+                ranked.push((self.matcher.edit_distance(text), line.number, line.offset, line.raw.to_vec()));
+                continue;
+            }
+            if self.context {
+                // 和上一组之间有间隔时输出分隔符
+                let first = before.front().map_or(line_number, |(number, _, _)| *number);
+                let gap = match last_printed {
+                    Some(last) => first > last + 1,
+                    None => separate,
+                };
+                if gap {
+                    self.write_group_separator(out)?;
+                }
+                for (number, offset, raw) in before.drain(..) {
+                    self.write_line(out, &source, Line { number, offset, raw: &raw }, false)?;
+                }
+                after = config.after_context;
+                last_printed = Some(line_number);
+            }
+            stats.matches += self.write_line(out, &source, line, true)? as u64;
+        }
+
+        // 稳定排序,距离相同的行保持原来的顺序
+        ranked.sort_by_key(|(distance, ..)| *distance);
+        for (_, number, offset, raw) in ranked {
+            stats.matches += self.write_line(out, &source, Line { number, offset, raw: &raw }, true)? as u64;
+        }
+
+        if config.count {
+            if self.with_filename {
+                self.write_colored(out, PATH_COLOR, source.name.as_bytes())?;
+                self.write_colored(out, SEPARATOR_COLOR, b":")?;
+            }
+            writeln!(out, "{}", stats.matched_lines)?;
+        }
+        if let Some(path) = &source.path {
+            stats.bytes_searched = offset;
+            stats.searches_with_match = (stats.matched_lines > 0) as u64;
+            if stats.matched_lines > 0 {
+                write_json(out, &json::end(path, binary_offset, &stats, started.elapsed()))?;
+            }
+            *self.totals.lock().unwrap() += stats;
+        }
+        Ok(last_printed.is_some())
+    }
+
+    /// 输出一行,和grep一样匹配行的文件名、行号之后是`:`,上下文行之后是`-`.
+    /// 返回这一行中匹配的个数,只在输出JSON时计算,否则总是0
+    fn write_line<W: Write>(&self, out: &mut W, source: &Source, line: Line, selected: bool) -> io::Result<usize> {
+        let text = line.text();
+        if let Some(path) = &source.path {
+            let spans = self.matcher.find_spans(text);
+            write_json(out, &json::line(path, selected, line.number, line.offset, line.raw, &spans))?;
+            return Ok(spans.len());
+        }
+
+        let separator: &[u8] = if selected { b":" } else { b"-" };
+        let mut prefix = Vec::new();
+        if self.with_filename {
+            self.write_colored(&mut prefix, PATH_COLOR, source.name.as_bytes())?;
+            self.write_colored(&mut prefix, SEPARATOR_COLOR, separator)?;
+        }
+        if self.line_number {
+            self.write_colored(&mut prefix, LINE_NUMBER_COLOR, line.number.to_string().as_bytes())?;
+            self.write_colored(&mut prefix, SEPARATOR_COLOR, separator)?;
+        }
+
+        let config = self.config;
+        match &self.regex {
+            Some(regex) if config.only_matching => {
+                // 反向匹配时选中的行中没有匹配的部分,和grep一样什么也不输出
+                if config.invert_match {
+                    return Ok(0);
+                }
+                for captures in matches(regex, text, config.word_regexp) {
+                    out.write_all(&prefix)?;
+                    // 有`--replace`时输出替换后的内容
+                    let part = match &config.replace {
+                        Some(replacement) => {
+                            let mut part = Vec::new();
+                            self.expand(&captures, replacement, &mut part);
+                            part
+                        }
+                        None => matched_part(&captures),
+                    };
+                    self.write_colored(out, MATCH_COLOR, &part)?;
+                    out.write_all(b"\n")?;
+                }
+            }
+            _ => {
+                out.write_all(&prefix)?;
+                // 上下文行不做替换
+                match self.replace(text).filter(|_| selected) {
+                    Some((replaced, inserted)) => self.write_spans(out, &replaced, &inserted)?,
+                    None if self.color => self.write_spans(out, text, &self.matcher.find_spans(text))?,
+                    None => out.write_all(text)?,
+                }
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(0)
+    }
+
+    /// 分隔不相邻的上下文组,多个文件并行搜索时也用来分隔不同文件的组.JSON输出中没有分隔符
+    pub(crate) fn write_group_separator<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        if self.json {
+            return Ok(());
+        }
+        self.write_colored(out, SEPARATOR_COLOR, GROUP_SEPARATOR)?;
+        out.write_all(b"\n")
+    }
+
+    /// 输出JSON时,所有文件搜索完后输出统计数据
+    pub(crate) fn write_summary<W: Write>(&self, out: &mut W, elapsed_total: Duration) -> io::Result<()> {
+        if !self.json {
+            return Ok(());
+        }
+        write_json(out, &json::summary(&self.totals.lock().unwrap(), elapsed_total))
+    }
+
+    /// 着色时用ANSI转义序列包裹`text`,否则原样输出
+    fn write_colored<W: Write>(&self, out: &mut W, color: &[u8], text: &[u8]) -> io::Result<()> {
+        if !self.color || text.is_empty() {
+            return out.write_all(text);
+        }
+        out.write_all(color)?;
+        out.write_all(text)?;
+        out.write_all(RESET)
+    }
+
+    /// 输出一行,着色时高亮`spans`中的部分
+    fn write_spans<W: Write>(&self, out: &mut W, line: &[u8], spans: &[Range<usize>]) -> io::Result<()> {
+        let mut last = 0;
+        for span in spans.iter().cloned() {
+            out.write_all(&line[last..span.start])?;
+            self.write_colored(out, MATCH_COLOR, &line[span.clone()])?;
+            last = span.end;
+        }
+        out.write_all(&line[last..])
+    }
+}
+
+/// 正在搜索的文件在输出中的名字,以及输出JSON时的路径
+struct Source {
+    name: String,
+    path: Option<Value>,
+}
+
+/// 读到的一行,`raw`包括行尾的换行符,`offset`是这一行在文件中的字节偏移量
+struct Line<'l> {
+    number: usize,
+    offset: u64,
+    raw: &'l [u8],
+}
+
+impl Line<'_> {
+    /// 去掉`\n`或`\r\n`之后的内容
+    fn text(&self) -> &[u8] {
+        split_terminator(self.raw).0
+    }
+}
+
+/// 把一行分成内容和行尾的`\n`或`\r\n`
+fn split_terminator(raw: &[u8]) -> (&[u8], &[u8]) {
+    let text = raw.strip_suffix(b"\n").map_or(raw, |line| line.strip_suffix(b"\r").unwrap_or(line));
+    raw.split_at(text.len())
+}
+
+fn write_json<W: Write>(out: &mut W, record: &Value) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn search(args: &[&str], content: &str) -> String {
+        String::from_utf8(search_bytes(args, content.as_bytes())).unwrap()
+    }
+
+    fn search_bytes(args: &[&str], content: &[u8]) -> Vec<u8> {
+        let args = ["minigrep"].iter().chain(args).map(|arg| arg.to_string());
+        let config = Config::new(args).unwrap();
+        let searcher = Searcher::new(&config).unwrap();
+        let file = FileEntry { path: PathBuf::from("log"), explicit: true };
+        let mut out = Vec::new();
+        searcher.search_reader(&file, searcher.decode(content), false, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    // 重叠和相邻的上下文合并,不相邻的组之间输出分隔符,匹配行和上下文行的标记不同
+    fn context_groups() {
+        let content = "a\nERROR 1\nb\nc\nERROR 2\nd\ne\nf\ng\nERROR 3\nh\n";
+        assert_eq!(
+            "1-a\n2:ERROR 1\n3-b\n4-c\n5:ERROR 2\n6-d\n--\n9-g\n10:ERROR 3\n11-h\n",
+            search(&["-n", "-C1", "ERROR"], content)
+        );
+        assert_eq!("ERROR 1\nb\nc\nERROR 2\nd\ne\n--\nERROR 3\nh\n", search(&["-A", "2", "ERROR"], content));
+        assert_eq!("c\nERROR 2\n--\ng\nERROR 3\n", search(&["--before-context=1", "-E", "ERROR [23]"], content));
+
+        // 上下文在文件的开头和结尾截断
+        assert_eq!("ERROR\na\n--\nb\nERROR\n", search(&["-C1", "ERROR"], "ERROR\na\nx\ny\nb\nERROR\n"));
+        // 后文的最后一行紧挨着下一个匹配行时也合并成一组
+        assert_eq!("ERROR 1\na\nb\nERROR 2\n", search(&["-A2", "ERROR"], "ERROR 1\na\nb\nERROR 2\n"));
+        assert_eq!("ERROR 1\na\n--\nERROR 2\n", search(&["-A1", "ERROR"], "ERROR 1\na\nb\nERROR 2\n"));
+    }
+
+    #[test]
+    // 不是合法UTF-8的行照常搜索,原样输出
+    fn latin1_bytes() {
+        let content = b"caf\xe9 ouvert\nERROR \xe9chec\nfin\n";
+        assert_eq!(b"ERROR \xe9chec\n".to_vec(), search_bytes(&["ERROR"], content));
+        assert_eq!(b"2:ERROR \xe9chec\n".to_vec(), search_bytes(&["-n", "-i", "error"], content));
+        // 在Unicode模式下`.`不匹配非法的字节,`(?-u:.)`匹配任意字节
+        assert_eq!(b"caf\xe9 ouvert\n".to_vec(), search_bytes(&["-E", r"^caf(?-u:.) ouvert$"], content));
+        // 指定编码后转码成UTF-8
+        assert_eq!("ERROR échec\n", String::from_utf8(search_bytes(&["--encoding=latin1", "échec"], content)).unwrap());
+    }
+
+    #[test]
+    // UTF-16有BOM时自动转码,也可以用--encoding指定
+    fn utf16() {
+        let text = "first line\nERROR here\n";
+        let le: Vec<u8> = [0xff, 0xfe].into_iter().chain(text.encode_utf16().flat_map(u16::to_le_bytes)).collect();
+        assert_eq!("ERROR here\n", String::from_utf8(search_bytes(&["ERROR"], &le)).unwrap());
+        let be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!("ERROR here\n", String::from_utf8(search_bytes(&["--encoding", "utf-16be", "ERROR"], &be)).unwrap());
+    }
+
+    #[test]
+    // 含有NUL的文件只提示有匹配,-a时按文本搜索
+    fn binary_matches() {
+        let content = b"\x7fELF\0\0\nversion ERROR\n";
+        assert_eq!("Binary file log matches\n", String::from_utf8(search_bytes(&["ERROR"], content)).unwrap());
+        assert_eq!("", String::from_utf8(search_bytes(&["nothing"], content)).unwrap());
+        assert_eq!("1\n", String::from_utf8(search_bytes(&["-c", "ERROR"], content)).unwrap());
+        assert_eq!("version ERROR\n", String::from_utf8(search_bytes(&["-a", "ERROR"], content)).unwrap());
+    }
+
+    #[test]
+    // 着色时高亮匹配部分,行号、分隔符也着色;不区分大小写时按原文的位置高亮
+    fn colored() {
+        let content = "Trust RUST\nnothing\nfin\nrust\n";
+        assert_eq!(
+            "\x1b[32m1\x1b[0m\x1b[36m:\x1b[0mT\x1b[1;31mrust\x1b[0m \x1b[1;31mRUST\x1b[0m\n",
+            search(&["--color=always", "-n", "-i", "rust"], &content[..11])
+        );
+        // 开尔文符号整个被高亮
+        assert_eq!(
+            "\x1b[1;31m\u{212A}ELVIN\x1b[0m!\n",
+            search(&["--color=always", "-i", "kelvin"], "\u{212A}ELVIN!\n")
+        );
+        // 上下文行和分隔符
+        assert_eq!(
+            "T\x1b[1;31mrust\x1b[0m RUST\nnothing\n\x1b[36m--\x1b[0m\n\x1b[1;31mrust\x1b[0m\n",
+            search(&["--color=always", "-A1", "rust"], content)
+        );
+        assert_eq!("\x1b[1;31mRUST\x1b[0m\n", search(&["--color=always", "-o", "-E", "[A-Z]{2,}"], content));
+        assert_eq!("Trust RUST\n", search(&["--color=never", "RUST"], content));
+    }
+
+    #[test]
+    // JSON输出:有匹配的文件依次输出begin、match和context、end,偏移量按原始的字节计算
+    fn json_records() {
+        let content = "first\r\nERROR one ERROR\nlast\n";
+        let records: Vec<serde_json::Value> = search(&["--json", "-B1", "ERROR"], content)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let types: Vec<&str> = records.iter().map(|record| record["type"].as_str().unwrap()).collect();
+        assert_eq!(vec!["begin", "context", "match", "end"], types);
+        assert_eq!("log", records[0]["data"]["path"]["text"]);
+        assert_eq!("first\r\n", records[1]["data"]["lines"]["text"]);
+        assert_eq!(7, records[2]["data"]["absolute_offset"]);
+        assert_eq!(2, records[2]["data"]["line_number"]);
+        assert_eq!(
+            serde_json::json!({ "match": { "text": "ERROR" }, "start": 10, "end": 15 }),
+            records[2]["data"]["submatches"][1]
+        );
+        let stats = &records[3]["data"]["stats"];
+        assert_eq!(1, stats["matched_lines"]);
+        assert_eq!(2, stats["matches"]);
+        assert_eq!(28, stats["bytes_searched"]);
+
+        // 没有匹配的文件不输出记录
+        assert_eq!("", search(&["--json", "nothing"], content));
+    }
+
+    #[test]
+    // 替换匹配的部分,正则表达式模式下可以引用捕获组,上下文行不做替换
+    fn replace_lines() {
+        let content = "foo(1) + foo(22)\nkeep foo\nfood(3)\n";
+        assert_eq!("bar(1) + bar(22)\nkeep bar\nbard(3)\n", search(&["-r", "bar", "foo"], content));
+        // 字面模式下`$1`不是引用
+        assert_eq!("$1(1) + $1(22)\n", search(&["-r$1", "-w", "foo"], &content[..17]));
+        assert_eq!(
+            "call(1) + call(22)\nkeep foo\n",
+            search(&["-E", "--replace=call(${n})", "-A1", r"\bfoo\((?P<n>\d+)\)"], content)
+        );
+        assert_eq!("[1]\n[22]\n[3]\n", search(&["-Eo", "-r", "[$1]", r"\((\d+)\)"], content));
+        assert_eq!(
+            "\x1b[1;31m1\x1b[0m + \x1b[1;31m22\x1b[0m\n",
+            search(&["--color=always", "-Er", "$1", r"foo\((\d+)\)"], content)
+        );
+    }
+
+    #[test]
+    // 只输出匹配部分时每处匹配占一行;反向匹配选中的行没有匹配的部分,什么也不输出,但照常计数
+    fn only_matching_inverted() {
+        let content = "foo bar foo
+nothing
+barfoo
+";
+        assert_eq!("foo\nfoo\nfoo\n", search(&["-o", "foo"], content));
+        assert_eq!("", search(&["-o", "-v", "foo"], content));
+        assert_eq!("", search(&["-ov", "-n", "nothing"], content));
+        assert_eq!("1\n", search(&["-o", "-v", "-c", "foo"], content));
+    }
+
+    #[test]
+    // 近似匹配时每个文件中匹配的行按编辑距离排序输出
+    fn fuzzy_ranked() {
+        let content = "fn recieve()\nfn reply()\nfn receive()\nlet RECEIVED = 1;\n";
+        assert_eq!("3:fn receive()\n1:fn recieve()\n", search(&["-n", "--fuzzy=2", "receive"], content));
+        assert_eq!("fn receive()\nlet RECEIVED = 1;\nfn recieve()\n", search(&["-i", "--fuzzy", "2", "receive"], content));
+        assert_eq!("3\n", search(&["-ic", "--fuzzy=2", "receive"], content));
+        // 空行和查询字符串的距离是查询字符串的长度
+        assert_eq!("2:x\n1:\n", search(&["-n", "--fuzzy=5", "xy"], "\nx\n"));
+        assert_eq!(
+            "fn \x1b[1;31mreceive\x1b[0m()\nfn \x1b[1;31mreceve\x1b[0m()\n",
+            search(&["--color=always", "--fuzzy=1", "receive"], "fn receve()\nfn receive()\n")
+        );
+    }
+}
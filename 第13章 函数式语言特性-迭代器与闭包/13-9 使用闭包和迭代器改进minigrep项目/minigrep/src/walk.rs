@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use ignore::overrides::OverrideBuilder;
+use ignore::types::TypesBuilder;
+use ignore::{WalkBuilder, WalkState};
+use crate::Config;
+
+/// 判断是否为二进制文件时只检查开头的这么多字节
+const BINARY_CHECK_LEN: usize = 8 * 1024;
+
+/// 遍历得到的一个文件
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub path: PathBuf,
+    /// 命令行上直接给出的文件,搜索时不跳过二进制文件
+    pub explicit: bool,
+}
+
+impl FileEntry {
+    /// 路径`-`表示标准输入,要搜索名为`-`的文件可以写成`./-`
+    pub fn is_stdin(&self) -> bool {
+        self.path.as_os_str() == "-"
+    }
+
+    /// 输出时使用的名字
+    pub fn name(&self) -> String {
+        if self.is_stdin() {
+            return "(standard input)".to_string();
+        }
+        self.path.display().to_string()
+    }
+}
+
+/// 遍历的结果,个别目录或文件出错不影响其余部分
+#[derive(Debug, Default)]
+pub struct Walked {
+    pub files: Vec<FileEntry>,
+    pub errors: Vec<ignore::Error>,
+}
+
+/// 按配置遍历要搜索的路径,目录会被多个线程并行递归展开
+/// 默认跳过隐藏文件和被`.gitignore`、`.ignore`忽略的文件,不在git仓库中时`.gitignore`同样生效.
+/// 命令行上直接给出的文件总会被搜索,不受这些规则和`--glob`、`--type`的影响.
+/// 结果按命令行上路径的顺序排列,同一路径下的文件按路径排序,和按文件名排序的深度优先遍历顺序相同
+pub fn files(config: &Config) -> Result<Walked, ignore::Error> {
+    let mut walked = Walked::default();
+    for root in &config.paths {
+        if root == "-" {
+            walked.files.push(FileEntry { path: PathBuf::from(root), explicit: true });
+            continue;
+        }
+        let found = Mutex::new(Walked::default());
+        builder(config, root)?.build_parallel().run(|| {
+            let found = &found;
+            Box::new(move |entry| {
+                let mut found = found.lock().unwrap();
+                match entry {
+                    Ok(entry) if entry.file_type().is_some_and(|kind| kind.is_file()) => {
+                        let explicit = entry.depth() == 0;
+                        found.files.push(FileEntry { path: entry.into_path(), explicit });
+                    }
+                    Ok(_) => {}
+                    Err(err) => found.errors.push(err),
+                }
+                WalkState::Continue
+            })
+        });
+
+        let mut found = found.into_inner().unwrap();
+        found.files.sort_by(|a, b| a.path.cmp(&b.path));
+        walked.files.append(&mut found.files);
+        walked.errors.append(&mut found.errors);
+    }
+    Ok(walked)
+}
+
+fn builder(config: &Config, root: &str) -> Result<WalkBuilder, ignore::Error> {
+    let mut overrides = OverrideBuilder::new(".");
+    for glob in &config.globs {
+        overrides.add(glob)?;
+    }
+
+    let mut types = TypesBuilder::new();
+    types.add_defaults();
+    for name in &config.types {
+        types.select(name);
+    }
+    for name in &config.types_not {
+        types.negate(name);
+    }
+
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(!config.hidden)
+        .ignore(!config.no_ignore)
+        .git_ignore(!config.no_ignore)
+        .git_global(!config.no_ignore)
+        .git_exclude(!config.no_ignore)
+        .parents(!config.no_ignore)
+        .require_git(false)
+        .overrides(overrides.build()?)
+        .types(types.build()?)
+        .threads(config.threads());
+    // 被`--glob`或`--type`选中的文件会绕过`hidden`的检查,这里再过滤一次
+    if !config.hidden {
+        builder.filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'));
+    }
+    Ok(builder)
+}
+
+/// 和grep一样,开头部分含有NUL字节的文件被当作二进制文件
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_CHECK_LEN)].contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    fn config(paths: &[&Path], args: &[&str]) -> Config {
+        let args = ["minigrep"].iter().chain(args).chain(&["query"]).map(|arg| arg.to_string());
+        let paths = paths.iter().map(|path| path.display().to_string());
+        Config::new(args.chain(paths)).unwrap()
+    }
+
+    fn names(config: &Config, root: &Path) -> Vec<String> {
+        let walked = files(config).unwrap();
+        assert!(walked.errors.is_empty());
+        walked
+            .files
+            .iter()
+            .map(|file| file.path.strip_prefix(root).unwrap().display().to_string())
+            .collect()
+    }
+
+    #[test]
+    // 忽略规则、隐藏文件以及glob和类型过滤
+    fn filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join(".hidden.rs"), "").unwrap();
+        fs::write(root.join("app.log"), "").unwrap();
+        fs::write(root.join("notes.txt"), "").unwrap();
+        fs::write(root.join("src/main.rs"), "").unwrap();
+        fs::write(root.join("src/util.py"), "").unwrap();
+        fs::write(root.join("target/out.rs"), "").unwrap();
+
+        assert_eq!(vec!["notes.txt", "src/main.rs", "src/util.py"], names(&config(&[root], &[]), root));
+        assert_eq!(vec!["src/main.rs"], names(&config(&[root], &["-trust"]), root));
+        assert_eq!(vec!["notes.txt", "src/main.rs"], names(&config(&[root], &["-Tpy"]), root));
+        assert_eq!(vec!["src/util.py"], names(&config(&[root], &["--glob=*.py"]), root));
+        assert_eq!(vec!["notes.txt", "src/util.py"], names(&config(&[root], &["-g!*.rs"]), root));
+        assert_eq!(
+            vec![".gitignore", ".hidden.rs", "app.log", "notes.txt", "src/main.rs", "src/util.py", "target/out.rs"],
+            names(&config(&[root], &["--hidden", "--no-ignore"]), root)
+        );
+
+        // 直接给出的文件不受过滤规则影响,结果按命令行上的顺序排列
+        let log = root.join("app.log");
+        let src = root.join("src");
+        assert_eq!(vec!["src/main.rs", "src/util.py", "app.log"], names(&config(&[&src, &log], &["-j4"]), root));
+        assert_eq!(vec!["app.log"], names(&config(&[&log], &["-trust"]), root));
+        assert!(files(&config(&[&log], &[])).unwrap().files[0].explicit);
+
+        assert!(files(&config(&[root], &["-tnosuchtype"])).is_err());
+    }
+
+    #[test]
+    fn binary_detection() {
+        assert!(!is_binary(b"plain text\n"));
+        assert!(is_binary(b"\x7fELF\x02\x01\x01\0\0"));
+        let mut late = vec![b'a'; BINARY_CHECK_LEN];
+        late.push(0);
+        assert!(!is_binary(&late));
+    }
+}
@@ -0,0 +1,50 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+// 像`tail -f app.log | minigrep ERROR`一样,输入还没结束时匹配的行就已经输出
+fn streams_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_minigrep"))
+        .arg("ERROR")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            sender.send(line.unwrap()).unwrap();
+        }
+    });
+
+    stdin.write_all(b"INFO started\nERROR first\n").unwrap();
+    stdin.flush().unwrap();
+    assert_eq!("ERROR first", receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+
+    stdin.write_all(b"INFO still running\nERROR second\r\n").unwrap();
+    drop(stdin);
+    assert_eq!("ERROR second", receiver.recv_timeout(Duration::from_secs(5)).unwrap());
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+// `-`表示标准输入,可以和文件一起搜索
+fn dash_is_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_minigrep"))
+        .args(["-c", "body", "-", "poem.txt"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"somebody\nnobody\nanybody\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!("(standard input):3\npoem.txt:3\n", String::from_utf8(output.stdout).unwrap());
+}
@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use crate::app::pages::render;
+use crate::http::{HttpError, Request, Response};
+use crate::routing::Router;
+use crate::template::{Context, Templates};
+
+/// 注册登录、退出登录页面,登录状态保存在会话中,需要配合`SessionLayer`使用
+pub fn routes(router: Router, templates: Arc<Templates>) -> Router {
+    router
+        .get("/login", move |_| render(&templates, 200, "login.html", Context::new()))
+        .post("/login", login)
+        .post("/logout", logout)
+}
+
+fn login(request: &Request) -> Result<Response, HttpError> {
+    let form = request.form()?;
+    let name = form
+        .get("name")
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| HttpError::bad_request("missing name"))?;
+    let session = request.session().ok_or_else(|| HttpError::internal("sessions are not enabled"))?;
+    // 登录前后使用不同的会话id,防止会话固定攻击
+    session.regenerate();
+    session.insert("user", name);
+    Ok(Response::redirect("/"))
+}
+
+fn logout(request: &Request) -> Response {
+    if let Some(session) = request.session() {
+        session.destroy();
+    }
+    Response::redirect("/")
+}
@@ -0,0 +1,133 @@
+pub mod pages;
+
+pub mod account;
+
+pub mod todos;
+pub use todos::TodoStore;
+
+use std::sync::Arc;
+use std::time::Duration;
+use crate::auth::{BasicAuth, BearerAuth, Htpasswd, PrefixAuth};
+use crate::cgi::CgiHandler;
+use crate::config::{AuthSettings, Config, RateLimitSettings, VhostSettings};
+use crate::files::ServeDir;
+use crate::middleware::{AccessLog, RateLimitConfig, RateLimiter};
+use crate::routing::Router;
+use crate::session::{MemoryStore, SessionLayer};
+use crate::template::Templates;
+use crate::vhost::{VirtualHost, VirtualHosts};
+
+/// 应用的数据,重新加载配置、重建路由时保留,因此不会丢失待办事项和已登录的会话
+#[derive(Clone, Default)]
+pub struct AppState {
+    todos: Arc<TodoStore>,
+    sessions: Arc<MemoryStore>,
+}
+
+impl AppState {
+    pub fn new() -> AppState {
+        AppState::default()
+    }
+}
+
+/// 构建服务器使用的全部路由
+pub fn router(templates: Arc<Templates>) -> Router {
+    router_with(templates, &AppState::new())
+}
+
+/// 使用已有的数据构建路由
+pub fn router_with(templates: Arc<Templates>, state: &AppState) -> Router {
+    app_routes(Router::new(), templates, state)
+}
+
+/// 在`router`已有的中间件之后添加应用的路由和会话中间件
+fn app_routes(router: Router, templates: Arc<Templates>, state: &AppState) -> Router {
+    let router = pages::routes(router, templates.clone());
+    let router = account::routes(router, templates);
+    todos::routes(router, state.todos.clone()).layer(SessionLayer::new(state.sessions.clone()))
+}
+
+/// 按照配置构建路由,包括静态文件目录、CGI程序、虚拟主机、认证和限流
+/// `config`需要已经通过`Config::validate()`的校验.限流器随路由一起重建,重新加载后信任的代理和闲置超时立即生效,
+/// 所有客户端的令牌桶都是满的
+pub fn configure(config: &Config, state: &AppState) -> Router {
+    // debug构建下开启模板热重载,修改模板后无需重启服务器
+    let templates = Templates::new(&config.templates).hot_reload(cfg!(debug_assertions));
+    // 限流器最先添加,位于最外层,被限流的请求不会再查询会话存储
+    let mut router = Router::new();
+    if let Some(rate_limit) = &config.rate_limit {
+        router = router.layer(rate_limiter(rate_limit));
+    }
+    // 认证在会话之前进行,未通过认证的请求同样不会查询会话存储
+    for (prefix, settings) in &config.auth {
+        router = router.layer(prefix_auth(prefix, settings));
+    }
+    router = app_routes(router, Arc::new(templates), state);
+    for (prefix, root) in &config.static_roots {
+        let files = ServeDir::new(root);
+        let pattern = format!("{}/*path", prefix.trim_end_matches('/'));
+        router = router.get(&pattern, move |request| files.serve(request));
+    }
+    for (prefix, settings) in &config.cgi {
+        let mut cgi = CgiHandler::new(&settings.program).timeout(Duration::from_secs(settings.timeout_secs));
+        for arg in &settings.args {
+            cgi = cgi.arg(arg.as_str());
+        }
+        // `*path`也匹配空路径,因此前缀本身同样由该程序处理
+        let pattern = format!("{}/*path", prefix.trim_end_matches('/'));
+        let post = cgi.clone();
+        router = router
+            .get(&pattern, move |request| cgi.serve(request))
+            .post(&pattern, move |request| post.serve(request));
+    }
+    if !config.vhosts.is_empty() {
+        // 虚拟主机必须是最后添加的中间件,外层的限流对所有主机生效
+        router = router.layer(virtual_hosts(&config.vhosts));
+    }
+    router
+}
+
+fn rate_limiter(settings: &RateLimitSettings) -> RateLimiter {
+    let config = RateLimitConfig::new(settings.rate, settings.burst)
+        .idle_timeout(Duration::from_secs(settings.idle_timeout_secs));
+    let config = settings.trusted_proxies.iter().fold(config, |config, &proxy| config.trust_proxy(proxy));
+    RateLimiter::new(config)
+}
+
+fn prefix_auth(prefix: &str, settings: &AuthSettings) -> PrefixAuth {
+    let realm = settings.realm.as_deref().unwrap_or(prefix);
+    // 文件在校验配置时已经成功加载过,这里再次加载失败时不放行任何请求
+    if let Some(path) = &settings.tokens {
+        let auth = BearerAuth::new(realm).load(path).unwrap_or_else(|e| {
+            eprintln!("failed to load tokens {}: {}", path.display(), e);
+            BearerAuth::new(realm)
+        });
+        return PrefixAuth::new(prefix, auth);
+    }
+    let users = settings.htpasswd.as_ref().map_or_else(Htpasswd::default, |path| {
+        Htpasswd::open(path).unwrap_or_else(|e| {
+            eprintln!("failed to load htpasswd {}: {}", path.display(), e);
+            Htpasswd::default()
+        })
+    });
+    PrefixAuth::new(prefix, BasicAuth::new(realm, users))
+}
+
+fn virtual_hosts(settings: &[VhostSettings]) -> VirtualHosts {
+    let mut hosts = VirtualHosts::new();
+    for vhost in settings {
+        let mut host = VirtualHost::new(&vhost.names[0], Router::new()).root(&vhost.root);
+        for alias in &vhost.names[1..] {
+            host = host.alias(alias);
+        }
+        if let Some(path) = &vhost.access_log {
+            // 目录已经校验过,这里失败通常是权限问题,此时只是不记录日志,不影响提供服务
+            match AccessLog::open(path) {
+                Ok(log) => host = host.access_log(log),
+                Err(e) => eprintln!("failed to open access log {}: {}", path.display(), e),
+            }
+        }
+        hosts = if vhost.default { hosts.default_host(host) } else { hosts.host(host) };
+    }
+    hosts
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use crate::http::{Request, Response};
+use crate::routing::Router;
+use crate::template::{Context, Templates};
+
+/// 注册由模板渲染的页面,包括`/`、`/sleep`以及404页面
+pub fn routes(router: Router, templates: Arc<Templates>) -> Router {
+    let hello = templates.clone();
+    let sleep = templates.clone();
+    let not_found = templates;
+
+    router
+        .get("/", move |request| render(&hello, 200, "hello.html", home_context(request)))
+        .get("/sleep", move |request| {
+            thread::sleep(Duration::from_secs(5));
+            render(&sleep, 200, "hello.html", home_context(request))
+        })
+        .fallback(move |request| {
+            let context = Context::new().with("path", request.path());
+            render(&not_found, 404, "404.html", context)
+        })
+}
+
+fn home_context(request: &Request) -> Context {
+    let mut context = Context::new().with("routes", vec!["/", "/sleep", "/api/todos", "/login"]);
+    if let Some(user) = request.session().and_then(|session| session.get::<String>("user")) {
+        context.insert("user", user);
+    }
+    context
+}
+
+pub(crate) fn render(templates: &Templates, status: u16, name: &str, context: Context) -> Response {
+    match templates.render(name, &context) {
+        Ok(body) => Response::html(status, body),
+        Err(e) => {
+            eprintln!("failed to render {}: {}", name, e);
+            Response::text(500, "Internal Server Error")
+        }
+    }
+}
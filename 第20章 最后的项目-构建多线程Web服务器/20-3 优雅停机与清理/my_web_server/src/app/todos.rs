@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::http::{HttpError, Request, Response};
+use crate::routing::Router;
+use crate::sse::{Broadcaster, Event, Sse};
+
+/// 一个待办事项
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Todo {
+    pub id: u64,
+    pub title: String,
+    pub done: bool,
+}
+
+/// 创建待办事项时的请求体
+#[derive(Debug, Deserialize)]
+pub struct NewTodo {
+    pub title: String,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// 修改待办事项时的请求体,未提供的字段保持不变
+#[derive(Debug, Deserialize)]
+pub struct UpdateTodo {
+    pub title: Option<String>,
+    pub done: Option<bool>,
+}
+
+/// 保存在内存中的待办事项,进程退出后数据即丢失
+/// 每次修改都会以`created`、`updated`、`deleted`事件广播出去
+pub struct TodoStore {
+    todos: Mutex<HashMap<u64, Todo>>,
+    next_id: AtomicU64,
+    events: Broadcaster,
+}
+
+impl Default for TodoStore {
+    fn default() -> TodoStore {
+        TodoStore {
+            todos: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+            events: Broadcaster::new(100),
+        }
+    }
+}
+
+impl TodoStore {
+    pub fn new() -> TodoStore {
+        TodoStore::default()
+    }
+
+    /// 待办事项的修改事件
+    pub fn events(&self) -> &Broadcaster {
+        &self.events
+    }
+
+    fn publish(&self, event: &str, todo: &Todo) {
+        let data = serde_json::to_string(todo).unwrap_or_default();
+        self.events.send(Event::new(data).with_event(event));
+    }
+
+    /// 按id升序列出所有待办事项
+    pub fn list(&self) -> Vec<Todo> {
+        let mut todos: Vec<Todo> = self.todos.lock().unwrap().values().cloned().collect();
+        todos.sort_by_key(|todo| todo.id);
+        todos
+    }
+
+    pub fn get(&self, id: u64) -> Option<Todo> {
+        self.todos.lock().unwrap().get(&id).cloned()
+    }
+
+    pub fn create(&self, new: NewTodo) -> Todo {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let todo = Todo { id, title: new.title, done: new.done };
+        self.todos.lock().unwrap().insert(id, todo.clone());
+        self.publish("created", &todo);
+        todo
+    }
+
+    pub fn update(&self, id: u64, update: UpdateTodo) -> Option<Todo> {
+        let mut todos = self.todos.lock().unwrap();
+        let todo = todos.get_mut(&id)?;
+        if let Some(title) = update.title {
+            todo.title = title;
+        }
+        if let Some(done) = update.done {
+            todo.done = done;
+        }
+        let todo = todo.clone();
+        drop(todos);
+        self.publish("updated", &todo);
+        Some(todo)
+    }
+
+    pub fn delete(&self, id: u64) -> Option<Todo> {
+        let todo = self.todos.lock().unwrap().remove(&id)?;
+        self.publish("deleted", &todo);
+        Some(todo)
+    }
+}
+
+/// 注册`/api/todos`下的增删改查接口,以及推送修改事件的`/api/todos/events`
+pub fn routes(router: Router, store: Arc<TodoStore>) -> Router {
+    let events = store.clone();
+    let list = store.clone();
+    let create = store.clone();
+    let show = store.clone();
+    let update = store.clone();
+    let delete = store;
+
+    router
+        .get("/api/todos", move |_| Response::json(&list.list()))
+        .post("/api/todos", move |request| -> Result<Response, HttpError> {
+            let todo = create.create(request.json()?);
+            Ok(Response::json(&todo)
+                .with_status(201)
+                .with_header("Location", format!("/api/todos/{}", todo.id)))
+        })
+        // 需要在`/api/todos/:id`之前注册
+        .get("/api/todos/events", move |request| {
+            Sse::new(events.events().subscribe(request.header("Last-Event-ID")))
+        })
+        .get("/api/todos/:id", move |request| -> Result<Response, HttpError> {
+            let todo = show.get(todo_id(request)?).ok_or_else(todo_not_found)?;
+            Ok(Response::json(&todo))
+        })
+        .put("/api/todos/:id", move |request| -> Result<Response, HttpError> {
+            let id = todo_id(request)?;
+            let todo = update.update(id, request.json()?).ok_or_else(todo_not_found)?;
+            Ok(Response::json(&todo))
+        })
+        .delete("/api/todos/:id", move |request| -> Result<Response, HttpError> {
+            delete.delete(todo_id(request)?).ok_or_else(todo_not_found)?;
+            Ok(Response::new(204))
+        })
+}
+
+fn todo_id(request: &Request) -> Result<u64, HttpError> {
+    request
+        .param("id")
+        .and_then(|id| id.parse().ok())
+        .ok_or_else(|| HttpError::bad_request("todo id must be a positive integer"))
+}
+
+fn todo_not_found() -> HttpError {
+    HttpError::not_found("todo not found")
+}
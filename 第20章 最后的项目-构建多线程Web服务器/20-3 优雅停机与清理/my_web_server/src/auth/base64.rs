@@ -0,0 +1,45 @@
+/// 解码标准字母表的Base64,末尾的`=`填充可以省略,格式错误时返回`None`
+pub(crate) fn decode(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim_end_matches('=');
+    let mut output = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in input.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    // 剩余6位说明输入长度不合法
+    if bits >= 6 {
+        return None;
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes() {
+        assert_eq!(Some(b"Aladdin:open sesame".to_vec()), decode("QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+        assert_eq!(Some(b"ab".to_vec()), decode("YWI="));
+        assert_eq!(Some(b"ab".to_vec()), decode("YWI"));
+        assert_eq!(Some(Vec::new()), decode(""));
+        assert_eq!(None, decode("YW!="));
+        assert_eq!(None, decode("Y"));
+    }
+}
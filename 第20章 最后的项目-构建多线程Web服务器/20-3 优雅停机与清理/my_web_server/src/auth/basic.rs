@@ -0,0 +1,40 @@
+use crate::auth::{base64, credentials, unauthorized, AuthUser, Htpasswd};
+use crate::http::{Request, Response};
+use crate::middleware::{Middleware, Next};
+
+/// HTTP Basic认证中间件,用户名和密码由htpasswd文件校验
+/// Basic认证以明文传输密码,应当只在HTTPS或可信的内网中使用
+pub struct BasicAuth {
+    realm: String,
+    users: Htpasswd,
+}
+
+impl BasicAuth {
+    pub fn new<R: Into<String>>(realm: R, users: Htpasswd) -> BasicAuth {
+        BasicAuth { realm: realm.into(), users }
+    }
+
+    /// 校验请求携带的用户名和密码,成功时返回用户名
+    pub fn authenticate(&self, request: &Request) -> Option<String> {
+        let encoded = credentials(request, "Basic")?;
+        let decoded = String::from_utf8(base64::decode(encoded)?).ok()?;
+        let (user, password) = decoded.split_once(':')?;
+        if self.users.verify(user, password) {
+            Some(user.to_string())
+        } else {
+            None
+        }
+    }
+}
+
+impl Middleware for BasicAuth {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        match self.authenticate(request) {
+            Some(user) => {
+                request.extensions_mut().insert(AuthUser::new(user));
+                next.run(request)
+            }
+            None => unauthorized(format!("Basic realm=\"{}\", charset=\"UTF-8\"", self.realm.replace('"', ""))),
+        }
+    }
+}
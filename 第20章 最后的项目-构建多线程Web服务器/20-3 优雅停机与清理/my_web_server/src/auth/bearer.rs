@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::auth::{credentials, unauthorized, AuthUser};
+use crate::http::{Request, Response};
+use crate::middleware::{Middleware, Next};
+
+/// Bearer令牌认证中间件,令牌必须出现在配置的令牌列表中
+pub struct BearerAuth {
+    realm: String,
+    /// 令牌 -> 令牌的名称,名称作为`AuthUser`交给处理函数
+    tokens: HashMap<String, String>,
+}
+
+impl BearerAuth {
+    pub fn new<R: Into<String>>(realm: R) -> BearerAuth {
+        BearerAuth { realm: realm.into(), tokens: HashMap::new() }
+    }
+
+    /// 添加一个令牌,`name`用于标识令牌的持有者
+    pub fn token<T: Into<String>, N: Into<String>>(mut self, token: T, name: N) -> BearerAuth {
+        self.tokens.insert(token.into(), name.into());
+        self
+    }
+
+    /// 从文件中加载令牌,每行为`名称:令牌`或单独的令牌(名称与令牌相同),以`#`开头的行为注释
+    pub fn load<P: AsRef<Path>>(mut self, path: P) -> io::Result<BearerAuth> {
+        for line in fs::read_to_string(path)?.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once(':') {
+                Some((name, token)) => self.tokens.insert(token.trim().to_string(), name.trim().to_string()),
+                None => self.tokens.insert(line.to_string(), line.to_string()),
+            };
+        }
+        Ok(self)
+    }
+
+    /// 校验请求携带的令牌,成功时返回令牌的名称
+    /// 逐个以固定时间比较所有令牌,避免通过响应时间猜出令牌
+    pub fn authenticate(&self, request: &Request) -> Option<String> {
+        let presented = credentials(request, "Bearer")?;
+        let mut matched = None;
+        for (token, name) in &self.tokens {
+            if constant_time_eq(token.as_bytes(), presented.as_bytes()) {
+                matched = Some(name.clone());
+            }
+        }
+        matched
+    }
+}
+
+impl Middleware for BearerAuth {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        match self.authenticate(request) {
+            Some(name) => {
+                request.extensions_mut().insert(AuthUser::new(name));
+                next.run(request)
+            }
+            None => {
+                let realm = self.realm.replace('"', "");
+                // 没有携带令牌时不返回error参数(RFC 6750 3.1)
+                let challenge = if credentials(request, "Bearer").is_some() {
+                    format!("Bearer realm=\"{}\", error=\"invalid_token\"", realm)
+                } else {
+                    format!("Bearer realm=\"{}\"", realm)
+                };
+                unauthorized(challenge)
+            }
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
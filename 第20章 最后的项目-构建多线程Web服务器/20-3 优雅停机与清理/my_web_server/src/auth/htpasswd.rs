@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// htpasswd格式的用户文件,每行为`用户名:密码哈希`,以`#`开头的行为注释
+/// 只接受bcrypt(`$2a$`、`$2b$`、`$2y$`,即`htpasswd -B`生成的哈希)和SHA-crypt(`$5$`、`$6$`)哈希,
+/// MD5、DES等不安全的哈希在加载时就会被拒绝
+#[derive(Debug, Clone, Default)]
+pub struct Htpasswd {
+    users: HashMap<String, String>,
+}
+
+impl Htpasswd {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Htpasswd> {
+        Htpasswd::parse(&fs::read_to_string(path)?)
+    }
+
+    /// 解析文件内容,错误信息中包含出错的行号
+    pub fn parse(contents: &str) -> io::Result<Htpasswd> {
+        let mut users = HashMap::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, message));
+            let (user, hash) = line.split_once(':').ok_or_else(|| invalid("expected `user:hash`"))?;
+            if user.is_empty() {
+                return Err(invalid("empty user name"));
+            }
+            if scheme(hash).is_none() {
+                return Err(invalid("unsupported hash, use bcrypt or SHA-crypt"));
+            }
+            users.insert(user.to_string(), hash.to_string());
+        }
+        Ok(Htpasswd { users })
+    }
+
+    /// 添加或替换一个用户,`hash`必须是bcrypt或SHA-crypt哈希
+    /// # Panics
+    /// 哈希格式不受支持时会触发panic
+    pub fn insert(&mut self, user: &str, hash: &str) {
+        assert!(scheme(hash).is_some(), "unsupported password hash for `{}`", user);
+        self.users.insert(user.to_string(), hash.to_string());
+    }
+
+    /// 校验用户名和密码
+    /// 用户不存在时同样对一个已有用户的哈希计算一次,结果直接丢弃,避免通过响应时间判断用户名是否存在
+    pub fn verify(&self, user: &str, password: &str) -> bool {
+        match self.users.get(user) {
+            Some(hash) => verify_hash(password, hash),
+            None => {
+                if let Some(dummy) = self.users.values().next() {
+                    std::hint::black_box(verify_hash(password, dummy));
+                }
+                false
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.users.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+}
+
+fn verify_hash(password: &str, hash: &str) -> bool {
+    match scheme(hash) {
+        Some(Scheme::Bcrypt) => pwhash::bcrypt::verify(password, hash),
+        Some(Scheme::Sha256) => pwhash::sha256_crypt::verify(password, hash),
+        Some(Scheme::Sha512) => pwhash::sha512_crypt::verify(password, hash),
+        None => false,
+    }
+}
+
+enum Scheme {
+    Bcrypt,
+    Sha256,
+    Sha512,
+}
+
+fn scheme(hash: &str) -> Option<Scheme> {
+    if ["$2a$", "$2b$", "$2y$"].iter().any(|prefix| hash.starts_with(prefix)) {
+        Some(Scheme::Bcrypt)
+    } else if hash.starts_with("$5$") {
+        Some(Scheme::Sha256)
+    } else if hash.starts_with("$6$") {
+        Some(Scheme::Sha512)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_supported_hashes() {
+        let bcrypt = pwhash::bcrypt::hash_with(
+            pwhash::bcrypt::BcryptSetup { cost: Some(4), ..Default::default() },
+            "secret",
+        )
+        .unwrap();
+        let sha512 = pwhash::sha512_crypt::hash("hunter2").unwrap();
+        let contents = format!("# users\nalice:{}\n\nbob:{}\n", bcrypt, sha512);
+        let htpasswd = Htpasswd::parse(&contents).unwrap();
+
+        assert_eq!(2, htpasswd.len());
+        assert!(htpasswd.verify("alice", "secret"));
+        assert!(!htpasswd.verify("alice", "hunter2"));
+        assert!(htpasswd.verify("bob", "hunter2"));
+        // 不存在的用户使用别人的密码也无法通过
+        assert!(!htpasswd.verify("carol", "secret"));
+        assert!(!htpasswd.verify("carol", "hunter2"));
+        assert!(!Htpasswd::default().verify("alice", "secret"));
+    }
+
+    #[test]
+    fn rejects_weak_hashes() {
+        let error = Htpasswd::parse("alice:$apr1$abc$def\n").unwrap_err();
+        assert_eq!("line 1: unsupported hash, use bcrypt or SHA-crypt", error.to_string());
+        assert!(Htpasswd::parse("\n\nno-colon").unwrap_err().to_string().starts_with("line 3"));
+    }
+}
@@ -0,0 +1,35 @@
+pub mod htpasswd;
+pub use htpasswd::Htpasswd;
+
+pub mod basic;
+pub use basic::BasicAuth;
+
+pub mod bearer;
+pub use bearer::BearerAuth;
+
+pub mod prefix;
+pub use prefix::PrefixAuth;
+
+pub mod user;
+pub use user::AuthUser;
+
+mod base64;
+
+use crate::http::{HttpError, IntoResponse, Request, Response};
+
+/// 取出`Authorization: <scheme> <credentials>`中的凭据,认证方案不区分大小写
+fn credentials<'a>(request: &'a Request, scheme: &str) -> Option<&'a str> {
+    let (name, value) = request.header("Authorization")?.trim().split_once(' ')?;
+    if name.eq_ignore_ascii_case(scheme) {
+        Some(value.trim())
+    } else {
+        None
+    }
+}
+
+/// `401 Unauthorized`响应,`challenge`为`WWW-Authenticate`头部的值
+fn unauthorized(challenge: String) -> Response {
+    HttpError::new(401, "unauthorized")
+        .into_response()
+        .with_header("WWW-Authenticate", challenge)
+}
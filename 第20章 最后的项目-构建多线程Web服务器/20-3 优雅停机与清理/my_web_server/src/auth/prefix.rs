@@ -0,0 +1,52 @@
+use crate::http::{Request, Response};
+use crate::middleware::{Middleware, Next};
+
+/// 只对某个URL前缀下的路径进行认证的中间件
+/// 通过`Router::layer()`添加,因此也保护静态文件目录、CGI程序和虚拟主机这些不在`route_layer()`之前注册的路由
+pub struct PrefixAuth {
+    /// 前缀的各个路径段,和路由一样忽略空的路径段,`/admin`同样保护`//admin/`
+    segments: Vec<String>,
+    auth: Box<dyn Middleware>,
+}
+
+impl PrefixAuth {
+    /// `prefix`为`/`时保护所有路径
+    pub fn new<M: Middleware + 'static>(prefix: &str, auth: M) -> PrefixAuth {
+        PrefixAuth { segments: segments(prefix).map(str::to_string).collect(), auth: Box::new(auth) }
+    }
+
+    fn covers(&self, path: &str) -> bool {
+        let mut parts = segments(path);
+        self.segments.iter().all(|segment| parts.next() == Some(segment.as_str()))
+    }
+}
+
+impl Middleware for PrefixAuth {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        if self.covers(request.path()) {
+            self.auth.handle(request, next)
+        } else {
+            next.run(request)
+        }
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|part| !part.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::BearerAuth;
+
+    #[test]
+    fn covered_paths() {
+        let admin = PrefixAuth::new("/admin/", BearerAuth::new("admin"));
+        assert!(admin.covers("/admin"));
+        assert!(admin.covers("//admin/users"));
+        assert!(!admin.covers("/administrator"));
+        assert!(!admin.covers("/"));
+        assert!(PrefixAuth::new("/", BearerAuth::new("all")).covers("/anything"));
+    }
+}
@@ -0,0 +1,15 @@
+/// 通过认证的用户,认证中间件会把它放入请求的`extensions`中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthUser {
+    name: String,
+}
+
+impl AuthUser {
+    pub fn new<N: Into<String>>(name: N) -> AuthUser {
+        AuthUser { name: name.into() }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
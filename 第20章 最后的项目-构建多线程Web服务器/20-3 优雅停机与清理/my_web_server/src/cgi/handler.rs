@@ -0,0 +1,255 @@
+use std::env;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::auth::AuthUser;
+use crate::cgi::parse_output;
+use crate::http::{HttpError, IntoResponse, Request, Response};
+use crate::vhost::host_name;
+
+/// 以CGI/1.1方式执行外部程序的处理函数
+/// 每个请求启动一次程序:请求信息通过环境变量传入,请求body写入程序的stdin,
+/// 程序在stdout中输出响应头部和body.程序超时后连同它启动的子进程一起被杀死,并返回`504 Gateway Timeout`
+///
+/// 路由模式以`*path`结尾时,匹配到的部分作为`PATH_INFO`传给程序
+#[derive(Debug, Clone)]
+pub struct CgiHandler {
+    program: PathBuf,
+    args: Vec<String>,
+    env: Vec<(String, String)>,
+    current_dir: Option<PathBuf>,
+    timeout: Duration,
+    max_output: usize,
+}
+
+impl CgiHandler {
+    /// 默认超时时间为30秒,输出最多10MB
+    pub fn new<P: Into<PathBuf>>(program: P) -> CgiHandler {
+        CgiHandler {
+            program: program.into(),
+            args: Vec::new(),
+            env: Vec::new(),
+            current_dir: None,
+            timeout: Duration::from_secs(30),
+            max_output: 10 * 1024 * 1024,
+        }
+    }
+
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> CgiHandler {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// 额外传给程序的环境变量,程序不会继承服务器的环境变量(`PATH`除外)
+    pub fn env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> CgiHandler {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> CgiHandler {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> CgiHandler {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 程序输出的最大字节数,超出时杀死程序并返回502
+    pub fn max_output(mut self, max_output: usize) -> CgiHandler {
+        self.max_output = max_output;
+        self
+    }
+
+    pub fn serve(&self, request: &Request) -> Response {
+        match self.run(request) {
+            Ok(response) => response,
+            Err(error) => error.into_response(),
+        }
+    }
+
+    fn run(&self, request: &Request) -> Result<Response, HttpError> {
+        let deadline = Instant::now() + self.timeout;
+        let mut child = self.spawn(request).map_err(|e| {
+            eprintln!("failed to start {}: {}", self.program.display(), e);
+            HttpError::internal("failed to start CGI program")
+        })?;
+
+        // stdin、stdout和stderr分别在单独的线程中读写,避免程序与服务器互相等待对方的管道
+        let stdin = child.stdin.take();
+        let body = request.body().to_vec();
+        thread::spawn(move || {
+            if let Some(mut stdin) = stdin {
+                // 程序可以不读取body就退出,此时的BrokenPipe可以忽略
+                let _ = stdin.write_all(&body);
+            }
+        });
+        let stderr = child.stderr.take();
+        let program = self.program.display().to_string();
+        thread::spawn(move || {
+            if let Some(stderr) = stderr {
+                for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                    eprintln!("{}: {}", program, line);
+                }
+            }
+        });
+        let stdout = child.stdout.take();
+        let limit = (self.max_output as u64).saturating_add(1);
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            let result = match stdout {
+                Some(stdout) => stdout.take(limit).read_to_end(&mut output).map(|_| output),
+                None => Ok(output),
+            };
+            let _ = sender.send(result);
+        });
+
+        let output = match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                kill(&mut child);
+                eprintln!("failed to read output of {}: {}", self.program.display(), e);
+                return Err(HttpError::new(502, "failed to read CGI output"));
+            }
+            Err(_) => {
+                kill(&mut child);
+                return Err(HttpError::new(504, "CGI program timed out"));
+            }
+        };
+        if output.len() > self.max_output {
+            kill(&mut child);
+            return Err(HttpError::new(502, "CGI output too large"));
+        }
+        // 程序关闭了stdout但可能还没有退出
+        if !wait_until(&mut child, deadline) {
+            kill(&mut child);
+            return Err(HttpError::new(504, "CGI program timed out"));
+        }
+
+        parse_output(&output).ok_or_else(|| {
+            eprintln!("{} produced malformed CGI output", self.program.display());
+            HttpError::new(502, "malformed CGI output")
+        })
+    }
+
+    fn spawn(&self, request: &Request) -> io::Result<Child> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .env_clear()
+            .envs(cgi_env(request))
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(path) = env::var_os("PATH") {
+            command.env("PATH", path);
+        }
+        if let Some(dir) = &self.current_dir {
+            command.current_dir(dir);
+        }
+        // 程序在自己的进程组中运行,超时时可以连同它启动的子进程一起杀死
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            command.process_group(0);
+        }
+        command.spawn()
+    }
+}
+
+/// 轮询等待程序退出,超过`deadline`时返回`false`
+fn wait_until(child: &mut Child, deadline: Instant) -> bool {
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => return true,
+            Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            Ok(None) => return false,
+            Err(_) => return true,
+        }
+    }
+}
+
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    {
+        // 进程组id与程序的pid相同,向负的pid发送信号即发给整个进程组
+        if let Ok(pid) = libc::pid_t::try_from(child.id()) {
+            unsafe {
+                libc::kill(-pid, libc::SIGKILL);
+            }
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// RFC 3875定义的CGI环境变量
+fn cgi_env(request: &Request) -> Vec<(String, String)> {
+    let mut vars = vec![
+        ("GATEWAY_INTERFACE".to_string(), "CGI/1.1".to_string()),
+        ("SERVER_SOFTWARE".to_string(), concat!("my_web_server/", env!("CARGO_PKG_VERSION")).to_string()),
+        ("SERVER_PROTOCOL".to_string(), request.version().to_string()),
+        ("REQUEST_METHOD".to_string(), request.method().to_string()),
+        ("QUERY_STRING".to_string(), request.query().unwrap_or("").to_string()),
+    ];
+    let mut set = |key: &str, value: String| vars.push((key.to_string(), value));
+
+    let (script_name, path_info) = script_and_path_info(request);
+    set("SCRIPT_NAME", script_name);
+    set("PATH_INFO", path_info);
+
+    let host = request.header("Host").unwrap_or("");
+    set("SERVER_NAME", host_name(host).unwrap_or_else(|| "localhost".to_string()));
+    let port = host
+        .rsplit_once(':')
+        .map(|(_, port)| port)
+        .filter(|port| port.bytes().all(|b| b.is_ascii_digit()) && !port.is_empty());
+    set("SERVER_PORT", port.unwrap_or("80").to_string());
+
+    if let Some(addr) = request.peer_addr() {
+        set("REMOTE_ADDR", addr.ip().to_string());
+        set("REMOTE_PORT", addr.port().to_string());
+    }
+    if let Some(user) = request.extensions().get::<AuthUser>() {
+        set("REMOTE_USER", user.name().to_string());
+    }
+    if !request.body().is_empty() {
+        set("CONTENT_LENGTH", request.body().len().to_string());
+    }
+    if let Some(content_type) = request.header("Content-Type") {
+        set("CONTENT_TYPE", content_type.to_string());
+    }
+
+    for (name, _) in request.headers().iter() {
+        // 凭据不交给程序;`Proxy`头部会变成`HTTP_PROXY`,被许多程序当作代理设置(httpoxy)
+        let skipped = ["Content-Type", "Content-Length", "Authorization", "Proxy"];
+        if skipped.iter().any(|skip| name.eq_ignore_ascii_case(skip)) {
+            continue;
+        }
+        let key = format!("HTTP_{}", name.to_ascii_uppercase().replace('-', "_"));
+        if vars.iter().any(|(existing, _)| *existing == key) {
+            continue;
+        }
+        let value: Vec<&str> = request.headers().get_all(name).collect();
+        vars.push((key, value.join(", ")));
+    }
+    vars
+}
+
+/// 路由参数`path`之前的部分作为`SCRIPT_NAME`,参数本身作为`PATH_INFO`
+fn script_and_path_info(request: &Request) -> (String, String) {
+    let path = request.path();
+    match request.param("path").filter(|rest| !rest.is_empty()) {
+        Some(rest) => match path.strip_suffix(rest) {
+            Some(script) => (script.trim_end_matches('/').to_string(), format!("/{}", rest)),
+            None => (path.to_string(), String::new()),
+        },
+        None => (path.trim_end_matches('/').to_string(), String::new()),
+    }
+}
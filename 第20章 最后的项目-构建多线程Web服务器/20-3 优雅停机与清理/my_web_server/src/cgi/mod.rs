@@ -0,0 +1,5 @@
+pub mod handler;
+pub use handler::CgiHandler;
+
+mod output;
+use output::parse_output;
@@ -0,0 +1,115 @@
+use crate::http::{Headers, Response};
+
+/// 由服务器自己决定的头部:逐跳头部和消息长度,程序输出的这些头部会被丢弃(RFC 3875 6.3)
+/// 否则程序给出错误的`Content-Length`会破坏响应的分帧,`Connection: keep-alive`会让服务器不再关闭连接
+const SERVER_HEADERS: [&str; 9] = [
+    "Connection",
+    "Keep-Alive",
+    "Proxy-Connection",
+    "Proxy-Authenticate",
+    "TE",
+    "Trailer",
+    "Transfer-Encoding",
+    "Upgrade",
+    "Content-Length",
+];
+
+/// 解析CGI程序的输出
+/// 输出由头部、空行和body组成,头部中的`Status`决定状态码;
+/// 没有`Status`但有`Location`时视为重定向(302),否则为200.输出格式不正确时返回`None`
+/// 状态码只接受200到599,1xx是中间响应,不能作为程序的最终响应
+pub(crate) fn parse_output(output: &[u8]) -> Option<Response> {
+    let (head, body) = split_head(output)?;
+    let head = std::str::from_utf8(head).ok()?;
+
+    let mut headers = Headers::new();
+    let mut status = None;
+    for line in head.lines() {
+        let (name, value) = line.split_once(':')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() || name.contains(' ') {
+            return None;
+        }
+        if name.eq_ignore_ascii_case("Status") {
+            // 例如`Status: 404 Not Found`,原因短语由服务器自己生成
+            let code: u16 = value.split_whitespace().next()?.parse().ok()?;
+            if !(200..=599).contains(&code) {
+                return None;
+            }
+            status = Some(code);
+        } else if !SERVER_HEADERS.iter().any(|header| header.eq_ignore_ascii_case(name)) {
+            headers.append(name, value);
+        }
+    }
+
+    // 规范要求至少有一个Content-Type、Location或Status头部
+    if status.is_none() && !headers.contains("Content-Type") && !headers.contains("Location") {
+        return None;
+    }
+    let status = status.unwrap_or(if headers.contains("Location") { 302 } else { 200 });
+    let mut response = Response::new(status).with_body(body.to_vec());
+    *response.headers_mut() = headers;
+    Some(response)
+}
+
+/// 在第一个空行处分开头部和body,程序可能使用`\r\n`或`\n`换行
+fn split_head(output: &[u8]) -> Option<(&[u8], &[u8])> {
+    let mut start = 0;
+    while start <= output.len() {
+        let end = output[start..].iter().position(|&b| b == b'\n').map(|i| start + i)?;
+        let line = &output[start..end];
+        if line.is_empty() || line == b"\r" {
+            return Some((&output[..start], &output[end + 1..]));
+        }
+        start = end + 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_headers_and_body() {
+        let response = parse_output(b"Content-Type: text/plain\r\nX-Script: yes\r\n\r\nhello\n").unwrap();
+        assert_eq!(200, response.status());
+        assert_eq!(Some("text/plain"), response.headers().get("Content-Type"));
+        assert_eq!(Some("yes"), response.headers().get("X-Script"));
+        assert_eq!(b"hello\n", response.body());
+
+        let not_found = parse_output(b"Status: 404 Not Found\nContent-Type: text/plain\n\nmissing").unwrap();
+        assert_eq!(404, not_found.status());
+        assert!(!not_found.headers().contains("Status"));
+
+        let redirect = parse_output(b"Location: https://example.com/\n\n").unwrap();
+        assert_eq!(302, redirect.status());
+    }
+
+    #[test]
+    fn rejects_malformed_output() {
+        assert!(parse_output(b"hello world\n").is_none());
+        assert!(parse_output(b"Content-Type: text/plain\nno blank line").is_none());
+        assert!(parse_output(b"X-Only: header\n\nbody").is_none());
+        assert!(parse_output(b"Status: abc\n\n").is_none());
+        assert!(parse_output(b"Status: 101 Switching Protocols\n\n").is_none());
+        assert!(parse_output(b"Status: 600\n\n").is_none());
+    }
+
+    #[test]
+    // 分帧和逐跳头部由服务器决定,程序输出的会被丢弃
+    fn drops_framing_headers() {
+        let output = b"Content-Type: text/plain\nContent-Length: 999\nConnection: keep-alive\ntransfer-encoding: chunked\nX-Kept: 1\n\nhi";
+        let response = parse_output(output).unwrap();
+        for name in ["Content-Length", "Connection", "Transfer-Encoding"] {
+            assert!(!response.headers().contains(name), "{} should be dropped", name);
+        }
+        assert_eq!(Some("1"), response.headers().get("X-Kept"));
+
+        let mut written = Vec::new();
+        parse_output(output).unwrap().write_to(&mut written, true).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains("Content-Length: 2\r\n"));
+        assert!(written.contains("Connection: close\r\n"));
+    }
+}
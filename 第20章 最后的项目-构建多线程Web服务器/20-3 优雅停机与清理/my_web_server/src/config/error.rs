@@ -0,0 +1,37 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// 加载配置文件时可能出现的错误
+#[derive(Debug)]
+pub enum ConfigError {
+    /// 配置文件无法读取
+    Io { path: PathBuf, source: io::Error },
+    /// 配置文件不是合法的JSON或字段不正确
+    Parse(serde_json::Error),
+    /// 配置的值不合法,例如静态目录不存在
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "failed to read config {}: {}", path.display(), source)
+            }
+            ConfigError::Parse(e) => write!(f, "invalid config: {}", e),
+            ConfigError::Invalid(message) => write!(f, "invalid config: {}", message),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Parse(e) => Some(e),
+            ConfigError::Invalid(_) => None,
+        }
+    }
+}
@@ -0,0 +1,8 @@
+pub mod settings;
+pub use settings::{AuthSettings, CgiSettings, Config, RateLimitSettings, VhostSettings};
+
+pub mod error;
+pub use error::ConfigError;
+
+pub mod watcher;
+pub use watcher::ConfigWatcher;
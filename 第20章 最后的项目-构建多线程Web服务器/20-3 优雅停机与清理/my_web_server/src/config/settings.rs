@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Deserialize;
+use crate::auth::{BearerAuth, Htpasswd};
+use crate::config::ConfigError;
+use crate::form::MultipartLimits;
+use crate::server::Limits;
+
+/// 服务器配置,从JSON文件加载,例如:
+/// `{"templates": "templates", "static_roots": {"/files": "public"}, "rate_limit": {"rate": 5.0, "burst": 20}}`
+/// 监听地址和worker数量不在其中,因为它们无法在运行时修改
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// 模板目录
+    pub templates: PathBuf,
+    /// URL前缀 -> 静态文件目录
+    pub static_roots: BTreeMap<String, PathBuf>,
+    /// URL前缀 -> CGI程序
+    pub cgi: BTreeMap<String, CgiSettings>,
+    /// URL前缀 -> 认证方式,前缀下的所有路径都需要认证,包括静态文件、CGI程序和虚拟主机
+    pub auth: BTreeMap<String, AuthSettings>,
+    /// 虚拟主机,按顺序匹配`Host`头部,没有主机匹配时由上面配置的路由处理
+    pub vhosts: Vec<VhostSettings>,
+    /// 按客户端IP限流,不设置时不限流.信任的代理和闲置超时同样可以重新加载
+    pub rate_limit: Option<RateLimitSettings>,
+    /// 请求body的最大字节数
+    pub max_body_size: usize,
+    /// `multipart/form-data`请求体的最大字节数,这样的请求边读边解析,较大的文件写入临时文件,因此可以大于`max_body_size`.
+    /// 单个文件的大小另外受`MultipartLimits::max_part_size`限制
+    pub max_upload_size: usize,
+    /// 读取请求的超时时间(秒)
+    pub read_timeout_secs: u64,
+    /// 是否接受h2c(先验知识)连接,只在启动时读取,重新加载时修改不会生效
+    pub http2: bool,
+}
+
+/// 限流设置,例如:
+/// `{"rate": 5.0, "burst": 20, "trusted_proxies": ["127.0.0.1"], "idle_timeout_secs": 300}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitSettings {
+    /// 每秒补充的令牌数
+    pub rate: f64,
+    /// 令牌桶的容量
+    pub burst: u32,
+    /// 受信任的反向代理,来自这些地址的请求按`X-Forwarded-For`中的客户端限流,默认为空
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// 令牌桶闲置多久(秒)后被清理,默认为60秒
+    #[serde(default = "default_idle_timeout")]
+    pub idle_timeout_secs: u64,
+}
+
+fn default_idle_timeout() -> u64 {
+    60
+}
+
+/// 认证方式,`htpasswd`和`tokens`必须设置且只能设置其中一个,例如:
+/// `{"htpasswd": "admin.htpasswd", "realm": "admin"}`使用Basic认证,`{"tokens": "api.tokens"}`使用Bearer令牌
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AuthSettings {
+    /// `WWW-Authenticate`中的realm,默认为URL前缀
+    #[serde(default)]
+    pub realm: Option<String>,
+    /// htpasswd格式的用户文件,见`Htpasswd`
+    #[serde(default)]
+    pub htpasswd: Option<PathBuf>,
+    /// 令牌文件,格式见`BearerAuth::load()`
+    #[serde(default)]
+    pub tokens: Option<PathBuf>,
+}
+
+/// 只提供静态文件的虚拟主机,例如:
+/// `{"names": ["docs.internal", "*.docs.internal"], "root": "sites/docs", "access_log": "logs/docs.log"}`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VhostSettings {
+    /// 主机名和别名,`*.example.com`匹配所有子域名
+    pub names: Vec<String>,
+    /// 文档根目录
+    pub root: PathBuf,
+    /// 该主机的访问日志文件,不设置时不记录
+    #[serde(default)]
+    pub access_log: Option<PathBuf>,
+    /// 没有主机匹配时是否交给该主机处理,最多只能有一个默认主机
+    #[serde(default)]
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CgiSettings {
+    pub program: PathBuf,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// 程序运行的超时时间(秒),默认为30秒
+    #[serde(default = "default_cgi_timeout")]
+    pub timeout_secs: u64,
+}
+
+fn default_cgi_timeout() -> u64 {
+    30
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        let limits = Limits::default();
+        Config {
+            templates: PathBuf::from("templates"),
+            static_roots: BTreeMap::new(),
+            cgi: BTreeMap::new(),
+            auth: BTreeMap::new(),
+            vhosts: Vec::new(),
+            rate_limit: None,
+            max_body_size: limits.max_body_size,
+            max_upload_size: limits.multipart.max_size,
+            read_timeout_secs: limits.read_timeout.as_secs(),
+            http2: false,
+        }
+    }
+}
+
+impl Config {
+    /// 读取并校验配置文件
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Config::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, ConfigError> {
+        let config: Config = serde_json::from_str(contents).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// 检查配置的值,保证用它构建路由时不会panic
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |message: String| Err(ConfigError::Invalid(message));
+
+        if !self.templates.is_dir() {
+            return invalid(format!("templates directory {} does not exist", self.templates.display()));
+        }
+        for (prefix, root) in &self.static_roots {
+            check_prefix(prefix)?;
+            if !root.is_dir() {
+                return invalid(format!("static root {} does not exist", root.display()));
+            }
+        }
+        for (prefix, cgi) in &self.cgi {
+            check_prefix(prefix)?;
+            if !cgi.program.is_file() {
+                return invalid(format!("CGI program {} does not exist", cgi.program.display()));
+            }
+            if cgi.timeout_secs == 0 {
+                return invalid(format!("timeout_secs of CGI program {} must be positive", cgi.program.display()));
+            }
+        }
+        for (prefix, auth) in &self.auth {
+            check_prefix(prefix)?;
+            // 预先加载一次文件,格式错误时拒绝新配置而不是在运行时才发现
+            let loaded = match (&auth.htpasswd, &auth.tokens) {
+                (Some(htpasswd), None) => Htpasswd::open(htpasswd).map(|_| ()),
+                (None, Some(tokens)) => BearerAuth::new("").load(tokens).map(|_| ()),
+                _ => return invalid(format!("auth for `{}` needs exactly one of htpasswd and tokens", prefix)),
+            };
+            if let Err(e) = loaded {
+                return invalid(format!("failed to load credentials for `{}`: {}", prefix, e));
+            }
+        }
+        for vhost in &self.vhosts {
+            if vhost.names.is_empty() || vhost.names.iter().any(|name| name.is_empty()) {
+                return invalid(format!("virtual host with root {} needs at least one non-empty name", vhost.root.display()));
+            }
+            if !vhost.root.is_dir() {
+                return invalid(format!("document root {} does not exist", vhost.root.display()));
+            }
+            if let Some(log) = &vhost.access_log {
+                // 日志文件不存在时会被创建,但所在的目录必须存在
+                let dir = log.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+                if !dir.is_dir() {
+                    return invalid(format!("directory of access log {} does not exist", log.display()));
+                }
+            }
+        }
+        if self.vhosts.iter().filter(|vhost| vhost.default).count() > 1 {
+            return invalid("at most one virtual host can be the default".to_string());
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            if !rate_limit.rate.is_finite() || rate_limit.rate <= 0.0 || rate_limit.burst == 0 {
+                return invalid("rate_limit.rate and rate_limit.burst must be positive".to_string());
+            }
+            if rate_limit.idle_timeout_secs == 0 {
+                return invalid("rate_limit.idle_timeout_secs must be positive".to_string());
+            }
+            // `0.0.0.0`和`::`不会是对端地址,写在这里多半是把监听地址误当成了代理地址
+            if let Some(proxy) = rate_limit.trusted_proxies.iter().find(|proxy| proxy.is_unspecified()) {
+                return invalid(format!("trusted proxy {} is not a valid peer address", proxy));
+            }
+        }
+        if self.max_body_size == 0 || self.max_upload_size == 0 || self.read_timeout_secs == 0 {
+            return invalid("max_body_size, max_upload_size and read_timeout_secs must be positive".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_body_size: self.max_body_size,
+            read_timeout: Duration::from_secs(self.read_timeout_secs),
+            multipart: MultipartLimits { max_size: self.max_upload_size, ..MultipartLimits::default() },
+        }
+    }
+}
+
+/// 前缀会被拼接成路由模式,因此不能包含路由参数
+fn check_prefix(prefix: &str) -> Result<(), ConfigError> {
+    if prefix.starts_with('/') && !prefix.contains([':', '*']) {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(format!("prefix `{}` must start with `/` and contain no `:` or `*`", prefix)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_validates() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let config = Config::parse(&format!(
+            r#"{{"templates": "{dir}/templates", "static_roots": {{"/assets": "{dir}/src"}}, "rate_limit": {{"rate": 2.5, "burst": 10}}}}"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            Some(RateLimitSettings { rate: 2.5, burst: 10, trusted_proxies: Vec::new(), idle_timeout_secs: 60 }),
+            config.rate_limit
+        );
+        let proxied = Config::parse(&format!(
+            r#"{{"templates": "{dir}/templates", "rate_limit": {{"rate": 1, "burst": 1, "trusted_proxies": ["10.0.0.1", "::1"], "idle_timeout_secs": 300}}}}"#,
+        ))
+        .unwrap();
+        let rate_limit = proxied.rate_limit.unwrap();
+        assert_eq!(vec!["10.0.0.1".parse::<IpAddr>().unwrap(), "::1".parse().unwrap()], rate_limit.trusted_proxies);
+        assert_eq!(300, rate_limit.idle_timeout_secs);
+        assert_eq!(Limits::default(), config.limits());
+
+        let error = |json: String| Config::parse(&json).unwrap_err().to_string();
+        assert!(error(format!(r#"{{"templates": "{dir}/missing"}}"#)).contains("does not exist"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "static_roots": {{"/a/*x": "{dir}"}}}}"#)).contains("prefix"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "rate_limit": {{"rate": 0, "burst": 1}}}}"#)).contains("positive"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "rate_limit": {{"rate": 1, "burst": 1, "idle_timeout_secs": 0}}}}"#))
+            .contains("idle_timeout_secs"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "rate_limit": {{"rate": 1, "burst": 1, "trusted_proxies": ["0.0.0.0"]}}}}"#))
+            .contains("trusted proxy"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "rate_limit": {{"rate": 1, "burst": 1, "trusted_proxies": ["proxy"]}}}}"#))
+            .contains("invalid IP address"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "cgi": {{"/report": {{"program": "{dir}/src"}}}}}}"#)).contains("CGI program"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "vhosts": [{{"names": [], "root": "{dir}"}}]}}"#)).contains("name"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "vhosts": [{{"names": ["a"], "root": "{dir}/missing"}}]}}"#)).contains("document root"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "vhosts": [{{"names": ["a"], "root": "{dir}", "access_log": "{dir}/missing/a.log"}}]}}"#)).contains("access log"));
+        assert!(error(format!(
+            r#"{{"templates": "{dir}/templates", "vhosts": [{{"names": ["a"], "root": "{dir}", "default": true}}, {{"names": ["b"], "root": "{dir}", "default": true}}]}}"#
+        ))
+        .contains("default"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "auth": {{"/admin": {{}}}}}}"#)).contains("exactly one"));
+        assert!(error(format!(r#"{{"templates": "{dir}/templates", "auth": {{"/admin": {{"htpasswd": "{dir}/Cargo.toml"}}}}}}"#))
+            .contains("failed to load credentials"));
+        assert!(error(r#"{"workers": 8}"#.to_string()).contains("unknown field"));
+    }
+}
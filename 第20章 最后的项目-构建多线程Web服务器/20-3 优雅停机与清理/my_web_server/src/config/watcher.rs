@@ -0,0 +1,72 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::config::Config;
+
+/// 监视配置文件,文件被修改或进程收到`SIGHUP`时重新加载
+/// 新配置校验失败时只记录错误,继续使用旧配置
+pub struct ConfigWatcher {
+    path: PathBuf,
+    interval: Duration,
+}
+
+impl ConfigWatcher {
+    pub fn new<P: Into<PathBuf>>(path: P) -> ConfigWatcher {
+        ConfigWatcher { path: path.into(), interval: Duration::from_secs(1) }
+    }
+
+    /// 检查文件修改时间的间隔,默认为1秒
+    pub fn interval(mut self, interval: Duration) -> ConfigWatcher {
+        self.interval = interval;
+        self
+    }
+
+    /// 启动监视线程,每次成功加载新配置后调用`on_reload`
+    pub fn spawn<F>(self, mut on_reload: F) -> io::Result<thread::JoinHandle<()>>
+    where
+        F: FnMut(Config) + Send + 'static,
+    {
+        let hangup = Arc::new(AtomicBool::new(false));
+        register_sighup(&hangup)?;
+
+        thread::Builder::new().name("config-watcher".to_string()).spawn(move || {
+            let mut last_modified = modified(&self.path);
+            loop {
+                thread::sleep(self.interval);
+                let current = modified(&self.path);
+                let signaled = hangup.swap(false, Ordering::SeqCst);
+                if current == last_modified && !signaled {
+                    continue;
+                }
+                last_modified = current;
+
+                match Config::load(&self.path) {
+                    Ok(config) => {
+                        println!("reloaded config from {}", self.path.display());
+                        on_reload(config);
+                    }
+                    Err(e) => eprintln!("rejected new config, keeping the old one: {}", e),
+                }
+            }
+        })
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+#[cfg(unix)]
+fn register_sighup(flag: &Arc<AtomicBool>) -> io::Result<()> {
+    signal_hook::flag::register(signal_hook::consts::SIGHUP, flag.clone()).map(|_| ())
+}
+
+/// 非Unix平台没有`SIGHUP`,只能通过修改文件触发重新加载
+#[cfg(not(unix))]
+fn register_sighup(_flag: &Arc<AtomicBool>) -> io::Result<()> {
+    Ok(())
+}
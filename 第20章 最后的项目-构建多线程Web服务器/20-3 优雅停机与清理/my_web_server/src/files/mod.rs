@@ -0,0 +1,8 @@
+pub mod serve_dir;
+pub use serve_dir::ServeDir;
+
+pub mod range;
+pub use range::{ByteRange, Ranges};
+
+pub mod mime;
+pub use mime::content_type;
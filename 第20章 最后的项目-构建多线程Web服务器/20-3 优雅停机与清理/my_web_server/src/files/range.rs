@@ -0,0 +1,158 @@
+/// 一次请求最多接受的范围数,防止客户端用大量细碎的范围放大响应
+const MAX_RANGES: usize = 32;
+
+/// 闭区间`[start, end]`表示的字节范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// 范围内的字节数,闭区间至少包含一个字节
+    pub(crate) fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    /// `Content-Range`头部的值,例如`bytes 0-499/1234`
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+/// 解析`Range`头部的结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum Ranges {
+    /// 头部格式不正确或不是`bytes`单位,按规范忽略它并返回完整内容
+    Full,
+    /// 可以满足的范围,已排序并合并了重叠或相邻的范围
+    Partial(Vec<ByteRange>),
+    /// 所有范围都超出了内容长度,应返回`416 Range Not Satisfiable`
+    Unsatisfiable,
+}
+
+impl Ranges {
+    /// 针对长度为`len`的内容解析`Range`头部,例如`bytes=0-499`、`bytes=500-`、`bytes=-500`
+    pub fn parse(header: &str, len: u64) -> Ranges {
+        let specs = match header.trim().strip_prefix("bytes=") {
+            Some(specs) => specs,
+            None => return Ranges::Full,
+        };
+
+        let mut ranges = Vec::new();
+        let mut count = 0;
+        for spec in specs.split(',').map(str::trim).filter(|spec| !spec.is_empty()) {
+            count += 1;
+            if count > MAX_RANGES {
+                return Ranges::Full;
+            }
+            match parse_spec(spec, len) {
+                Some(Some(range)) => ranges.push(range),
+                Some(None) => {}
+                None => return Ranges::Full,
+            }
+        }
+
+        if count == 0 {
+            Ranges::Full
+        } else if ranges.is_empty() {
+            Ranges::Unsatisfiable
+        } else {
+            Ranges::Partial(coalesce(ranges))
+        }
+    }
+}
+
+/// 解析单个范围
+/// 格式错误时返回`None`,格式正确但超出内容长度时返回`Some(None)`
+fn parse_spec(spec: &str, len: u64) -> Option<Option<ByteRange>> {
+    let (first, last) = spec.split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+
+    if first.is_empty() {
+        // `-n`表示最后n个字节
+        let suffix: u64 = parse_number(last)?;
+        if suffix == 0 || len == 0 {
+            return Some(None);
+        }
+        return Some(Some(ByteRange { start: len.saturating_sub(suffix), end: len - 1 }));
+    }
+
+    let start: u64 = parse_number(first)?;
+    let end = if last.is_empty() {
+        u64::MAX
+    } else {
+        let end = parse_number(last)?;
+        if end < start {
+            return None;
+        }
+        end
+    };
+    if start >= len {
+        return Some(None);
+    }
+    Some(Some(ByteRange { start, end: end.min(len - 1) }))
+}
+
+/// 只接受十进制数字,`str::parse`会额外接受`+`号
+fn parse_number(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn coalesce(mut ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<ByteRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end.saturating_add(1) => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start: u64, end: u64) -> ByteRange {
+        ByteRange { start, end }
+    }
+
+    #[test]
+    fn single_ranges() {
+        assert_eq!(Ranges::Partial(vec![range(0, 499)]), Ranges::parse("bytes=0-499", 1000));
+        assert_eq!(Ranges::Partial(vec![range(500, 999)]), Ranges::parse("bytes=500-", 1000));
+        assert_eq!(Ranges::Partial(vec![range(900, 999)]), Ranges::parse("bytes=-100", 1000));
+        // 超出末尾的部分被截断
+        assert_eq!(Ranges::Partial(vec![range(990, 999)]), Ranges::parse("bytes=990-2000", 1000));
+        assert_eq!(Ranges::Partial(vec![range(0, 999)]), Ranges::parse("bytes=-5000", 1000));
+    }
+
+    #[test]
+    // 重叠和相邻的范围被合并,超出长度的范围被丢弃
+    fn multiple_ranges() {
+        assert_eq!(
+            Ranges::Partial(vec![range(0, 9), range(20, 39)]),
+            Ranges::parse("bytes=20-29, 0-9, 30-39, 5000-", 1000)
+        );
+        assert_eq!(Ranges::Partial(vec![range(0, 19)]), Ranges::parse("bytes=0-9,10-19", 1000));
+    }
+
+    #[test]
+    fn unsatisfiable_and_invalid() {
+        assert_eq!(Ranges::Unsatisfiable, Ranges::parse("bytes=1000-", 1000));
+        assert_eq!(Ranges::Unsatisfiable, Ranges::parse("bytes=-0", 1000));
+        assert_eq!(Ranges::Unsatisfiable, Ranges::parse("bytes=0-", 0));
+
+        assert_eq!(Ranges::Full, Ranges::parse("items=0-9", 1000));
+        assert_eq!(Ranges::Full, Ranges::parse("bytes=9-0", 1000));
+        assert_eq!(Ranges::Full, Ranges::parse("bytes=+1-2", 1000));
+        assert_eq!(Ranges::Full, Ranges::parse("bytes=", 1000));
+        let many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(Ranges::Full, Ranges::parse(&format!("bytes={}", many), 1000));
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::hash_map::RandomState;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use crate::files::{content_type, ByteRange, Ranges};
+use crate::http::{HttpError, IntoResponse, Method, Request, Response};
+
+/// 提供某个目录下的静态文件
+/// 支持`Range`请求(单个范围返回`206 Partial Content`,多个范围返回`multipart/byteranges`),
+/// 以及基于`ETag`的`If-None-Match`和`If-Range`.文件内容在写响应时才读取,不会整个读入内存
+/// 路由模式需要以`*path`结尾,例如`router.get("/files/*path", move |request| files.serve(request))`
+#[derive(Debug, Clone)]
+pub struct ServeDir {
+    root: PathBuf,
+}
+
+impl ServeDir {
+    pub fn new<P: Into<PathBuf>>(root: P) -> ServeDir {
+        ServeDir { root: root.into() }
+    }
+
+    /// 处理请求,文件路径取自路由参数`path`
+    /// 请求的是目录时返回其中的`index.html`
+    pub fn serve(&self, request: &Request) -> Response {
+        if !matches!(request.method(), Method::Get | Method::Head) {
+            return Response::text(405, "Method Not Allowed").with_header("Allow", "GET, HEAD");
+        }
+        let relative = request.param("path").unwrap_or_else(|| request.path());
+        let path = match self.resolve(relative) {
+            Some(path) => path,
+            None => return HttpError::not_found("file not found").into_response(),
+        };
+        match serve_file(request, &path) {
+            Ok(response) => response,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                HttpError::not_found("file not found").into_response()
+            }
+            Err(e) => {
+                eprintln!("failed to serve {}: {}", path.display(), e);
+                HttpError::internal("failed to read file").into_response()
+            }
+        }
+    }
+
+    /// 将请求路径映射为根目录下的文件
+    /// 拒绝`..`等可能逃出根目录的路径段
+    fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        let mut path = self.root.clone();
+        for segment in relative.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return None,
+                _ if segment.contains(['\\', '\0']) => return None,
+                _ => path.push(segment),
+            }
+        }
+        if path.is_dir() {
+            path.push("index.html");
+        }
+        Some(path)
+    }
+}
+
+fn serve_file(request: &Request, path: &Path) -> io::Result<Response> {
+    let metadata = fs::metadata(path)?;
+    if !metadata.is_file() {
+        return Err(io::ErrorKind::NotFound.into());
+    }
+    let len = metadata.len();
+    let etag = etag(&metadata);
+    let content_type = content_type(path);
+
+    if request.header("If-None-Match").is_some_and(|tags| etag_matches(tags, &etag)) {
+        return Ok(Response::new(304).with_header("ETag", etag));
+    }
+
+    let ranges = match request.header("Range") {
+        // 规范只为GET定义了Range;If-Range与当前版本不符时返回完整的新内容
+        Some(range) if *request.method() == Method::Get
+            && request.header("If-Range").is_none_or(|tag| tag.trim() == etag) =>
+        {
+            Ranges::parse(range, len)
+        }
+        _ => Ranges::Full,
+    };
+
+    let mut file = File::open(path)?;
+    let response = match ranges {
+        Ranges::Full => Response::ok()
+            .with_header("Content-Type", content_type)
+            .with_reader(file, len),
+        Ranges::Unsatisfiable => HttpError::new(416, "range not satisfiable")
+            .into_response()
+            .with_header("Content-Range", format!("bytes */{}", len)),
+        Ranges::Partial(ranges) if ranges.len() == 1 => {
+            let range = ranges[0];
+            file.seek(SeekFrom::Start(range.start))?;
+            Response::new(206)
+                .with_header("Content-Type", content_type)
+                .with_header("Content-Range", range.content_range(len))
+                .with_reader(file, range.len())
+        }
+        Ranges::Partial(ranges) => {
+            let boundary = boundary();
+            let body = ByteRangesReader::new(file, &ranges, len, content_type, &boundary);
+            let body_len = body.len();
+            Response::new(206)
+                .with_header("Content-Type", format!("multipart/byteranges; boundary={}", boundary))
+                .with_reader(body, body_len)
+        }
+    };
+    Ok(response.with_header("Accept-Ranges", "bytes").with_header("ETag", etag))
+}
+
+/// 由文件长度和修改时间生成的`ETag`,文件被修改后随之改变
+fn etag(metadata: &fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|duration| duration.as_nanos())
+        .unwrap_or(0);
+    format!("\"{:x}-{:x}\"", metadata.len(), modified)
+}
+
+/// `If-None-Match`可以是`*`或逗号分隔的多个`ETag`,弱比较时忽略`W/`前缀
+fn etag_matches(header: &str, etag: &str) -> bool {
+    header.split(',').map(str::trim).any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+fn boundary() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    format!("byteranges_{:016x}", hasher.finish())
+}
+
+/// `multipart/byteranges`格式的body
+/// 由各部分的头部和文件中对应的范围依次拼接而成,读取时才从文件中读出数据
+struct ByteRangesReader {
+    file: File,
+    segments: VecDeque<Segment>,
+}
+
+enum Segment {
+    Bytes(Cursor<Vec<u8>>),
+    File { offset: u64, remaining: u64 },
+}
+
+impl ByteRangesReader {
+    fn new(file: File, ranges: &[ByteRange], total: u64, content_type: &str, boundary: &str) -> ByteRangesReader {
+        let mut segments = VecDeque::new();
+        for (i, range) in ranges.iter().enumerate() {
+            // 第一个分隔符之前不需要换行
+            let separator = if i == 0 { "" } else { "\r\n" };
+            let head = format!(
+                "{}--{}\r\nContent-Type: {}\r\nContent-Range: {}\r\n\r\n",
+                separator,
+                boundary,
+                content_type,
+                range.content_range(total)
+            );
+            segments.push_back(Segment::Bytes(Cursor::new(head.into_bytes())));
+            segments.push_back(Segment::File { offset: range.start, remaining: range.len() });
+        }
+        let tail = format!("\r\n--{}--\r\n", boundary);
+        segments.push_back(Segment::Bytes(Cursor::new(tail.into_bytes())));
+        ByteRangesReader { file, segments }
+    }
+
+    fn len(&self) -> u64 {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Bytes(bytes) => bytes.get_ref().len() as u64,
+                Segment::File { remaining, .. } => *remaining,
+            })
+            .sum()
+    }
+}
+
+impl Read for ByteRangesReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while let Some(segment) = self.segments.front_mut() {
+            let n = match segment {
+                Segment::Bytes(bytes) => bytes.read(buf)?,
+                Segment::File { offset, remaining } => {
+                    if *remaining == 0 {
+                        0
+                    } else {
+                        self.file.seek(SeekFrom::Start(*offset))?;
+                        let max = buf.len().min(usize::try_from(*remaining).unwrap_or(usize::MAX));
+                        let n = self.file.read(&mut buf[..max])?;
+                        if n == 0 {
+                            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "file was truncated"));
+                        }
+                        *offset += n as u64;
+                        *remaining -= n as u64;
+                        n
+                    }
+                }
+            };
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            self.segments.pop_front();
+        }
+        Ok(0)
+    }
+}
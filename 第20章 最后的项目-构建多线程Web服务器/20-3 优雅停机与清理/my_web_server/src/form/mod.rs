@@ -0,0 +1,8 @@
+pub mod urlencoded;
+pub use urlencoded::Form;
+
+pub mod multipart;
+pub use multipart::{Multipart, MultipartLimits, Part, PartData};
+
+pub mod temp_file;
+pub use temp_file::TempFile;
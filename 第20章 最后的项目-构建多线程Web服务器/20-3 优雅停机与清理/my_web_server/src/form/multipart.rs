@@ -0,0 +1,417 @@
+use std::env;
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::form::TempFile;
+use crate::http::{Headers, HttpError};
+
+/// 解析`multipart/form-data`时的限制
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipartLimits {
+    /// 整个请求体的最大字节数.服务器边读边解析这样的请求,因此它不受`Limits::max_body_size`限制
+    pub max_size: usize,
+    /// 最多允许的part数量
+    pub max_parts: usize,
+    /// 单个part数据的最大字节数
+    pub max_part_size: usize,
+    /// 单个part头部的最大字节数
+    pub max_header_size: usize,
+    /// part数据超过该字节数时转存到临时文件,而不是保存在内存中
+    pub memory_threshold: usize,
+    /// 临时文件所在的目录,默认为系统临时目录
+    pub temp_dir: PathBuf,
+}
+
+impl Default for MultipartLimits {
+    fn default() -> MultipartLimits {
+        MultipartLimits {
+            max_size: 64 * 1024 * 1024,
+            max_parts: 100,
+            max_part_size: 10 * 1024 * 1024,
+            max_header_size: 8 * 1024,
+            memory_threshold: 64 * 1024,
+            temp_dir: env::temp_dir(),
+        }
+    }
+}
+
+/// part的数据,较小的保存在内存中,较大的保存在临时文件中
+#[derive(Debug)]
+pub enum PartData {
+    Memory(Vec<u8>),
+    File(TempFile),
+}
+
+/// `multipart/form-data`中的一个part
+#[derive(Debug)]
+pub struct Part {
+    name: String,
+    filename: Option<String>,
+    headers: Headers,
+    data: PartData,
+    size: usize,
+}
+
+impl Part {
+    /// `Content-Disposition`中的`name`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 上传文件的原始文件名,普通字段没有文件名
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    pub fn content_type(&self) -> Option<&str> {
+        self.headers.get("Content-Type")
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn data(&self) -> &PartData {
+        &self.data
+    }
+
+    /// 数据的字节数
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 数据保存在内存中且是合法的UTF-8时,以字符串形式返回
+    pub fn text(&self) -> Option<&str> {
+        match &self.data {
+            PartData::Memory(bytes) => std::str::from_utf8(bytes).ok(),
+            PartData::File(_) => None,
+        }
+    }
+
+    /// 从头读取数据,无论数据保存在内存还是临时文件中
+    pub fn reader(&self) -> io::Result<Box<dyn Read + '_>> {
+        match &self.data {
+            PartData::Memory(bytes) => Ok(Box::new(Cursor::new(bytes))),
+            PartData::File(file) => Ok(Box::new(file.open()?)),
+        }
+    }
+
+    /// 将数据保存到`dest`,保存在临时文件中的数据会被直接移动过去
+    pub fn persist<P: AsRef<Path>>(self, dest: P) -> io::Result<()> {
+        match self.data {
+            PartData::Memory(bytes) => fs::write(dest, bytes),
+            PartData::File(file) => file.persist(dest),
+        }
+    }
+}
+
+/// 解析后的`multipart/form-data`请求体
+#[derive(Debug, Default)]
+pub struct Multipart {
+    parts: Vec<Part>,
+}
+
+impl Multipart {
+    /// 从`reader`中边读边解析,超过`memory_threshold`的part会被写入临时文件,解析本身只需要固定大小的缓冲区.
+    /// `reader`读到末尾就是请求体的结束,`max_size`由调用者限制(例如用`Read::take()`截取`Content-Length`个字节)
+    /// 超出限制时返回413,格式错误时返回400
+    pub fn parse<R: Read>(reader: R, boundary: &str, limits: &MultipartLimits) -> Result<Multipart, HttpError> {
+        if boundary.is_empty() || boundary.len() > 70 {
+            return Err(HttpError::bad_request("invalid multipart boundary"));
+        }
+
+        let mut source = Source::new(reader);
+        let first = format!("--{}", boundary);
+        let delimiter = format!("\r\n--{}", boundary);
+
+        // 第一个分隔符之前可能有前言(preamble),直接跳过
+        loop {
+            let line = source.read_line(limits.max_header_size)?;
+            if line == first.as_bytes() {
+                break;
+            }
+            if line == format!("{}--", first).as_bytes() {
+                return Ok(Multipart::default());
+            }
+        }
+
+        let mut parts = Vec::new();
+        loop {
+            if parts.len() >= limits.max_parts {
+                return Err(HttpError::payload_too_large(format!("more than {} parts", limits.max_parts)));
+            }
+            parts.push(read_part(&mut source, delimiter.as_bytes(), limits)?);
+
+            // 分隔符之后是`--`表示结束,否则是换行,后面跟着下一个part
+            let suffix = source.read_line(limits.max_header_size)?;
+            let suffix = trim_trailing_whitespace(&suffix);
+            if suffix.starts_with(b"--") {
+                break;
+            }
+            if !suffix.is_empty() {
+                return Err(HttpError::bad_request("malformed multipart delimiter"));
+            }
+        }
+
+        Ok(Multipart { parts })
+    }
+
+    pub fn parts(&self) -> &[Part] {
+        &self.parts
+    }
+
+    pub fn into_parts(self) -> Vec<Part> {
+        self.parts
+    }
+
+    /// 取得名为`name`的第一个part
+    pub fn part(&self, name: &str) -> Option<&Part> {
+        self.parts.iter().find(|part| part.name == name)
+    }
+
+    /// 取得名为`name`的普通字段的值
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.part(name).and_then(Part::text)
+    }
+}
+
+fn read_part<R: Read>(source: &mut Source<R>, delimiter: &[u8], limits: &MultipartLimits) -> Result<Part, HttpError> {
+    let mut headers = Headers::new();
+    let mut header_size = 0;
+    loop {
+        let line = source.read_line(limits.max_header_size)?;
+        if line.is_empty() {
+            break;
+        }
+        header_size += line.len();
+        if header_size > limits.max_header_size {
+            return Err(HttpError::new(431, "multipart headers too large"));
+        }
+        let line = String::from_utf8_lossy(&line);
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| HttpError::bad_request("malformed multipart header"))?;
+        headers.append(name.trim(), value.trim());
+    }
+
+    let disposition = headers
+        .get("Content-Disposition")
+        .ok_or_else(|| HttpError::bad_request("multipart part without Content-Disposition"))?;
+    let name = disposition_param(disposition, "name")
+        .ok_or_else(|| HttpError::bad_request("multipart part without a name"))?;
+    let filename = disposition_param(disposition, "filename");
+
+    let mut sink = Sink { data: PartData::Memory(Vec::new()), size: 0, limits };
+    source.read_until_delimiter(delimiter, &mut sink)?;
+
+    Ok(Part { name, filename, headers, data: sink.data, size: sink.size })
+}
+
+/// 从`form-data; name="field"; filename="a.txt"`中取出参数,参数值可以带引号
+fn disposition_param(disposition: &str, key: &str) -> Option<String> {
+    disposition.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case(key) {
+            return None;
+        }
+        let value = value.trim();
+        let value = match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            Some(quoted) => quoted.replace("\\\"", "\"").replace("\\\\", "\\"),
+            None => value.to_string(),
+        };
+        Some(value)
+    })
+}
+
+fn trim_trailing_whitespace(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().rposition(|b| !matches!(b, b' ' | b'\t')).map_or(0, |i| i + 1);
+    &bytes[..end]
+}
+
+/// 接收part数据,数据量超过阈值时从内存切换到临时文件
+struct Sink<'a> {
+    data: PartData,
+    size: usize,
+    limits: &'a MultipartLimits,
+}
+
+impl<'a> Sink<'a> {
+    fn write(&mut self, bytes: &[u8]) -> Result<(), HttpError> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        self.size += bytes.len();
+        if self.size > self.limits.max_part_size {
+            return Err(HttpError::payload_too_large(format!(
+                "multipart part exceeds {} bytes",
+                self.limits.max_part_size
+            )));
+        }
+
+        let io_error = |e: io::Error| HttpError::internal(format!("failed to write temp file: {}", e));
+        if let PartData::Memory(buffer) = &mut self.data {
+            if self.size <= self.limits.memory_threshold {
+                buffer.extend_from_slice(bytes);
+                return Ok(());
+            }
+            let mut file = TempFile::new_in(&self.limits.temp_dir).map_err(io_error)?;
+            file.file_mut().write_all(buffer).map_err(io_error)?;
+            self.data = PartData::File(file);
+        }
+        if let PartData::File(file) = &mut self.data {
+            file.file_mut().write_all(bytes).map_err(io_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// 带缓冲的读取器,支持按行读取和读取到分隔符为止
+struct Source<R> {
+    inner: R,
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read> Source<R> {
+    fn new(inner: R) -> Source<R> {
+        Source { inner, buffer: Vec::with_capacity(16 * 1024), pos: 0 }
+    }
+
+    /// 读入更多数据,已经读到末尾时返回`false`
+    fn fill(&mut self) -> Result<bool, HttpError> {
+        if self.pos > 0 {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+        }
+        let mut chunk = [0; 8 * 1024];
+        let n = self
+            .inner
+            .read(&mut chunk)
+            .map_err(|e| HttpError::bad_request(format!("failed to read multipart body: {}", e)))?;
+        self.buffer.extend_from_slice(&chunk[..n]);
+        Ok(n > 0)
+    }
+
+    /// 读取一行(不含行尾的`\r\n`)
+    fn read_line(&mut self, max: usize) -> Result<Vec<u8>, HttpError> {
+        loop {
+            let available = &self.buffer[self.pos..];
+            if let Some(i) = available.iter().position(|&b| b == b'\n') {
+                let mut line = available[..i].to_vec();
+                self.pos += i + 1;
+                if line.ends_with(b"\r") {
+                    line.pop();
+                }
+                return Ok(line);
+            }
+            if available.len() > max {
+                return Err(HttpError::new(431, "multipart line too long"));
+            }
+            if !self.fill()? {
+                return Err(HttpError::bad_request("unexpected end of multipart body"));
+            }
+        }
+    }
+
+    /// 将分隔符之前的数据交给`sink`,并消耗掉分隔符本身
+    /// 缓冲区末尾可能是分隔符的前半部分,因此总是保留最后`delimiter.len() - 1`个字节等待更多数据
+    fn read_until_delimiter(&mut self, delimiter: &[u8], sink: &mut Sink) -> Result<(), HttpError> {
+        loop {
+            let available = &self.buffer[self.pos..];
+            if let Some(i) = available.windows(delimiter.len()).position(|window| window == delimiter) {
+                sink.write(&available[..i])?;
+                self.pos += i + delimiter.len();
+                return Ok(());
+            }
+
+            let safe = available.len().saturating_sub(delimiter.len() - 1);
+            sink.write(&available[..safe])?;
+            self.pos += safe;
+            if !self.fill()? {
+                return Err(HttpError::bad_request("unexpected end of multipart body"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn body(parts: &[(&str, Option<&str>, &[u8])]) -> Vec<u8> {
+        let mut body = b"preamble\r\n".to_vec();
+        for (name, filename, data) in parts {
+            body.extend_from_slice(b"--XyZ\r\n");
+            match filename {
+                Some(filename) => body.extend_from_slice(
+                    format!("Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: application/octet-stream\r\n\r\n", name, filename).as_bytes(),
+                ),
+                None => body.extend_from_slice(format!("Content-Disposition: form-data; name={}\r\n\r\n", name).as_bytes()),
+            }
+            body.extend_from_slice(data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(b"--XyZ--\r\n");
+        body
+    }
+
+    /// 每次最多读出`n`个字节的读取器,用来测试分隔符跨越缓冲区边界的情况
+    struct Trickle<'a>(&'a [u8], usize);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(self.1).min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn fields_and_files() {
+        let body = body(&[("title", None, b"hello\r\nworld"), ("upload", Some("a \\\"b\\\".txt"), b"--XyZ not a boundary")]);
+        let multipart = Multipart::parse(Trickle(&body, 3), "XyZ", &MultipartLimits::default()).unwrap();
+
+        assert_eq!(2, multipart.parts().len());
+        assert_eq!(Some("hello\r\nworld"), multipart.field("title"));
+        let upload = multipart.part("upload").unwrap();
+        assert_eq!(Some("a \"b\".txt"), upload.filename());
+        assert_eq!(Some("application/octet-stream"), upload.content_type());
+        assert_eq!(Some("--XyZ not a boundary"), upload.text());
+    }
+
+    #[test]
+    // 超过阈值的part写入临时文件,丢弃后临时文件被删除
+    fn large_parts_spool_to_disk() {
+        let data = vec![b'x'; 10_000];
+        let body = body(&[("upload", Some("big.bin"), &data)]);
+        let limits = MultipartLimits { memory_threshold: 1024, ..MultipartLimits::default() };
+        let multipart = Multipart::parse(&body[..], "XyZ", &limits).unwrap();
+
+        let part = multipart.part("upload").unwrap();
+        let path = match part.data() {
+            PartData::File(file) => file.path().to_path_buf(),
+            PartData::Memory(_) => panic!("expected part to be spooled to disk"),
+        };
+        let mut contents = Vec::new();
+        part.reader().unwrap().read_to_end(&mut contents).unwrap();
+        assert_eq!(data, contents);
+        assert_eq!(10_000, part.size());
+
+        drop(multipart);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn limits() {
+        let body = body(&[("a", None, b"1"), ("b", None, b"22222")]);
+        let few_parts = MultipartLimits { max_parts: 1, ..MultipartLimits::default() };
+        assert_eq!(413, Multipart::parse(&body[..], "XyZ", &few_parts).unwrap_err().status());
+
+        let small_parts = MultipartLimits { max_part_size: 4, ..MultipartLimits::default() };
+        assert_eq!(413, Multipart::parse(&body[..], "XyZ", &small_parts).unwrap_err().status());
+
+        let truncated = &body[..body.len() - 12];
+        assert_eq!(400, Multipart::parse(truncated, "XyZ", &MultipartLimits::default()).unwrap_err().status());
+    }
+}
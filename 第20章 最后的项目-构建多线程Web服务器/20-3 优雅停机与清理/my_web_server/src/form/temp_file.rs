@@ -0,0 +1,71 @@
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// 临时文件,被丢弃时自动删除,除非已经通过`persist()`移动到别处
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl TempFile {
+    /// 在`dir`中创建一个新的临时文件
+    /// 文件名由进程id、时间戳和计数器组成,并使用`create_new`确保不会覆盖已有文件
+    pub fn new_in<P: AsRef<Path>>(dir: P) -> io::Result<TempFile> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        loop {
+            let name = format!(
+                "my_web_server-{}-{}-{}.tmp",
+                process::id(),
+                nanos,
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            );
+            let path = dir.as_ref().join(name);
+            match OpenOptions::new().read(true).write(true).create_new(true).open(&path) {
+                Ok(file) => return Ok(TempFile { path, file: Some(file) }),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 以只读方式重新打开文件,从头开始读取
+    pub fn open(&self) -> io::Result<File> {
+        File::open(&self.path)
+    }
+
+    pub(crate) fn file_mut(&mut self) -> &mut File {
+        self.file.as_mut().expect("temp file is open until dropped")
+    }
+
+    /// 将文件移动到`dest`,之后不会再被自动删除
+    /// 跨文件系统无法重命名时退化为复制
+    pub fn persist<P: AsRef<Path>>(mut self, dest: P) -> io::Result<()> {
+        self.file.take();
+        if fs::rename(&self.path, dest.as_ref()).is_err() {
+            fs::copy(&self.path, dest.as_ref())?;
+            fs::remove_file(&self.path)?;
+        }
+        self.path = PathBuf::new();
+        Ok(())
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        self.file.take();
+        if !self.path.as_os_str().is_empty() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::http::url::parse_query;
+
+/// `application/x-www-form-urlencoded`格式的表单
+/// 同名字段可以出现多次(例如多选框),因此按原始顺序保存所有字段
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Form {
+    fields: Vec<(String, String)>,
+}
+
+impl Form {
+    pub fn parse(body: &str) -> Form {
+        Form { fields: parse_query(body) }
+    }
+
+    /// 取得名为`name`的第一个字段
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// 取得名为`name`的所有字段
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.fields.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+
+/// 响应body
+/// 大文件不必整个读入内存,可以使用`Body::Reader`在写响应时边读边写
+pub enum Body {
+    Bytes(Vec<u8>),
+    /// 长度已知的流式body,`len`用作`Content-Length`
+    Reader { reader: Box<dyn Read + Send>, len: u64 },
+    /// 长度未知、持续时间很长的body,例如Server-Sent Events
+    /// 服务器写完响应头部后把连接交给该函数,由它在worker之外继续写入,写完后关闭连接即表示body结束
+    Takeover(Box<dyn FnOnce(TcpStream) + Send>),
+}
+
+impl Body {
+    pub fn len(&self) -> u64 {
+        match self {
+            Body::Bytes(bytes) => bytes.len() as u64,
+            Body::Reader { len, .. } => *len,
+            Body::Takeover(_) => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 内存中的body,流式body返回`None`
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Body::Bytes(bytes) => Some(bytes),
+            Body::Reader { .. } | Body::Takeover(_) => None,
+        }
+    }
+
+    /// 将body写入`writer`
+    /// 流式body读出的字节数少于`len`时返回错误,避免客户端一直等待缺失的数据
+    pub(crate) fn write_to<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Body::Bytes(bytes) => writer.write_all(bytes),
+            Body::Reader { reader, len } => {
+                let copied = io::copy(&mut reader.take(*len), writer)?;
+                if copied < *len {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "body ended early"));
+                }
+                Ok(())
+            }
+            // 连接由`Response::take_over()`交出
+            Body::Takeover(_) => Ok(()),
+        }
+    }
+}
+
+impl Default for Body {
+    fn default() -> Body {
+        Body::Bytes(Vec::new())
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Body {
+        Body::Bytes(bytes)
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Bytes(bytes) => write!(f, "Body::Bytes({} bytes)", bytes.len()),
+            Body::Reader { len, .. } => write!(f, "Body::Reader({} bytes)", len),
+            Body::Takeover(_) => write!(f, "Body::Takeover"),
+        }
+    }
+}
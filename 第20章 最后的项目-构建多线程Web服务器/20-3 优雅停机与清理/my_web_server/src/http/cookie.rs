@@ -0,0 +1,164 @@
+use std::fmt;
+use std::time::Duration;
+
+/// `SameSite`属性的取值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl fmt::Display for SameSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SameSite::Strict => write!(f, "Strict"),
+            SameSite::Lax => write!(f, "Lax"),
+            SameSite::None => write!(f, "None"),
+        }
+    }
+}
+
+/// 通过`Set-Cookie`头部发送给客户端的Cookie
+/// 使用`to_string()`得到头部的值
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    max_age: Option<Duration>,
+    path: Option<String>,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, value: V) -> Cookie {
+        Cookie {
+            name: name.into(),
+            value: value.into(),
+            max_age: None,
+            path: None,
+            domain: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        }
+    }
+
+    /// 创建一个让客户端删除名为`name`的Cookie的`Set-Cookie`
+    /// `path`和`domain`需要与设置该Cookie时相同才能生效
+    pub fn removal<N: Into<String>>(name: N) -> Cookie {
+        Cookie::new(name, "").max_age(Duration::ZERO)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// 设置有效期,不设置时为会话Cookie,浏览器关闭后失效
+    pub fn max_age(mut self, max_age: Duration) -> Cookie {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn path<P: Into<String>>(mut self, path: P) -> Cookie {
+        self.path = Some(path.into());
+        self
+    }
+
+    pub fn domain<D: Into<String>>(mut self, domain: D) -> Cookie {
+        self.domain = Some(domain.into());
+        self
+    }
+
+    /// 只通过HTTPS发送
+    pub fn secure(mut self, secure: bool) -> Cookie {
+        self.secure = secure;
+        self
+    }
+
+    /// 禁止页面中的JavaScript读取
+    pub fn http_only(mut self, http_only: bool) -> Cookie {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Cookie {
+        self.same_site = Some(same_site);
+        self
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={}", domain)?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={}", same_site)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解析请求中的`Cookie`头部,例如`a=1; b="2"`,值两侧的引号会被去掉
+pub fn parse_cookie_header(header: &str) -> Vec<(String, String)> {
+    header
+        .split(';')
+        .filter_map(|pair| {
+            let (name, value) = pair.split_once('=')?;
+            let name = name.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+            Some((name.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_cookie_attributes() {
+        let cookie = Cookie::new("sid", "abc")
+            .max_age(Duration::from_secs(3600))
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax);
+        assert_eq!("sid=abc; Max-Age=3600; Path=/; Secure; HttpOnly; SameSite=Lax", cookie.to_string());
+        assert_eq!("sid=; Max-Age=0", Cookie::removal("sid").to_string());
+    }
+
+    #[test]
+    fn parse_request_cookies() {
+        let cookies = parse_cookie_header("a=1; b=\"two\";c=x=y; ;bad");
+        assert_eq!(
+            vec![("a".to_string(), "1".to_string()), ("b".to_string(), "two".to_string()), ("c".to_string(), "x=y".to_string())],
+            cookies
+        );
+    }
+}
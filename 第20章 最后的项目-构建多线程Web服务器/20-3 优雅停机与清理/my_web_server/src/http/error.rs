@@ -0,0 +1,60 @@
+use std::error::Error;
+use std::fmt;
+use crate::http::{IntoResponse, Response};
+
+/// 处理请求过程中出现的、需要以某个状态码返回给客户端的错误
+/// 处理函数可以返回`Result<Response, HttpError>`,从而使用`?`运算符提前返回错误响应
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    pub fn new<M: Into<String>>(status: u16, message: M) -> HttpError {
+        HttpError { status, message: message.into() }
+    }
+
+    pub fn bad_request<M: Into<String>>(message: M) -> HttpError {
+        HttpError::new(400, message)
+    }
+
+    pub fn not_found<M: Into<String>>(message: M) -> HttpError {
+        HttpError::new(404, message)
+    }
+
+    pub fn payload_too_large<M: Into<String>>(message: M) -> HttpError {
+        HttpError::new(413, message)
+    }
+
+    pub fn unsupported_media_type<M: Into<String>>(message: M) -> HttpError {
+        HttpError::new(415, message)
+    }
+
+    pub fn internal<M: Into<String>>(message: M) -> HttpError {
+        HttpError::new(500, message)
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.status, self.message)
+    }
+}
+
+impl Error for HttpError {}
+
+/// 错误响应的body为`{"error": "<message>"}`形式的JSON
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        Response::json(&serde_json::json!({ "error": self.message })).with_status(self.status)
+    }
+}
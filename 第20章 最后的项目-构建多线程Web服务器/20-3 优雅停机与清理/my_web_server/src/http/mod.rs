@@ -0,0 +1,25 @@
+pub mod request;
+pub use request::Request;
+
+pub mod response;
+pub use response::{IntoResponse, Response};
+
+pub mod body;
+pub use body::Body;
+
+pub mod headers;
+pub use headers::Headers;
+
+pub mod method;
+pub use method::Method;
+
+pub mod error;
+pub use error::HttpError;
+
+pub mod url;
+
+pub mod cookie;
+pub use cookie::{Cookie, SameSite};
+
+pub mod extensions;
+pub use extensions::Extensions;
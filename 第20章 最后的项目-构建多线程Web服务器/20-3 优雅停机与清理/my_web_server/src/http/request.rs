@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use serde::de::DeserializeOwned;
+use crate::form::{Form, Multipart, MultipartLimits};
+use crate::http::cookie::parse_cookie_header;
+use crate::http::url::{parse_query, percent_decode};
+use crate::http::{Extensions, Headers, HttpError, Method};
+use crate::session::Session;
+
+/// 请求行和单个头部行的最大长度
+const MAX_LINE_SIZE: usize = 8 * 1024;
+/// 头部的最大数量
+const MAX_HEADERS: usize = 100;
+
+/// HTTP请求
+#[derive(Debug, Clone)]
+pub struct Request {
+    method: Method,
+    path: String,
+    query: Option<String>,
+    version: String,
+    headers: Headers,
+    body: Vec<u8>,
+    params: HashMap<String, String>,
+    peer_addr: Option<SocketAddr>,
+    extensions: Extensions,
+}
+
+impl Request {
+    /// 创建一个请求,`target`为请求行中的路径部分(可以带查询字符串)
+    pub fn new(method: Method, target: &str) -> Request {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query.to_string())),
+            None => (target, None),
+        };
+        Request {
+            method,
+            path: percent_decode(path, false),
+            query,
+            version: "HTTP/1.1".to_string(),
+            headers: Headers::new(),
+            body: Vec::new(),
+            params: HashMap::new(),
+            peer_addr: None,
+            extensions: Extensions::new(),
+        }
+    }
+
+    /// 从`reader`中读取一个完整的请求(包括body)
+    pub fn read_from<R: BufRead>(reader: &mut R, max_body_size: usize) -> Result<Request, HttpError> {
+        let mut request = Request::read_head(reader)?;
+        request.read_body(reader, max_body_size)?;
+        Ok(request)
+    }
+
+    /// 读取请求行和头部,不读取body
+    /// 与`read_body()`分开是为了让服务器有机会在读取body之前回复`100 Continue`
+    pub fn read_head<R: BufRead>(reader: &mut R) -> Result<Request, HttpError> {
+        // 按照RFC 7230的建议,忽略请求行之前的空行
+        let request_line = loop {
+            match read_line(reader)? {
+                Some(line) if line.is_empty() => continue,
+                Some(line) => break line,
+                None => return Err(HttpError::bad_request("connection closed before request line")),
+            }
+        };
+
+        let mut parts = request_line.split(' ');
+        let (method, target, version) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version), None) if version.starts_with("HTTP/") => {
+                (method, target, version)
+            }
+            _ => return Err(HttpError::bad_request(format!("malformed request line: {}", request_line))),
+        };
+
+        let mut request = Request::new(Method::from(method), target);
+        request.version = version.to_string();
+
+        loop {
+            let line = match read_line(reader)? {
+                Some(line) => line,
+                None => return Err(HttpError::bad_request("connection closed in headers")),
+            };
+            if line.is_empty() {
+                break;
+            }
+            if request.headers.iter().count() >= MAX_HEADERS {
+                return Err(HttpError::new(431, "too many headers"));
+            }
+            match line.split_once(':') {
+                Some((name, value)) if !name.is_empty() && !name.ends_with(' ') => {
+                    request.headers.append(name, value.trim());
+                }
+                _ => return Err(HttpError::bad_request(format!("malformed header: {}", line))),
+            }
+        }
+
+        Ok(request)
+    }
+
+    /// 带`Content-Length`的`multipart/form-data`请求:不把body读入内存,而是直接从`reader`中边读边解析,
+    /// 超过`memory_threshold`的part直接写入临时文件,解析结果留给`multipart_with()`取出.
+    /// 其他请求什么也不读并返回`false`,此时应该改用`read_body()`
+    pub(crate) fn read_multipart<R: BufRead>(&mut self, reader: &mut R, limits: &MultipartLimits) -> Result<bool, HttpError> {
+        let chunked = self.header("Transfer-Encoding").is_some();
+        let length = match self.header("Content-Length") {
+            Some(length) if !chunked && self.content_type().as_deref() == Some("multipart/form-data") => length
+                .parse::<usize>()
+                .map_err(|_| HttpError::bad_request("invalid Content-Length"))?,
+            _ => return Ok(false),
+        };
+        if length > limits.max_size {
+            return Err(HttpError::payload_too_large(format!("multipart body exceeds {} bytes", limits.max_size)));
+        }
+
+        let mut body = reader.by_ref().take(length as u64);
+        let multipart = Multipart::parse(&mut body, &self.boundary()?, limits)?;
+        // 结束分隔符之后的尾声(epilogue)没有意义,读完丢弃
+        io::copy(&mut body, &mut io::sink()).map_err(|e| HttpError::bad_request(format!("failed to read body: {}", e)))?;
+        self.extensions.insert(StreamedMultipart(Mutex::new(Some(multipart))));
+        Ok(true)
+    }
+
+    /// 根据`Content-Length`或`Transfer-Encoding: chunked`读取body
+    pub fn read_body<R: BufRead>(&mut self, reader: &mut R, max_body_size: usize) -> Result<(), HttpError> {
+        let chunked = self
+            .header("Transfer-Encoding")
+            .is_some_and(|encoding| encoding.eq_ignore_ascii_case("chunked"));
+        if chunked {
+            self.body = read_chunked(reader, max_body_size)?;
+            return Ok(());
+        }
+
+        let length = match self.header("Content-Length") {
+            Some(length) => length
+                .parse::<usize>()
+                .map_err(|_| HttpError::bad_request("invalid Content-Length"))?,
+            None => 0,
+        };
+        if length > max_body_size {
+            return Err(HttpError::payload_too_large(format!("body exceeds {} bytes", max_body_size)));
+        }
+
+        let mut body = vec![0; length];
+        reader
+            .read_exact(&mut body)
+            .map_err(|e| HttpError::bad_request(format!("failed to read body: {}", e)))?;
+        self.body = body;
+        Ok(())
+    }
+
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// 解码后的路径,不包含查询字符串
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// 未解码的查询字符串
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// 取得查询字符串中名为`name`的参数
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        parse_query(self.query.as_deref()?)
+            .into_iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub(crate) fn set_version<V: Into<String>>(&mut self, version: V) {
+        self.version = version.into();
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Request {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Request {
+        self.body = body.into();
+        self
+    }
+
+    /// 取得路由中`:name`形式的路径参数
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.get(name).map(String::as_str)
+    }
+
+    pub(crate) fn set_params(&mut self, params: HashMap<String, String>) {
+        self.params = params;
+    }
+
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    pub fn with_peer_addr(mut self, addr: SocketAddr) -> Request {
+        self.peer_addr = Some(addr);
+        self
+    }
+
+    /// 中间件附加到请求上的数据
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// 取得请求`Cookie`头部中名为`name`的Cookie的值
+    pub fn cookie(&self, name: &str) -> Option<String> {
+        self.headers
+            .get_all("Cookie")
+            .flat_map(parse_cookie_header)
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    }
+
+    /// 取得当前请求的会话,需要先通过`Router::layer()`启用`SessionLayer`
+    pub fn session(&self) -> Option<&Session> {
+        self.extensions.get::<Session>()
+    }
+
+    /// `Content-Type`中的媒体类型部分(不含参数),已转为小写
+    pub fn content_type(&self) -> Option<String> {
+        let content_type = self.header("Content-Type")?;
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        Some(mime.to_ascii_lowercase())
+    }
+
+    /// 将body按JSON反序列化为`T`
+    /// `Content-Type`不是JSON时返回415,body不是合法的JSON或与`T`的结构不符时返回400
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, HttpError> {
+        match self.content_type() {
+            Some(mime) if mime == "application/json" || mime.ends_with("+json") => {}
+            _ => return Err(HttpError::unsupported_media_type("expected Content-Type: application/json")),
+        }
+        serde_json::from_slice(&self.body).map_err(|e| HttpError::bad_request(format!("invalid JSON: {}", e)))
+    }
+
+    /// 将body按`application/x-www-form-urlencoded`解析为表单
+    /// `Content-Type`不符时返回415
+    pub fn form(&self) -> Result<Form, HttpError> {
+        if self.content_type().as_deref() != Some("application/x-www-form-urlencoded") {
+            return Err(HttpError::unsupported_media_type(
+                "expected Content-Type: application/x-www-form-urlencoded",
+            ));
+        }
+        Ok(Form::parse(&String::from_utf8_lossy(&self.body)))
+    }
+
+    /// 使用默认限制将body按`multipart/form-data`解析
+    pub fn multipart(&self) -> Result<Multipart, HttpError> {
+        self.multipart_with(&MultipartLimits::default())
+    }
+
+    /// 使用指定的限制将body按`multipart/form-data`解析
+    /// 服务器通过HTTP/1.1收到的带`Content-Length`的请求在交给路由之前已经按`Limits::multipart`边读边解析,
+    /// 这里直接取出解析结果,`limits`不再起作用,而且只能取出一次;
+    /// 其余的请求(chunked编码、HTTP/2)的body已经读入内存,在这里按`limits`解析.
+    /// `Content-Type`不符时返回415,缺少`boundary`参数时返回400
+    pub fn multipart_with(&self, limits: &MultipartLimits) -> Result<Multipart, HttpError> {
+        if self.content_type().as_deref() != Some("multipart/form-data") {
+            return Err(HttpError::unsupported_media_type("expected Content-Type: multipart/form-data"));
+        }
+        if let Some(StreamedMultipart(streamed)) = self.extensions.get::<StreamedMultipart>() {
+            return streamed
+                .lock()
+                .unwrap()
+                .take()
+                .ok_or_else(|| HttpError::internal("multipart body has already been taken"));
+        }
+        if self.body.len() > limits.max_size {
+            return Err(HttpError::payload_too_large(format!("multipart body exceeds {} bytes", limits.max_size)));
+        }
+        Multipart::parse(&self.body[..], &self.boundary()?, limits)
+    }
+
+    fn boundary(&self) -> Result<String, HttpError> {
+        self.header("Content-Type")
+            .and_then(|content_type| content_type_param(content_type, "boundary"))
+            .ok_or_else(|| HttpError::bad_request("multipart/form-data without boundary"))
+    }
+}
+
+/// 服务器在路由之前解析好的`multipart/form-data`请求体,`Request::multipart_with()`取出后为`None`
+struct StreamedMultipart(Mutex<Option<Multipart>>);
+
+/// 取出`Content-Type`中的参数,例如`multipart/form-data; boundary="abc"`中的`boundary`
+fn content_type_param(content_type: &str, key: &str) -> Option<String> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case(key) {
+            Some(value.trim().trim_matches('"').to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// 读取一行(以`\r\n`或`\n`结尾)并去掉行尾,连接关闭且没有读到任何数据时返回`None`
+fn read_line<R: BufRead>(reader: &mut R) -> Result<Option<String>, HttpError> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE_SIZE as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .map_err(|e| HttpError::bad_request(format!("failed to read request: {}", e)))?;
+
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if !line.ends_with(b"\n") {
+        return if line.len() > MAX_LINE_SIZE {
+            Err(HttpError::new(431, "header line too long"))
+        } else {
+            Err(HttpError::bad_request("unexpected end of request"))
+        };
+    }
+
+    line.pop();
+    if line.ends_with(b"\r") {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| HttpError::bad_request("request head is not valid UTF-8"))
+}
+
+/// 读取`Transfer-Encoding: chunked`编码的body,trailer部分会被丢弃
+fn read_chunked<R: BufRead>(reader: &mut R, max_body_size: usize) -> Result<Vec<u8>, HttpError> {
+    let mut body = Vec::new();
+    loop {
+        let line = read_line(reader)?.ok_or_else(|| HttpError::bad_request("unexpected end of chunked body"))?;
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| HttpError::bad_request("invalid chunk size"))?;
+        if size == 0 {
+            break;
+        }
+        if body.len() + size > max_body_size {
+            return Err(HttpError::payload_too_large(format!("body exceeds {} bytes", max_body_size)));
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader
+            .read_exact(&mut body[start..])
+            .map_err(|e| HttpError::bad_request(format!("failed to read chunk: {}", e)))?;
+        if read_line(reader)? != Some(String::new()) {
+            return Err(HttpError::bad_request("missing CRLF after chunk"));
+        }
+    }
+
+    while let Some(line) = read_line(reader)? {
+        if line.is_empty() {
+            break;
+        }
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Item {
+        name: String,
+    }
+
+    #[test]
+    fn parse_request_with_body() {
+        let raw = "POST /items%20x?a=1&b=two+words HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: 17\r\n\r\n{\"name\":\"ferris\"}";
+        let request = Request::read_from(&mut raw.as_bytes(), 1024).unwrap();
+        assert_eq!(&Method::Post, request.method());
+        assert_eq!("/items x", request.path());
+        assert_eq!(Some("two words".to_string()), request.query_param("b"));
+        assert_eq!(Some("localhost"), request.header("host"));
+        assert_eq!(17, request.body().len());
+    }
+
+    #[test]
+    fn parse_chunked_body() {
+        let raw = "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5;ext=1\r\npedia\r\n0\r\n\r\n";
+        let request = Request::read_from(&mut raw.as_bytes(), 1024).unwrap();
+        assert_eq!(b"Wikipedia", request.body());
+    }
+
+    #[test]
+    fn body_limit() {
+        let raw = "POST / HTTP/1.1\r\nContent-Length: 2048\r\n\r\n";
+        let error = Request::read_from(&mut raw.as_bytes(), 1024).unwrap_err();
+        assert_eq!(413, error.status());
+    }
+
+    #[test]
+    // Content-Type不对时返回415,JSON格式错误时返回400
+    fn json_body() {
+        let request = Request::new(Method::Post, "/").with_body(r#"{"name":"ferris"}"#);
+        assert_eq!(415, request.json::<Item>().unwrap_err().status());
+
+        let request = request.with_header("Content-Type", "application/json");
+        assert_eq!(Item { name: "ferris".to_string() }, request.json().unwrap());
+
+        let request = request.with_body("{\"name\":");
+        assert_eq!(400, request.json::<Item>().unwrap_err().status());
+    }
+}
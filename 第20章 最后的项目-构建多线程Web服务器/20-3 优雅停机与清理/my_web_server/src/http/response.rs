@@ -0,0 +1,209 @@
+use std::io::{self, Read, Write};
+use std::net::TcpStream;
+use serde::Serialize;
+use crate::http::{Body, Cookie, Headers};
+
+/// HTTP响应
+#[derive(Debug)]
+pub struct Response {
+    status: u16,
+    headers: Headers,
+    body: Body,
+}
+
+impl Response {
+    /// 创建一个状态码为`status`、body为空的响应
+    pub fn new(status: u16) -> Response {
+        Response { status, headers: Headers::new(), body: Body::default() }
+    }
+
+    pub fn ok() -> Response {
+        Response::new(200)
+    }
+
+    pub fn html<B: Into<String>>(status: u16, body: B) -> Response {
+        Response::new(status)
+            .with_header("Content-Type", "text/html; charset=utf-8")
+            .with_body(body.into())
+    }
+
+    pub fn text<B: Into<String>>(status: u16, body: B) -> Response {
+        Response::new(status)
+            .with_header("Content-Type", "text/plain; charset=utf-8")
+            .with_body(body.into())
+    }
+
+    /// 将`value`序列化为JSON作为body,状态码为200
+    /// 序列化失败(例如映射的键不是字符串)时返回500
+    pub fn json<T: Serialize + ?Sized>(value: &T) -> Response {
+        match serde_json::to_vec(value) {
+            Ok(body) => Response::new(200)
+                .with_header("Content-Type", "application/json")
+                .with_body(body),
+            Err(e) => Response::text(500, format!("failed to serialize response: {}", e)),
+        }
+    }
+
+    pub fn with_status(mut self, status: u16) -> Response {
+        self.status = status;
+        self
+    }
+
+    /// 设置头部,同名头部会被替换
+    pub fn with_header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> Response {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// 重定向到`location`,使用`303 See Other`,使得表单提交之后浏览器以GET请求新地址
+    pub fn redirect<L: Into<String>>(location: L) -> Response {
+        Response::new(303).with_header("Location", location)
+    }
+
+    /// 追加一个`Set-Cookie`头部
+    pub fn with_cookie(mut self, cookie: Cookie) -> Response {
+        self.set_cookie(cookie);
+        self
+    }
+
+    pub fn set_cookie(&mut self, cookie: Cookie) {
+        self.headers.append("Set-Cookie", cookie.to_string());
+    }
+
+    /// 写完响应头部之后把连接交给`f`,用于长度未知的长连接响应,见`Body::Takeover`
+    pub fn with_takeover<F: FnOnce(TcpStream) + Send + 'static>(mut self, f: F) -> Response {
+        self.body = Body::Takeover(Box::new(f));
+        self
+    }
+
+    pub fn with_body<B: Into<Vec<u8>>>(mut self, body: B) -> Response {
+        self.body = Body::Bytes(body.into());
+        self
+    }
+
+    /// 使用长度为`len`的流式body,写响应时才从`reader`中读取
+    pub fn with_reader<R: Read + Send + 'static>(mut self, reader: R, len: u64) -> Response {
+        self.body = Body::Reader { reader: Box::new(reader), len };
+        self
+    }
+
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut Headers {
+        &mut self.headers
+    }
+
+    /// 内存中的body,流式body返回空切片
+    pub fn body(&self) -> &[u8] {
+        self.body.as_bytes().unwrap_or_default()
+    }
+
+    pub fn body_len(&self) -> u64 {
+        self.body.len()
+    }
+
+    /// 拆分为状态码、头部和body,供HTTP/2等其他格式写出响应
+    pub(crate) fn into_parts(self) -> (u16, Headers, Body) {
+        (self.status, self.headers, self.body)
+    }
+
+    /// 将响应按HTTP/1.1格式写入`writer`
+    /// 服务器每个连接只处理一个请求,因此默认附带`Connection: close`
+    /// `include_body`为假时只写状态行和头部,用于响应HEAD请求
+    pub fn write_to<W: Write>(&mut self, writer: &mut W, include_body: bool) -> io::Result<()> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason_phrase(self.status));
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        // 204和304响应不能带body,也就不需要Content-Length
+        let bodiless = self.status == 204 || self.status == 304;
+        let takeover = matches!(self.body, Body::Takeover(_));
+        if !bodiless && !takeover && !self.headers.contains("Content-Length") {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        if !self.headers.contains("Connection") {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+
+        writer.write_all(head.as_bytes())?;
+        if include_body {
+            self.body.write_to(writer)?;
+        }
+        writer.flush()
+    }
+
+    /// 如果body是`Body::Takeover`,把连接交给它并返回`None`,否则原样返回连接
+    pub fn take_over(&mut self, stream: TcpStream) -> Option<TcpStream> {
+        match std::mem::take(&mut self.body) {
+            Body::Takeover(f) => {
+                f(stream);
+                None
+            }
+            body => {
+                self.body = body;
+                Some(stream)
+            }
+        }
+    }
+}
+
+/// 能够转换为`Response`的类型,处理函数的返回值需要实现该trait
+pub trait IntoResponse {
+    fn into_response(self) -> Response;
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> Response {
+        self
+    }
+}
+
+impl<T: IntoResponse, E: IntoResponse> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Response {
+        match self {
+            Ok(response) => response.into_response(),
+            Err(error) => error.into_response(),
+        }
+    }
+}
+
+/// 状态码对应的原因短语
+pub fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        422 => "Unprocessable Entity",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "",
+    }
+}
@@ -0,0 +1,762 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Chain, Cursor, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use crate::http::{Body, HttpError, IntoResponse, Method, Request, Response};
+use crate::http2::frame::{self, ErrorCode, Frame, FrameType};
+use crate::http2::hpack::{Decoder, Encoder, HpackError};
+use crate::pool::Spawner;
+use crate::routing::Router;
+use crate::server::Limits;
+
+/// 每个连接允许同时打开的流的数量,通过`SETTINGS_MAX_CONCURRENT_STREAMS`告知客户端
+const MAX_CONCURRENT_STREAMS: usize = 100;
+/// 一个头部块(HEADERS及其后续CONTINUATION帧)压缩后的最大字节数
+const MAX_HEADER_BLOCK: usize = 64 * 1024;
+/// 解码后头部列表的最大字节数,通过`SETTINGS_MAX_HEADER_LIST_SIZE`告知客户端,超出时回复431
+const MAX_HEADER_LIST_SIZE: usize = 64 * 1024;
+/// 一个请求最多的头部字段数,与HTTP/1.1相同
+const MAX_HEADERS: usize = 100;
+/// HTTP/2中不允许出现的连接相关头部,响应中的这些头部会被丢弃
+const CONNECTION_HEADERS: [&str; 5] = ["connection", "keep-alive", "proxy-connection", "transfer-encoding", "upgrade"];
+
+type Reader = BufReader<Chain<Cursor<Vec<u8>>, TcpStream>>;
+
+/// 处理一个已经读完连接前言的HTTP/2连接,直到连接关闭
+/// `buffered`是读取前言时多读进缓冲区的字节,它们排在连接中尚未读取的数据之前.
+/// 调用者的线程只负责读帧,每个流的请求作为任务交给线程池,由worker处理后直接写回响应
+pub(crate) fn serve(stream: TcpStream, buffered: Vec<u8>, router: Arc<Router>, limits: Limits, spawner: Spawner) {
+    let writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(e) => {
+            eprintln!("failed to clone HTTP/2 connection: {}", e);
+            return;
+        }
+    };
+    let _ = writer.set_write_timeout(Some(limits.read_timeout));
+    let shared = Arc::new(Shared {
+        writer: Mutex::new(writer),
+        flow: Mutex::new(Flow {
+            window: frame::DEFAULT_WINDOW_SIZE as i64,
+            streams: HashMap::new(),
+            initial_window: frame::DEFAULT_WINDOW_SIZE as i64,
+            max_frame_size: frame::DEFAULT_MAX_FRAME_SIZE,
+            closed: false,
+        }),
+        flow_changed: Condvar::new(),
+        send_timeout: limits.read_timeout,
+    });
+
+    let receive_window = limits.max_body_size.clamp(frame::DEFAULT_WINDOW_SIZE as usize, frame::MAX_WINDOW_SIZE as usize);
+    let mut connection = Connection {
+        peer_addr: stream.peer_addr().ok(),
+        reader: BufReader::new(Cursor::new(buffered).chain(stream)),
+        shared,
+        decoder: Decoder::new().max_header_list_size(MAX_HEADER_LIST_SIZE).max_fields(MAX_HEADERS),
+        receive_window,
+        available: receive_window,
+        router,
+        limits,
+        spawner,
+        streams: HashMap::new(),
+        continuation: None,
+        last_stream_id: 0,
+    };
+    connection.run();
+}
+
+/// 导致整个连接关闭的错误
+enum ConnectionError {
+    /// 对端违反了协议,发送GOAWAY后关闭连接
+    GoAway(ErrorCode, &'static str),
+    /// 读写连接出错,连接已经不可用,直接关闭
+    Closed,
+}
+
+impl From<io::Error> for ConnectionError {
+    fn from(_: io::Error) -> ConnectionError {
+        ConnectionError::Closed
+    }
+}
+
+/// 读取帧的线程持有的连接状态
+struct Connection {
+    reader: Reader,
+    shared: Arc<Shared>,
+    decoder: Decoder,
+    /// 连接的接收窗口,即整个连接上最多缓冲的请求body字节数,不小于一个最大的请求body
+    receive_window: usize,
+    /// 接收窗口中还剩下的字节数.请求body交给worker之后才归还给客户端,
+    /// 因此无论打开多少个流,尚未交给worker的body总共不会超过`receive_window`
+    available: usize,
+    router: Arc<Router>,
+    limits: Limits,
+    spawner: Spawner,
+    peer_addr: Option<SocketAddr>,
+    /// 已经收到头部、还在接收body的流
+    streams: HashMap<u32, PendingStream>,
+    /// 尚未收到END_HEADERS的头部块,此时只能接收同一个流的CONTINUATION帧
+    continuation: Option<HeaderBlock>,
+    last_stream_id: u32,
+}
+
+struct PendingStream {
+    request: Request,
+    body: Vec<u8>,
+    /// 该流占用的连接接收窗口,包括DATA帧中的填充
+    received: usize,
+}
+
+struct HeaderBlock {
+    stream_id: u32,
+    end_stream: bool,
+    fragment: Vec<u8>,
+}
+
+impl Connection {
+    fn run(&mut self) {
+        let settings = frame::encode_settings(&[
+            (frame::SETTINGS_MAX_CONCURRENT_STREAMS, MAX_CONCURRENT_STREAMS as u32),
+            (frame::SETTINGS_MAX_HEADER_LIST_SIZE, MAX_HEADER_LIST_SIZE as u32),
+        ]);
+        // 连接窗口只能通过WINDOW_UPDATE增大,初始值固定为65535
+        let increment = (self.receive_window - frame::DEFAULT_WINDOW_SIZE as usize) as u32;
+        let preface = self.shared.write_frame(FrameType::Settings, 0, 0, &settings).and_then(|_| match increment {
+            0 => Ok(()),
+            _ => self.shared.write_frame(FrameType::WindowUpdate, 0, 0, &increment.to_be_bytes()),
+        });
+        if preface.is_ok() {
+            if let Err(ConnectionError::GoAway(code, message)) = self.read_frames() {
+                eprintln!("HTTP/2 connection error: {}", message);
+                self.go_away(code, message);
+            }
+        }
+        self.shared.close();
+    }
+
+    fn read_frames(&mut self) -> Result<(), ConnectionError> {
+        let mut first = true;
+        loop {
+            // 先确认有数据可读再读取整个帧,读超时只会发生在帧与帧之间,不会丢掉读了一半的帧
+            match self.reader.fill_buf() {
+                Ok([]) => return Ok(()),
+                Ok(_) => {}
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                    if self.shared.active_streams() == 0 {
+                        self.go_away(ErrorCode::NoError, "idle timeout");
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            let frame = match Frame::read_from(&mut self.reader, frame::DEFAULT_MAX_FRAME_SIZE) {
+                Ok(frame) => frame,
+                Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                    return Err(ConnectionError::GoAway(ErrorCode::FrameSizeError, "frame too large"));
+                }
+                Err(e) => return Err(e.into()),
+            };
+            // 客户端的连接前言必须以SETTINGS帧结束
+            if first && frame.header.kind != FrameType::Settings {
+                return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "expected SETTINGS"));
+            }
+            first = false;
+            self.handle(frame)?;
+        }
+    }
+
+    fn handle(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        let header = frame.header;
+        if let Some(block) = &self.continuation {
+            if header.kind != FrameType::Continuation || header.stream_id != block.stream_id {
+                return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "expected CONTINUATION"));
+            }
+        }
+
+        match header.kind {
+            FrameType::Data => self.on_data(frame),
+            FrameType::Headers => self.on_headers(frame),
+            FrameType::Continuation => self.on_continuation(frame),
+            FrameType::Settings => self.on_settings(frame),
+            FrameType::WindowUpdate => self.on_window_update(frame),
+            FrameType::Ping => {
+                if header.stream_id != 0 {
+                    return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "PING on a stream"));
+                }
+                if frame.payload.len() != 8 {
+                    return Err(ConnectionError::GoAway(ErrorCode::FrameSizeError, "malformed PING"));
+                }
+                if !header.has_flag(frame::ACK) {
+                    self.shared.write_frame(FrameType::Ping, frame::ACK, 0, &frame.payload)?;
+                }
+                Ok(())
+            }
+            FrameType::RstStream => {
+                if header.stream_id == 0 {
+                    return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "RST_STREAM on stream 0"));
+                }
+                if frame.payload.len() != 4 {
+                    return Err(ConnectionError::GoAway(ErrorCode::FrameSizeError, "malformed RST_STREAM"));
+                }
+                // 正在为该流写响应的worker会在等待发送窗口时发现流已经关闭
+                self.discard_stream(header.stream_id)?;
+                self.shared.finish(header.stream_id);
+                Ok(())
+            }
+            // 不支持优先级,按到达顺序处理所有流
+            FrameType::Priority if header.stream_id == 0 => {
+                Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "PRIORITY on stream 0"))
+            }
+            FrameType::PushPromise => Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "clients cannot push")),
+            // 客户端准备关闭连接,已经打开的流照常完成,连接在客户端关闭时结束
+            FrameType::Priority | FrameType::GoAway | FrameType::Unknown(_) => Ok(()),
+        }
+    }
+
+    fn on_headers(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        let header = frame.header;
+        if header.stream_id == 0 || header.stream_id.is_multiple_of(2) {
+            return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "invalid stream id"));
+        }
+        let mut fragment = frame
+            .unpadded()
+            .ok_or(ConnectionError::GoAway(ErrorCode::ProtocolError, "invalid padding"))?;
+        if header.has_flag(frame::PRIORITY) {
+            fragment = fragment
+                .get(5..)
+                .ok_or(ConnectionError::GoAway(ErrorCode::FrameSizeError, "malformed HEADERS"))?;
+        }
+
+        let block = HeaderBlock {
+            stream_id: header.stream_id,
+            end_stream: header.has_flag(frame::END_STREAM),
+            fragment: fragment.to_vec(),
+        };
+        if header.has_flag(frame::END_HEADERS) {
+            self.on_header_block(block)
+        } else {
+            self.continuation = Some(block);
+            Ok(())
+        }
+    }
+
+    fn on_continuation(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        let mut block = self
+            .continuation
+            .take()
+            .ok_or(ConnectionError::GoAway(ErrorCode::ProtocolError, "unexpected CONTINUATION"))?;
+        block.fragment.extend_from_slice(&frame.payload);
+        if block.fragment.len() > MAX_HEADER_BLOCK {
+            return Err(ConnectionError::GoAway(ErrorCode::EnhanceYourCalm, "header block too large"));
+        }
+
+        if frame.header.has_flag(frame::END_HEADERS) {
+            self.on_header_block(block)
+        } else {
+            self.continuation = Some(block);
+            Ok(())
+        }
+    }
+
+    fn on_header_block(&mut self, block: HeaderBlock) -> Result<(), ConnectionError> {
+        // 即使随后要拒绝这个流也必须解码,否则双方的动态表会不一致
+        // 头部过大时动态表已经更新,`None`表示只需用431拒绝这个流
+        let fields = match self.decoder.decode(&block.fragment) {
+            Ok(fields) => Some(fields),
+            Err(HpackError::HeaderListTooLarge) => None,
+            Err(_) => return Err(ConnectionError::GoAway(ErrorCode::CompressionError, "invalid header block")),
+        };
+        let id = block.stream_id;
+
+        // 已经收到过头部的流上再次出现的头部块是trailer,它必须结束该流,内容被丢弃
+        if let Some(stream) = self.streams.remove(&id) {
+            self.release(stream.received)?;
+            if block.end_stream {
+                self.dispatch_request(id, stream);
+            } else {
+                self.shared.reset(id, ErrorCode::ProtocolError)?;
+            }
+            return Ok(());
+        }
+        if id <= self.last_stream_id {
+            return Err(ConnectionError::GoAway(ErrorCode::StreamClosed, "HEADERS on a closed stream"));
+        }
+        self.last_stream_id = id;
+
+        if self.shared.active_streams() >= MAX_CONCURRENT_STREAMS {
+            self.shared.write_frame(FrameType::RstStream, 0, id, &u32::from(ErrorCode::RefusedStream).to_be_bytes())?;
+            return Ok(());
+        }
+        let fields = match fields {
+            Some(fields) => fields,
+            None => {
+                self.shared.open(id);
+                let error = HttpError::new(431, "request header fields too large");
+                self.dispatch(id, false, block.end_stream, move || error.into_response());
+                return Ok(());
+            }
+        };
+        let request = match build_request(fields, self.peer_addr) {
+            Ok(request) => request,
+            Err(_) => {
+                self.shared.write_frame(FrameType::RstStream, 0, id, &u32::from(ErrorCode::ProtocolError).to_be_bytes())?;
+                return Ok(());
+            }
+        };
+
+        self.shared.open(id);
+        let stream = PendingStream { request, body: Vec::new(), received: 0 };
+        if block.end_stream {
+            self.dispatch_request(id, stream);
+        } else {
+            self.streams.insert(id, stream);
+        }
+        Ok(())
+    }
+
+    fn on_data(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        let header = frame.header;
+        let id = header.stream_id;
+        if id == 0 {
+            return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "DATA on stream 0"));
+        }
+        let data = frame
+            .unpadded()
+            .ok_or(ConnectionError::GoAway(ErrorCode::ProtocolError, "invalid padding"))?;
+
+        // 流量控制按整个负载(包括填充)计算,不遵守窗口的客户端会被断开
+        let len = frame.payload.len();
+        if len > self.available {
+            return Err(ConnectionError::GoAway(ErrorCode::FlowControlError, "connection window exceeded"));
+        }
+        self.available -= len;
+
+        let mut stream = match self.streams.remove(&id) {
+            Some(stream) => stream,
+            None if id > self.last_stream_id => {
+                return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "DATA on an idle stream"));
+            }
+            // 已经被重置或已经回复的流,丢弃数据并立即归还窗口
+            None => return self.release(len),
+        };
+        stream.received += len;
+
+        if stream.body.len() + data.len() > self.limits.max_body_size {
+            self.release(stream.received)?;
+            let head_only = *stream.request.method() == Method::Head;
+            let error = HttpError::payload_too_large(format!("body exceeds {} bytes", self.limits.max_body_size));
+            self.dispatch(id, head_only, false, move || error.into_response());
+            return Ok(());
+        }
+        stream.body.extend_from_slice(data);
+
+        if header.has_flag(frame::END_STREAM) {
+            self.release(stream.received)?;
+            self.dispatch_request(id, stream);
+        } else {
+            // 流的窗口随收随还,单个流的body由`max_body_size`限制
+            if len > 0 {
+                self.shared.write_frame(FrameType::WindowUpdate, 0, id, &(len as u32).to_be_bytes())?;
+            }
+            self.streams.insert(id, stream);
+        }
+        Ok(())
+    }
+
+    fn on_settings(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        if frame.header.stream_id != 0 {
+            return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "SETTINGS on a stream"));
+        }
+        if frame.header.has_flag(frame::ACK) {
+            return match frame.payload.is_empty() {
+                true => Ok(()),
+                false => Err(ConnectionError::GoAway(ErrorCode::FrameSizeError, "SETTINGS ACK with payload")),
+            };
+        }
+
+        let settings = frame::parse_settings(&frame.payload)
+            .ok_or(ConnectionError::GoAway(ErrorCode::FrameSizeError, "malformed SETTINGS"))?;
+        for (id, value) in settings {
+            match id {
+                frame::SETTINGS_ENABLE_PUSH if value > 1 => {
+                    return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "invalid SETTINGS_ENABLE_PUSH"));
+                }
+                frame::SETTINGS_INITIAL_WINDOW_SIZE
+                    if value > frame::MAX_WINDOW_SIZE || !self.shared.set_initial_window(value) =>
+                {
+                    return Err(ConnectionError::GoAway(ErrorCode::FlowControlError, "window too large"));
+                }
+                frame::SETTINGS_MAX_FRAME_SIZE => {
+                    let size = value as usize;
+                    if !(frame::DEFAULT_MAX_FRAME_SIZE..=frame::MAX_FRAME_SIZE_LIMIT).contains(&size) {
+                        return Err(ConnectionError::GoAway(ErrorCode::ProtocolError, "invalid SETTINGS_MAX_FRAME_SIZE"));
+                    }
+                    self.shared.flow.lock().unwrap().max_frame_size = size;
+                }
+                // 编码器不使用动态表,因此不需要处理SETTINGS_HEADER_TABLE_SIZE
+                _ => {}
+            }
+        }
+        self.shared.write_frame(FrameType::Settings, frame::ACK, 0, &[])?;
+        Ok(())
+    }
+
+    fn on_window_update(&mut self, frame: Frame) -> Result<(), ConnectionError> {
+        let id = frame.header.stream_id;
+        if frame.payload.len() != 4 {
+            return Err(ConnectionError::GoAway(ErrorCode::FrameSizeError, "malformed WINDOW_UPDATE"));
+        }
+        let increment = frame::read_u31(&frame.payload);
+
+        let code = if increment == 0 {
+            ErrorCode::ProtocolError
+        } else if self.shared.window_update(id, increment) {
+            return Ok(());
+        } else {
+            ErrorCode::FlowControlError
+        };
+        if id == 0 {
+            return Err(ConnectionError::GoAway(code, "invalid WINDOW_UPDATE"));
+        }
+        self.discard_stream(id)?;
+        self.shared.reset(id, code)?;
+        Ok(())
+    }
+
+    /// 丢弃还在接收body的流,它占用的连接窗口还给客户端
+    fn discard_stream(&mut self, id: u32) -> Result<(), ConnectionError> {
+        match self.streams.remove(&id) {
+            Some(stream) => self.release(stream.received),
+            None => Ok(()),
+        }
+    }
+
+    /// 缓冲的body已经交给worker或被丢弃,归还连接的接收窗口
+    fn release(&mut self, len: usize) -> Result<(), ConnectionError> {
+        if len > 0 {
+            self.available += len;
+            self.shared.write_frame(FrameType::WindowUpdate, 0, 0, &(len as u32).to_be_bytes())?;
+        }
+        Ok(())
+    }
+
+    fn dispatch_request(&self, id: u32, stream: PendingStream) {
+        let mut request = stream.request.with_body(stream.body);
+        let head_only = *request.method() == Method::Head;
+        let router = self.router.clone();
+        self.dispatch(id, head_only, true, move || router.handle(&mut request));
+    }
+
+    /// 把流交给线程池,worker调用`respond`得到响应后直接写回连接
+    /// `complete`为假表示还没有收到完整的请求,回复之后需要重置该流让客户端停止发送
+    fn dispatch<F>(&self, id: u32, head_only: bool, complete: bool, respond: F)
+    where
+        F: FnOnce() -> Response + Send + 'static,
+    {
+        // 线程池已经停止时任务会被丢弃,guard随之被丢弃并拒绝该流
+        let mut guard = StreamGuard { shared: self.shared.clone(), id, code: Some(ErrorCode::RefusedStream) };
+        self.spawner.execute(move || {
+            guard.code = Some(ErrorCode::InternalError);
+            let response = respond();
+            guard.code = None;
+
+            let shared = &guard.shared;
+            match send_response(shared, id, response, head_only) {
+                Ok(()) if !complete => {
+                    let _ = shared.reset(id, ErrorCode::NoError);
+                }
+                Ok(()) => {}
+                // 客户端一直不更新窗口,放弃这个响应
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    let _ = shared.reset(id, ErrorCode::Cancel);
+                }
+                Err(_) => {}
+            }
+            shared.finish(id);
+        });
+    }
+
+    fn go_away(&self, code: ErrorCode, message: &str) {
+        let mut payload = self.last_stream_id.to_be_bytes().to_vec();
+        payload.extend_from_slice(&u32::from(code).to_be_bytes());
+        payload.extend_from_slice(message.as_bytes());
+        let _ = self.shared.write_frame(FrameType::GoAway, 0, 0, &payload);
+    }
+}
+
+/// 读帧的线程与处理各个流的worker共享的部分
+struct Shared {
+    /// 一个帧必须完整地写入后才能写下一个帧
+    writer: Mutex<TcpStream>,
+    flow: Mutex<Flow>,
+    /// 发送窗口增大、流被关闭或连接被关闭时通知等待发送窗口的worker
+    flow_changed: Condvar,
+    /// 等待发送窗口的最长时间
+    send_timeout: Duration,
+}
+
+/// 发送方向的流量控制状态
+struct Flow {
+    /// 连接的发送窗口,对端的SETTINGS可能使流的窗口变为负数,因此使用有符号数
+    window: i64,
+    /// 打开的流及其发送窗口
+    streams: HashMap<u32, i64>,
+    initial_window: i64,
+    max_frame_size: usize,
+    closed: bool,
+}
+
+impl Shared {
+    fn write_frame(&self, kind: FrameType, flags: u8, stream_id: u32, payload: &[u8]) -> io::Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        frame::write_frame(&mut *writer, kind, flags, stream_id, payload)
+    }
+
+    /// 发送头部块,超过最大帧长度时拆分为HEADERS和若干CONTINUATION帧,这些帧之间不能插入其他帧
+    fn send_headers(&self, stream_id: u32, block: &[u8], end_stream: bool) -> io::Result<()> {
+        let max_frame_size = self.flow.lock().unwrap().max_frame_size;
+        let mut writer = self.writer.lock().unwrap();
+        let mut chunks = block.chunks(max_frame_size).peekable();
+        let mut kind = FrameType::Headers;
+        let mut flags = if end_stream { frame::END_STREAM } else { 0 };
+        while let Some(chunk) = chunks.next() {
+            if chunks.peek().is_none() {
+                flags |= frame::END_HEADERS;
+            }
+            frame::write_frame(&mut *writer, kind, flags, stream_id, chunk)?;
+            kind = FrameType::Continuation;
+            flags = 0;
+        }
+        Ok(())
+    }
+
+    /// 等待发送窗口,返回这次可以发送的字节数
+    /// 流已经关闭时返回`ConnectionAborted`错误,超时返回`TimedOut`错误
+    fn reserve(&self, stream_id: u32, want: usize) -> io::Result<usize> {
+        let aborted = || io::Error::new(io::ErrorKind::ConnectionAborted, "stream closed");
+        let mut flow = self.flow.lock().unwrap();
+        loop {
+            if flow.closed {
+                return Err(aborted());
+            }
+            let stream_window = *flow.streams.get(&stream_id).ok_or_else(aborted)?;
+            let available = flow.window.min(stream_window).min(flow.max_frame_size as i64);
+            if available > 0 {
+                let len = want.min(available as usize);
+                flow.window -= len as i64;
+                flow.streams.insert(stream_id, stream_window - len as i64);
+                return Ok(len);
+            }
+
+            let (guard, result) = self.flow_changed.wait_timeout(flow, self.send_timeout).unwrap();
+            flow = guard;
+            if result.timed_out() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "flow control window not updated"));
+            }
+        }
+    }
+
+    /// 增大发送窗口,超过允许的最大值时返回`false`,未知的流被忽略
+    fn window_update(&self, stream_id: u32, increment: u32) -> bool {
+        let mut flow = self.flow.lock().unwrap();
+        let window = match stream_id {
+            0 => &mut flow.window,
+            _ => match flow.streams.get_mut(&stream_id) {
+                Some(window) => window,
+                None => return true,
+            },
+        };
+        *window += increment as i64;
+        let valid = *window <= frame::MAX_WINDOW_SIZE as i64;
+        self.flow_changed.notify_all();
+        valid
+    }
+
+    /// 对端修改了流的初始窗口,所有打开的流按差值调整
+    fn set_initial_window(&self, value: u32) -> bool {
+        let mut flow = self.flow.lock().unwrap();
+        let delta = value as i64 - flow.initial_window;
+        flow.initial_window = value as i64;
+        let mut valid = true;
+        for window in flow.streams.values_mut() {
+            *window += delta;
+            valid &= *window <= frame::MAX_WINDOW_SIZE as i64;
+        }
+        self.flow_changed.notify_all();
+        valid
+    }
+
+    fn open(&self, stream_id: u32) {
+        let mut flow = self.flow.lock().unwrap();
+        let window = flow.initial_window;
+        flow.streams.insert(stream_id, window);
+    }
+
+    fn finish(&self, stream_id: u32) {
+        self.flow.lock().unwrap().streams.remove(&stream_id);
+        self.flow_changed.notify_all();
+    }
+
+    fn reset(&self, stream_id: u32, code: ErrorCode) -> io::Result<()> {
+        self.finish(stream_id);
+        self.write_frame(FrameType::RstStream, 0, stream_id, &u32::from(code).to_be_bytes())
+    }
+
+    fn active_streams(&self) -> usize {
+        self.flow.lock().unwrap().streams.len()
+    }
+
+    fn close(&self) {
+        self.flow.lock().unwrap().closed = true;
+        self.flow_changed.notify_all();
+    }
+}
+
+/// 随任务一起交给线程池,没有正常写完响应就被丢弃时(任务被丢弃或处理函数panic)重置该流
+struct StreamGuard {
+    shared: Arc<Shared>,
+    id: u32,
+    code: Option<ErrorCode>,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        if let Some(code) = self.code {
+            let _ = self.shared.reset(self.id, code);
+        }
+    }
+}
+
+/// 把DATA帧的负载作为`Write`,每次写入前等待发送窗口
+struct DataWriter<'a> {
+    shared: &'a Shared,
+    stream_id: u32,
+}
+
+impl Write for DataWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let len = self.shared.reserve(self.stream_id, buf.len())?;
+        self.shared.write_frame(FrameType::Data, 0, self.stream_id, &buf[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn send_response(shared: &Shared, stream_id: u32, response: Response, head_only: bool) -> io::Result<()> {
+    let (status, headers, mut body) = response.into_parts();
+    if let Body::Takeover(_) = body {
+        let error = HttpError::new(501, "long-lived responses are not supported over HTTP/2");
+        return send_response(shared, stream_id, error.into_response(), head_only);
+    }
+
+    // 204和304响应不能带body,也就不需要content-length
+    let bodiless = status == 204 || status == 304;
+    let status = status.to_string();
+    let content_length = body.len().to_string();
+    let names: Vec<String> = headers.iter().map(|(name, _)| name.to_ascii_lowercase()).collect();
+    let mut fields = vec![(":status", status.as_str())];
+    for (name, (_, value)) in names.iter().zip(headers.iter()) {
+        if !CONNECTION_HEADERS.contains(&name.as_str()) {
+            fields.push((name.as_str(), value));
+        }
+    }
+    if !bodiless && !headers.contains("Content-Length") {
+        fields.push(("content-length", content_length.as_str()));
+    }
+
+    let mut block = Vec::new();
+    Encoder::new().encode(fields, &mut block);
+    let end_stream = head_only || bodiless || body.is_empty();
+    shared.send_headers(stream_id, &block, end_stream)?;
+    if !end_stream {
+        body.write_to(&mut DataWriter { shared, stream_id })?;
+        shared.write_frame(FrameType::Data, frame::END_STREAM, stream_id, &[])?;
+    }
+    Ok(())
+}
+
+/// 由解码后的头部构造请求,伪头部`:authority`转换为`Host`头部
+fn build_request(fields: Vec<(String, String)>, peer_addr: Option<SocketAddr>) -> Result<Request, &'static str> {
+    let (mut method, mut scheme, mut authority, mut path) = (None, None, None, None);
+    let mut headers = Vec::new();
+    for (name, value) in fields {
+        if let Some(pseudo) = name.strip_prefix(':') {
+            if !headers.is_empty() {
+                return Err("pseudo-header after regular header");
+            }
+            let slot = match pseudo {
+                "method" => &mut method,
+                "scheme" => &mut scheme,
+                "authority" => &mut authority,
+                "path" => &mut path,
+                _ => return Err("unknown pseudo-header"),
+            };
+            if slot.replace(value).is_some() {
+                return Err("duplicate pseudo-header");
+            }
+        } else if name.bytes().any(|byte| byte.is_ascii_uppercase()) {
+            return Err("uppercase header name");
+        } else if CONNECTION_HEADERS.contains(&name.as_str()) {
+            return Err("connection-specific header");
+        } else {
+            headers.push((name, value));
+        }
+    }
+
+    let (method, path) = match (method, scheme, path) {
+        (Some(method), Some(_), Some(path)) if !path.is_empty() => (method, path),
+        _ => return Err("missing pseudo-header"),
+    };
+    let mut request = Request::new(Method::from(method.as_str()), &path);
+    request.set_version("HTTP/2.0");
+    if let Some(authority) = authority {
+        if !headers.iter().any(|(name, _)| name == "host") {
+            request.headers_mut().append("Host", authority);
+        }
+    }
+    for (name, value) in headers {
+        request.headers_mut().append(name, value);
+    }
+    if let Some(addr) = peer_addr {
+        request = request.with_peer_addr(addr);
+    }
+    Ok(request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn builds_request_from_pseudo_headers() {
+        let request = build_request(
+            fields(&[(":method", "POST"), (":scheme", "http"), (":authority", "example.com"), (":path", "/a%20b?x=1"), ("cookie", "a=1"), ("cookie", "b=2")]),
+            None,
+        )
+        .unwrap();
+        assert_eq!(&Method::Post, request.method());
+        assert_eq!("/a b", request.path());
+        assert_eq!(Some("x=1"), request.query());
+        assert_eq!("HTTP/2.0", request.version());
+        assert_eq!(Some("example.com"), request.header("Host"));
+        assert_eq!(Some("2".to_string()), request.cookie("b"));
+
+        assert!(build_request(fields(&[(":method", "GET"), (":scheme", "http")]), None).is_err());
+        assert!(build_request(fields(&[(":method", "GET"), (":scheme", "http"), (":path", "/"), ("Host", "x")]), None).is_err());
+        assert!(build_request(fields(&[(":method", "GET"), ("host", "x"), (":scheme", "http"), (":path", "/")]), None).is_err());
+        assert!(build_request(fields(&[(":method", "GET"), (":scheme", "http"), (":path", "/"), ("connection", "close")]), None).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use std::io::{self, Read, Write};
+
+/// 帧头部固定为9个字节:24位长度、8位类型、8位标志、1位保留位和31位流标识符
+pub const FRAME_HEADER_LEN: usize = 9;
+/// `SETTINGS_MAX_FRAME_SIZE`的初始值,也是它允许的最小值
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16_384;
+/// `SETTINGS_MAX_FRAME_SIZE`允许的最大值
+pub const MAX_FRAME_SIZE_LIMIT: usize = (1 << 24) - 1;
+/// 连接和流的流量控制窗口的初始值
+pub const DEFAULT_WINDOW_SIZE: u32 = 65_535;
+/// 流量控制窗口允许的最大值
+pub const MAX_WINDOW_SIZE: u32 = (1 << 31) - 1;
+
+/// DATA和HEADERS帧的标志:这是该流上发送方的最后一帧
+pub const END_STREAM: u8 = 0x1;
+/// SETTINGS和PING帧的标志:这是对对端的确认
+pub const ACK: u8 = 0x1;
+/// HEADERS和CONTINUATION帧的标志:头部块到此结束
+pub const END_HEADERS: u8 = 0x4;
+pub const PADDED: u8 = 0x8;
+/// HEADERS帧的标志:帧中带有优先级信息
+pub const PRIORITY: u8 = 0x20;
+
+/// SETTINGS帧中的参数
+pub const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+pub const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+pub const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+pub const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+pub const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+pub const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+/// 帧类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameType {
+    Data,
+    Headers,
+    Priority,
+    RstStream,
+    Settings,
+    PushPromise,
+    Ping,
+    GoAway,
+    WindowUpdate,
+    Continuation,
+    /// 未知类型的帧必须被忽略
+    Unknown(u8),
+}
+
+impl From<u8> for FrameType {
+    fn from(kind: u8) -> FrameType {
+        match kind {
+            0x0 => FrameType::Data,
+            0x1 => FrameType::Headers,
+            0x2 => FrameType::Priority,
+            0x3 => FrameType::RstStream,
+            0x4 => FrameType::Settings,
+            0x5 => FrameType::PushPromise,
+            0x6 => FrameType::Ping,
+            0x7 => FrameType::GoAway,
+            0x8 => FrameType::WindowUpdate,
+            0x9 => FrameType::Continuation,
+            _ => FrameType::Unknown(kind),
+        }
+    }
+}
+
+impl From<FrameType> for u8 {
+    fn from(kind: FrameType) -> u8 {
+        match kind {
+            FrameType::Data => 0x0,
+            FrameType::Headers => 0x1,
+            FrameType::Priority => 0x2,
+            FrameType::RstStream => 0x3,
+            FrameType::Settings => 0x4,
+            FrameType::PushPromise => 0x5,
+            FrameType::Ping => 0x6,
+            FrameType::GoAway => 0x7,
+            FrameType::WindowUpdate => 0x8,
+            FrameType::Continuation => 0x9,
+            FrameType::Unknown(kind) => kind,
+        }
+    }
+}
+
+/// RST_STREAM和GOAWAY帧中的错误码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NoError,
+    ProtocolError,
+    InternalError,
+    FlowControlError,
+    SettingsTimeout,
+    StreamClosed,
+    FrameSizeError,
+    RefusedStream,
+    Cancel,
+    CompressionError,
+    ConnectError,
+    EnhanceYourCalm,
+    InadequateSecurity,
+    Http11Required,
+    /// 未知的错误码不能被当作错误,可以按`InternalError`处理
+    Unknown(u32),
+}
+
+impl From<u32> for ErrorCode {
+    fn from(code: u32) -> ErrorCode {
+        match code {
+            0x0 => ErrorCode::NoError,
+            0x1 => ErrorCode::ProtocolError,
+            0x2 => ErrorCode::InternalError,
+            0x3 => ErrorCode::FlowControlError,
+            0x4 => ErrorCode::SettingsTimeout,
+            0x5 => ErrorCode::StreamClosed,
+            0x6 => ErrorCode::FrameSizeError,
+            0x7 => ErrorCode::RefusedStream,
+            0x8 => ErrorCode::Cancel,
+            0x9 => ErrorCode::CompressionError,
+            0xa => ErrorCode::ConnectError,
+            0xb => ErrorCode::EnhanceYourCalm,
+            0xc => ErrorCode::InadequateSecurity,
+            0xd => ErrorCode::Http11Required,
+            _ => ErrorCode::Unknown(code),
+        }
+    }
+}
+
+impl From<ErrorCode> for u32 {
+    fn from(code: ErrorCode) -> u32 {
+        match code {
+            ErrorCode::NoError => 0x0,
+            ErrorCode::ProtocolError => 0x1,
+            ErrorCode::InternalError => 0x2,
+            ErrorCode::FlowControlError => 0x3,
+            ErrorCode::SettingsTimeout => 0x4,
+            ErrorCode::StreamClosed => 0x5,
+            ErrorCode::FrameSizeError => 0x6,
+            ErrorCode::RefusedStream => 0x7,
+            ErrorCode::Cancel => 0x8,
+            ErrorCode::CompressionError => 0x9,
+            ErrorCode::ConnectError => 0xa,
+            ErrorCode::EnhanceYourCalm => 0xb,
+            ErrorCode::InadequateSecurity => 0xc,
+            ErrorCode::Http11Required => 0xd,
+            ErrorCode::Unknown(code) => code,
+        }
+    }
+}
+
+/// 帧头部
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    /// 负载的字节数,不包括头部本身
+    pub length: usize,
+    pub kind: FrameType,
+    pub flags: u8,
+    /// 0表示该帧作用于整个连接
+    pub stream_id: u32,
+}
+
+impl FrameHeader {
+    pub fn new(kind: FrameType, flags: u8, stream_id: u32, length: usize) -> FrameHeader {
+        FrameHeader { length, kind, flags, stream_id }
+    }
+
+    pub fn parse(bytes: &[u8; FRAME_HEADER_LEN]) -> FrameHeader {
+        FrameHeader {
+            length: u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]) as usize,
+            kind: FrameType::from(bytes[3]),
+            flags: bytes[4],
+            stream_id: read_u31(&bytes[5..9]),
+        }
+    }
+
+    pub fn encode(&self) -> [u8; FRAME_HEADER_LEN] {
+        let length = (self.length as u32).to_be_bytes();
+        let stream_id = (self.stream_id & MAX_WINDOW_SIZE).to_be_bytes();
+        [
+            length[1], length[2], length[3],
+            u8::from(self.kind),
+            self.flags,
+            stream_id[0], stream_id[1], stream_id[2], stream_id[3],
+        ]
+    }
+
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+/// 一个完整的帧
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub header: FrameHeader,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: FrameType, flags: u8, stream_id: u32, payload: Vec<u8>) -> Frame {
+        Frame { header: FrameHeader::new(kind, flags, stream_id, payload.len()), payload }
+    }
+
+    /// 读取一个帧,负载超过`max_size`时返回`InvalidData`错误且不读取负载
+    pub fn read_from<R: Read>(reader: &mut R, max_size: usize) -> io::Result<Frame> {
+        let mut head = [0; FRAME_HEADER_LEN];
+        reader.read_exact(&mut head)?;
+        let header = FrameHeader::parse(&head);
+        if header.length > max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("frame of {} bytes exceeds the maximum of {}", header.length, max_size),
+            ));
+        }
+        let mut payload = vec![0; header.length];
+        reader.read_exact(&mut payload)?;
+        Ok(Frame { header, payload })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_frame(writer, self.header.kind, self.header.flags, self.header.stream_id, &self.payload)
+    }
+
+    /// 去掉DATA和HEADERS帧中的填充,填充长度不合法时返回`None`
+    pub fn unpadded(&self) -> Option<&[u8]> {
+        if !self.header.has_flag(PADDED) {
+            return Some(&self.payload);
+        }
+        let (&pad_len, rest) = self.payload.split_first()?;
+        rest.len().checked_sub(pad_len as usize).map(|len| &rest[..len])
+    }
+}
+
+/// 写入一个帧,头部和负载合并成一次写入
+pub fn write_frame<W: Write>(writer: &mut W, kind: FrameType, flags: u8, stream_id: u32, payload: &[u8]) -> io::Result<()> {
+    let mut buf = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    buf.extend_from_slice(&FrameHeader::new(kind, flags, stream_id, payload.len()).encode());
+    buf.extend_from_slice(payload);
+    writer.write_all(&buf)
+}
+
+/// 解析SETTINGS帧的负载,每个参数占6个字节,长度不是6的倍数时返回`None`
+pub fn parse_settings(payload: &[u8]) -> Option<Vec<(u16, u32)>> {
+    if !payload.len().is_multiple_of(6) {
+        return None;
+    }
+    let settings = payload
+        .chunks(6)
+        .map(|chunk| {
+            let id = u16::from_be_bytes([chunk[0], chunk[1]]);
+            let value = u32::from_be_bytes([chunk[2], chunk[3], chunk[4], chunk[5]]);
+            (id, value)
+        })
+        .collect();
+    Some(settings)
+}
+
+pub fn encode_settings(settings: &[(u16, u32)]) -> Vec<u8> {
+    settings
+        .iter()
+        .flat_map(|&(id, value)| id.to_be_bytes().into_iter().chain(value.to_be_bytes()))
+        .collect()
+}
+
+/// 读取去掉最高保留位的31位整数,用于流标识符和窗口增量
+pub fn read_u31(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) & MAX_WINDOW_SIZE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_round_trip() {
+        let header = FrameHeader::new(FrameType::Headers, END_HEADERS | END_STREAM, 3, 0x1234);
+        let bytes = header.encode();
+        assert_eq!([0x00, 0x12, 0x34, 0x01, 0x05, 0x00, 0x00, 0x00, 0x03], bytes);
+        assert_eq!(header, FrameHeader::parse(&bytes));
+
+        // 读取时忽略保留位
+        let reserved = [0, 0, 0, 0x8, 0, 0x80, 0, 0, 0x1];
+        assert_eq!(1, FrameHeader::parse(&reserved).stream_id);
+        assert_eq!(FrameType::WindowUpdate, FrameHeader::parse(&reserved).kind);
+    }
+
+    #[test]
+    // 超过最大长度的帧被拒绝,填充被去掉
+    fn read_frames() {
+        let mut raw = Vec::new();
+        write_frame(&mut raw, FrameType::Data, PADDED, 1, &[2, b'h', b'i', 0, 0]).unwrap();
+        let frame = Frame::read_from(&mut &raw[..], DEFAULT_MAX_FRAME_SIZE).unwrap();
+        assert_eq!(Some(&b"hi"[..]), frame.unpadded());
+        assert!(Frame::read_from(&mut &raw[..], 4).is_err());
+
+        let bad_padding = Frame::new(FrameType::Data, PADDED, 1, vec![5, b'h']);
+        assert_eq!(None, bad_padding.unpadded());
+    }
+}
@@ -0,0 +1,223 @@
+use crate::http2::hpack::table::DynamicTable;
+use crate::http2::hpack::{huffman, HpackError};
+
+/// 动态表默认的最大字节数,即`SETTINGS_HEADER_TABLE_SIZE`的初始值
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+/// 解码后头部列表默认的最大字节数,按RFC 7541 4.1计算,即每个字段的名称和值的长度再加32
+pub const DEFAULT_MAX_HEADER_LIST_SIZE: usize = 64 * 1024;
+/// 一个头部块默认最多包含的字段数
+pub const DEFAULT_MAX_FIELDS: usize = 100;
+
+/// HPACK解码器
+/// 动态表在同一个连接的所有头部块之间共享,因此每个连接只能有一个解码器,并且必须按接收顺序解码
+#[derive(Debug)]
+pub struct Decoder {
+    table: DynamicTable,
+    /// 对端通过动态表大小更新所能设置的上限
+    max_table_size: usize,
+    max_header_list_size: usize,
+    max_fields: usize,
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder {
+            table: DynamicTable::new(DEFAULT_TABLE_SIZE),
+            max_table_size: DEFAULT_TABLE_SIZE,
+            max_header_list_size: DEFAULT_MAX_HEADER_LIST_SIZE,
+            max_fields: DEFAULT_MAX_FIELDS,
+        }
+    }
+
+    /// 解码后头部列表的最大字节数,应当与通过`SETTINGS_MAX_HEADER_LIST_SIZE`告知对端的值相同
+    pub fn max_header_list_size(mut self, size: usize) -> Decoder {
+        self.max_header_list_size = size;
+        self
+    }
+
+    /// 一个头部块最多包含的字段数
+    pub fn max_fields(mut self, count: usize) -> Decoder {
+        self.max_fields = count;
+        self
+    }
+
+    /// 解码一个完整的头部块(HEADERS及其后续CONTINUATION帧拼接后的内容)
+    /// 反复引用同一个表项的头部块很小,解码后却可能非常大,因此按解码后的大小和字段数限制,
+    /// 超出时返回`HeaderListTooLarge`.此时头部块仍然会被解码到底,动态表保持与对端一致,连接可以继续使用
+    pub fn decode(&mut self, mut block: &[u8]) -> Result<Vec<(String, String)>, HpackError> {
+        let mut fields = Vec::new();
+        // 超出限制之后不再保存字段,只更新动态表
+        let mut budget = Budget { count: 0, size: 0, max_fields: self.max_fields, max_size: self.max_header_list_size };
+        while let Some(&first) = block.first() {
+            if first & 0x80 != 0 {
+                // 索引头部字段
+                let index = decode_int(&mut block, 7)?;
+                let (name, value) = self.table.get(index).ok_or(HpackError::InvalidIndex(index))?;
+                if budget.accept(name, value) {
+                    fields.push((name.to_string(), value.to_string()));
+                }
+            } else if first & 0x40 != 0 {
+                // 带增量索引的字面头部字段
+                let (name, value) = self.literal(&mut block, 6)?;
+                if budget.accept(&name, &value) {
+                    fields.push((name.clone(), value.clone()));
+                }
+                self.table.insert(name, value);
+            } else if first & 0x20 != 0 {
+                // 动态表大小更新只能出现在头部块的开头
+                if budget.count > 0 {
+                    return Err(HpackError::UnexpectedSizeUpdate);
+                }
+                let size = decode_int(&mut block, 5)?;
+                if size > self.max_table_size {
+                    return Err(HpackError::TableSizeTooLarge(size));
+                }
+                self.table.set_max_size(size);
+            } else {
+                // 不索引(0000)或永不索引(0001)的字面头部字段,两者对解码器来说没有区别
+                let (name, value) = self.literal(&mut block, 4)?;
+                if budget.accept(&name, &value) {
+                    fields.push((name, value));
+                }
+            }
+        }
+        if fields.len() < budget.count {
+            return Err(HpackError::HeaderListTooLarge);
+        }
+        Ok(fields)
+    }
+
+    /// 解析字面头部字段,名称可以引用表中已有的条目,也可以是紧随其后的字符串
+    fn literal(&self, block: &mut &[u8], prefix: u8) -> Result<(String, String), HpackError> {
+        let index = decode_int(block, prefix)?;
+        let name = if index == 0 {
+            decode_string(block)?
+        } else {
+            let (name, _) = self.table.get(index).ok_or(HpackError::InvalidIndex(index))?;
+            name.to_string()
+        };
+        Ok((name, decode_string(block)?))
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
+    }
+}
+
+/// 一个头部块中已经解码的字段数和按RFC 7541 4.1计算的大小
+struct Budget {
+    count: usize,
+    size: usize,
+    max_fields: usize,
+    max_size: usize,
+}
+
+impl Budget {
+    /// 计入一个字段,返回是否仍在限制之内
+    fn accept(&mut self, name: &str, value: &str) -> bool {
+        self.count += 1;
+        self.size += name.len() + value.len() + 32;
+        self.count <= self.max_fields && self.size <= self.max_size
+    }
+}
+
+/// 解码带有`prefix`位前缀的整数,前缀之外的高位属于表示类型,会被忽略
+pub(crate) fn decode_int(block: &mut &[u8], prefix: u8) -> Result<usize, HpackError> {
+    let (&first, rest) = block.split_first().ok_or(HpackError::UnexpectedEnd)?;
+    *block = rest;
+    let max = (1usize << prefix) - 1;
+    let mut value = first as usize & max;
+    if value < max {
+        return Ok(value);
+    }
+
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = block.split_first().ok_or(HpackError::UnexpectedEnd)?;
+        *block = rest;
+        // 超过28位的整数在实际中不会出现,提前拒绝以免溢出
+        if shift > 21 {
+            return Err(HpackError::IntegerOverflow);
+        }
+        value += ((byte & 0x7f) as usize) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn decode_string(block: &mut &[u8]) -> Result<String, HpackError> {
+    let huffman_coded = block.first().is_some_and(|first| first & 0x80 != 0);
+    let len = decode_int(block, 7)?;
+    if len > block.len() {
+        return Err(HpackError::UnexpectedEnd);
+    }
+    let (raw, rest) = block.split_at(len);
+    *block = rest;
+
+    let bytes = if huffman_coded { huffman::decode(raw)? } else { raw.to_vec() };
+    String::from_utf8(bytes).map_err(|_| HpackError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap()).collect()
+    }
+
+    fn pairs(fields: &[(String, String)]) -> Vec<(&str, &str)> {
+        fields.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect()
+    }
+
+    #[test]
+    // RFC 7541 C.3和C.4中的请求示例,第二个请求引用了第一个请求插入动态表的条目
+    fn rfc_examples() {
+        let expected = [(":method", "GET"), (":scheme", "http"), (":path", "/"), (":authority", "www.example.com")];
+
+        let mut decoder = Decoder::new();
+        let fields = decoder.decode(&hex("828684410f7777772e6578616d706c652e636f6d")).unwrap();
+        assert_eq!(expected.to_vec(), pairs(&fields));
+        let fields = decoder.decode(&hex("828684be58086e6f2d6361636865")).unwrap();
+        assert_eq!(expected[..].iter().chain(&[("cache-control", "no-cache")]).copied().collect::<Vec<_>>(), pairs(&fields));
+
+        let mut decoder = Decoder::new();
+        let fields = decoder.decode(&hex("828684418cf1e3c2e5f23a6ba0ab90f4ff")).unwrap();
+        assert_eq!(expected.to_vec(), pairs(&fields));
+    }
+
+    #[test]
+    fn rejects_malformed_blocks() {
+        let mut decoder = Decoder::new();
+        assert_eq!(Err(HpackError::InvalidIndex(0)), decoder.decode(&[0x80]));
+        assert_eq!(Err(HpackError::InvalidIndex(62)), decoder.decode(&[0xbe]));
+        assert_eq!(Err(HpackError::UnexpectedEnd), decoder.decode(&[0x41, 0x05, b'a']));
+        assert_eq!(Err(HpackError::TableSizeTooLarge(8192)), decoder.decode(&[0x3f, 0xe1, 0x3f]));
+        assert_eq!(Err(HpackError::UnexpectedSizeUpdate), decoder.decode(&[0x82, 0x20]));
+    }
+
+    #[test]
+    // 反复引用动态表中的一个大表项:头部块只有几KB,解码后却有几百MB
+    fn limits_decoded_size() {
+        let value = "v".repeat(4000);
+        let mut block = vec![0x40, 0x01, b'x'];
+        crate::http2::hpack::encoder::encode_int(value.len(), 7, 0, &mut block);
+        block.extend_from_slice(value.as_bytes());
+        block.extend(std::iter::repeat_n(0xbe, 60_000));
+
+        let mut decoder = Decoder::new();
+        assert_eq!(Err(HpackError::HeaderListTooLarge), decoder.decode(&block));
+        // 动态表照常更新,之后的头部块仍然可以引用这个表项
+        assert_eq!(vec![("x".to_string(), value)], decoder.decode(&[0xbe]).unwrap());
+
+        let mut decoder = Decoder::new().max_fields(2);
+        assert_eq!(2, decoder.decode(&[0x82, 0x86]).unwrap().len());
+        assert_eq!(Err(HpackError::HeaderListTooLarge), decoder.decode(&[0x82, 0x86, 0x84]));
+        let mut decoder = Decoder::new().max_header_list_size(60);
+        assert_eq!(Err(HpackError::HeaderListTooLarge), decoder.decode(&[0x82, 0x86]));
+    }
+}
@@ -0,0 +1,101 @@
+use crate::http2::hpack::huffman;
+use crate::http2::hpack::table::STATIC_TABLE;
+
+/// HPACK编码器
+/// 只使用静态表,不向动态表插入条目,因此编码结果不依赖之前编码过的头部块,
+/// 多个线程可以各自编码响应头部而不必按发送顺序串行化
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Encoder;
+
+impl Encoder {
+    pub fn new() -> Encoder {
+        Encoder
+    }
+
+    /// 将头部编码后追加到`dst`,名称应当已经是小写
+    pub fn encode<'a, I>(&self, fields: I, dst: &mut Vec<u8>)
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        for (name, value) in fields {
+            let exact = STATIC_TABLE.iter().position(|&entry| entry == (name, value));
+            if let Some(index) = exact {
+                encode_int(index + 1, 7, 0x80, dst);
+                continue;
+            }
+
+            // 不索引的字面头部字段,名称尽量引用静态表
+            match STATIC_TABLE.iter().position(|&(entry, _)| entry == name) {
+                Some(index) => encode_int(index + 1, 4, 0x00, dst),
+                None => {
+                    dst.push(0x00);
+                    encode_string(name.as_bytes(), dst);
+                }
+            }
+            encode_string(value.as_bytes(), dst);
+        }
+    }
+}
+
+/// 编码带有`prefix`位前缀的整数,`flags`是该字节中前缀之外的高位
+pub(crate) fn encode_int(mut value: usize, prefix: u8, flags: u8, dst: &mut Vec<u8>) {
+    let max = (1usize << prefix) - 1;
+    if value < max {
+        dst.push(flags | value as u8);
+        return;
+    }
+    dst.push(flags | max as u8);
+    value -= max;
+    while value >= 0x80 {
+        dst.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    dst.push(value as u8);
+}
+
+/// 编码字符串,Huffman编码更短时使用Huffman编码
+fn encode_string(src: &[u8], dst: &mut Vec<u8>) {
+    let huffman_len = huffman::encoded_len(src);
+    if huffman_len < src.len() {
+        encode_int(huffman_len, 7, 0x80, dst);
+        huffman::encode(src, dst);
+    } else {
+        encode_int(src.len(), 7, 0x00, dst);
+        dst.extend_from_slice(src);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http2::hpack::Decoder;
+
+    #[test]
+    fn round_trip() {
+        let fields = [
+            (":status", "200"),
+            (":status", "201"),
+            ("content-type", "text/html; charset=utf-8"),
+            ("x-request-id", "0123456789abcdef0123456789abcdef"),
+            ("set-cookie", "a=1"),
+            ("set-cookie", "b=2"),
+        ];
+        let mut block = Vec::new();
+        Encoder::new().encode(fields.iter().copied(), &mut block);
+        assert_eq!(0x88, block[0]);
+
+        let decoded = Decoder::new().decode(&block).unwrap();
+        let decoded: Vec<(&str, &str)> = decoded.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        assert_eq!(fields.to_vec(), decoded);
+    }
+
+    #[test]
+    // RFC 7541 C.1中的整数示例
+    fn integers() {
+        let mut dst = Vec::new();
+        encode_int(10, 5, 0, &mut dst);
+        encode_int(1337, 5, 0, &mut dst);
+        encode_int(42, 8, 0, &mut dst);
+        assert_eq!(vec![0x0a, 0x1f, 0x9a, 0x0a, 0x2a], dst);
+    }
+}
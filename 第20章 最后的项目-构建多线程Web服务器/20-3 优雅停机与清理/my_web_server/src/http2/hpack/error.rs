@@ -0,0 +1,35 @@
+use std::error::Error;
+use std::fmt;
+
+/// 头部块无法解码,对HTTP/2连接来说是`COMPRESSION_ERROR`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HpackError {
+    UnexpectedEnd,
+    IntegerOverflow,
+    InvalidIndex(usize),
+    Huffman,
+    InvalidUtf8,
+    /// 动态表大小更新超出了`SETTINGS_HEADER_TABLE_SIZE`
+    TableSizeTooLarge(usize),
+    /// 动态表大小更新出现在头部字段之后
+    UnexpectedSizeUpdate,
+    /// 解码后的头部超出了大小或字段数的限制,动态表已经正常更新,只需拒绝这一个流
+    HeaderListTooLarge,
+}
+
+impl fmt::Display for HpackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HpackError::UnexpectedEnd => write!(f, "header block ended unexpectedly"),
+            HpackError::IntegerOverflow => write!(f, "integer overflow"),
+            HpackError::InvalidIndex(index) => write!(f, "invalid table index {}", index),
+            HpackError::Huffman => write!(f, "invalid Huffman code"),
+            HpackError::InvalidUtf8 => write!(f, "header field is not valid UTF-8"),
+            HpackError::TableSizeTooLarge(size) => write!(f, "dynamic table size {} exceeds the limit", size),
+            HpackError::UnexpectedSizeUpdate => write!(f, "dynamic table size update after header fields"),
+            HpackError::HeaderListTooLarge => write!(f, "decoded header list too large"),
+        }
+    }
+}
+
+impl Error for HpackError {}
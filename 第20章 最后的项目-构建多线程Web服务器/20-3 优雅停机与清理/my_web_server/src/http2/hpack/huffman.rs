@@ -0,0 +1,113 @@
+use std::sync::OnceLock;
+use crate::http2::hpack::huffman_table::CODES;
+use crate::http2::hpack::HpackError;
+
+const EOS: u16 = 256;
+/// 节点的子节点编号带有该标记时表示叶子,低位是符号
+const LEAF: u16 = 0x8000;
+
+/// Huffman编码`src`后追加到`dst`,最后不足一个字节的部分用EOS的前缀(全1)补齐
+pub(crate) fn encode(src: &[u8], dst: &mut Vec<u8>) {
+    let mut bits: u64 = 0;
+    let mut count = 0;
+    for &byte in src {
+        let (code, len) = CODES[byte as usize];
+        bits = (bits << len) | code as u64;
+        count += len;
+        while count >= 8 {
+            count -= 8;
+            dst.push((bits >> count) as u8);
+        }
+    }
+    if count > 0 {
+        dst.push(((bits << (8 - count)) | (0xff >> count)) as u8);
+    }
+}
+
+/// Huffman编码后的字节数,用于决定是否值得编码
+pub(crate) fn encoded_len(src: &[u8]) -> usize {
+    let bits: usize = src.iter().map(|&byte| CODES[byte as usize].1 as usize).sum();
+    bits.div_ceil(8)
+}
+
+pub(crate) fn decode(src: &[u8]) -> Result<Vec<u8>, HpackError> {
+    let tree = tree();
+    let mut dst = Vec::with_capacity(src.len() * 8 / 5);
+    let mut node = 0;
+    // 自上一个符号以来读到的位数,以及这些位是否全为1,用于检查结尾的填充
+    let mut pending = 0;
+    let mut all_ones = true;
+
+    for &byte in src {
+        for shift in (0..8).rev() {
+            let bit = (byte >> shift) & 1;
+            let next = tree[node][bit as usize];
+            pending += 1;
+            all_ones &= bit == 1;
+            if next & LEAF != 0 {
+                let symbol = next & !LEAF;
+                if symbol == EOS {
+                    return Err(HpackError::Huffman);
+                }
+                dst.push(symbol as u8);
+                node = 0;
+                pending = 0;
+                all_ones = true;
+            } else if next == 0 {
+                return Err(HpackError::Huffman);
+            } else {
+                node = next as usize;
+            }
+        }
+    }
+
+    // 填充最多7位且必须是EOS的前缀
+    if pending > 7 || !all_ones {
+        return Err(HpackError::Huffman);
+    }
+    Ok(dst)
+}
+
+/// 由编码表构造的解码树,每个节点保存0和1两个分支,0号节点是根,因此0也表示分支不存在
+fn tree() -> &'static [[u16; 2]] {
+    static TREE: OnceLock<Vec<[u16; 2]>> = OnceLock::new();
+    TREE.get_or_init(|| {
+        let mut tree = vec![[0u16; 2]];
+        for (symbol, &(code, len)) in CODES.iter().enumerate() {
+            let mut node = 0;
+            for shift in (0..len).rev() {
+                let bit = ((code >> shift) & 1) as usize;
+                if shift == 0 {
+                    tree[node][bit] = LEAF | symbol as u16;
+                } else {
+                    if tree[node][bit] == 0 {
+                        tree.push([0; 2]);
+                        tree[node][bit] = (tree.len() - 1) as u16;
+                    }
+                    node = tree[node][bit] as usize;
+                }
+            }
+        }
+        tree
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // RFC 7541 C.4.1中的"www.example.com"
+    fn round_trip() {
+        let encoded = [0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff];
+        let mut dst = Vec::new();
+        encode(b"www.example.com", &mut dst);
+        assert_eq!(&encoded[..], &dst[..]);
+        assert_eq!(encoded.len(), encoded_len(b"www.example.com"));
+        assert_eq!(b"www.example.com".to_vec(), decode(&encoded).unwrap());
+
+        // 填充超过7位或者不是全1都是错误
+        assert!(decode(&[0xf1, 0xff]).is_err());
+        assert!(decode(&[0xf1, 0xe0]).is_err());
+    }
+}
@@ -0,0 +1,12 @@
+pub mod decoder;
+pub use decoder::Decoder;
+
+pub mod encoder;
+pub use encoder::Encoder;
+
+pub mod error;
+pub use error::HpackError;
+
+mod huffman;
+mod huffman_table;
+mod table;
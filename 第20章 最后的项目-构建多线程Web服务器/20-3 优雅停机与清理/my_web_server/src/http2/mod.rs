@@ -0,0 +1,20 @@
+pub mod frame;
+pub use frame::{ErrorCode, Frame, FrameHeader, FrameType};
+
+pub mod hpack;
+
+pub(crate) mod connection;
+
+use crate::http::{Method, Request};
+
+/// 客户端在HTTP/2连接开头发送的连接前言,之后紧接着一个SETTINGS帧
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+/// 连接前言的前半部分恰好能被当作请求行为`PRI * HTTP/2.0`、没有头部的HTTP/1.1请求解析,
+/// 剩下的`SM\r\n\r\n`留在连接中
+pub(crate) fn is_preface(request: &Request) -> bool {
+    *request.method() == Method::Other("PRI".to_string())
+        && request.path() == "*"
+        && request.version() == "HTTP/2.0"
+        && request.headers().iter().next().is_none()
+}
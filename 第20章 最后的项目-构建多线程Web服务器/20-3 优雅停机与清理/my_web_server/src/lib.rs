@@ -1 +1,17 @@
-pub mod pool;
\ No newline at end of file
+pub mod pool;
+pub mod template;
+pub mod http;
+pub mod http2;
+pub mod form;
+pub mod files;
+pub mod cgi;
+pub mod sse;
+pub mod routing;
+pub mod middleware;
+pub mod session;
+pub mod auth;
+pub mod vhost;
+pub mod config;
+pub mod server;
+pub mod app;
+pub mod testing;
@@ -1,44 +1,29 @@
-use std::fs;
-use std::io::{Read, Write};
-use std::net::{TcpListener, TcpStream};
-use std::thread;
-use std::time::Duration;
-use my_web_server::pool;
+use std::process;
+use my_web_server::app::{self, AppState};
+use my_web_server::config::{Config, ConfigWatcher};
+use my_web_server::server::Server;
 
-fn main() {
-    let listener = TcpListener::bind("127.0.0.1:7878").unwrap();
-    let pool = pool::ThreadPool::new(4);
-    for stream in listener.incoming().take(2) {
-        let stream = stream.unwrap();
-        pool.execute(||{
-            handle_connection(stream);
-        })
-    }
+/// 配置文件,包括模板目录、静态文件目录、限流和请求大小限制
+const CONFIG_PATH: &str = "server.json";
 
-    println!("Shutting down.");
-}
+fn main() {
+    let config = Config::load(CONFIG_PATH).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    let state = AppState::new();
+    let server = Server::bind("127.0.0.1:7878", app::configure(&config, &state))
+        .unwrap()
+        .limits(config.limits())
+        .http2(config.http2);
 
-/// 本函数用于从TCP连接中读取数据并打印请求相关信息
-/// 通常来讲,读取操作是不需要mut的.但是TcpStream.read()方法需要一个可变引用(这里的可变引用指的是mut stream: TcpStream)
-/// 这是因为TcpStream内部维护了一个缓冲区,每次读取数据时都会将数据写入到这个缓冲区中,因此需要一个可变引用来修改这个缓冲区
-fn handle_connection(mut stream: TcpStream) {
-    let mut buffer = [0; 512];
-    stream.read(&mut buffer).unwrap();
+    // 修改配置文件或向进程发送SIGHUP时重新加载配置,监听的端口和线程池保持不变
+    let reload = server.reload_handle();
+    ConfigWatcher::new(CONFIG_PATH)
+        .spawn(move |config| reload.reload(app::configure(&config, &state), config.limits()))
+        .unwrap();
 
-    let get = "GET / HTTP/1.1\r\n";
-    let sleep = "GET /sleep HTTP/1.1\r\n";
-    let (status_line, filename) = if buffer.starts_with(get.as_bytes()) {
-        ("HTTP/1.1 200 OK\r\n\r\n", "hello.html")
-    } else if buffer.starts_with(sleep.as_bytes()) {
-        thread::sleep(Duration::from_secs(5));
-        ("HTTP/1.1 200 OK\r\n\r\n", "hello.html")
-    }
-    else {
-        ("HTTP/1.1 404 NOT FOUND\r\n\r\n", "404.html")
-    };
+    server.run();
 
-    let contents = fs::read_to_string(filename).unwrap();
-    let response = format!("{}{}", status_line, contents);
-    stream.write(response.as_bytes()).unwrap();
-    stream.flush().unwrap();
-}
\ No newline at end of file
+    println!("Shutting down.");
+}
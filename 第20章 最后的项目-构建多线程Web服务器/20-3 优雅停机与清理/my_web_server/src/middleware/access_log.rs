@@ -0,0 +1,141 @@
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::http::{Request, Response};
+use crate::middleware::{Middleware, Next};
+
+/// 访问日志,每个请求以Combined Log Format记录一行,例如:
+/// `127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326 "-" "curl/8.0"`
+pub struct AccessLog {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl AccessLog {
+    pub fn new<W: Write + Send + 'static>(writer: W) -> AccessLog {
+        AccessLog { writer: Mutex::new(Box::new(writer)) }
+    }
+
+    /// 以追加方式打开日志文件,文件不存在时会被创建
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<AccessLog> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(AccessLog::new(file))
+    }
+
+    /// 记录一个已经处理完的请求
+    pub fn log(&self, request: &Request, response: &Response) {
+        let line = format_line(request, response, SystemTime::now());
+        let mut writer = self.writer.lock().unwrap();
+        if let Err(e) = writer.write_all(line.as_bytes()).and_then(|_| writer.flush()) {
+            eprintln!("failed to write access log: {}", e);
+        }
+    }
+}
+
+impl Middleware for AccessLog {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        let response = next.run(request);
+        self.log(request, &response);
+        response
+    }
+}
+
+fn format_line(request: &Request, response: &Response, time: SystemTime) -> String {
+    let client = request
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "-".to_string());
+    let target = match request.query() {
+        Some(query) => format!("{}?{}", request.path(), query),
+        None => request.path().to_string(),
+    };
+    let request_line = format!("{} {} {}", request.method(), target, request.version());
+    let bytes = match response.body_len() {
+        0 => "-".to_string(),
+        len => len.to_string(),
+    };
+    format!(
+        "{} - - [{}] \"{}\" {} {} \"{}\" \"{}\"\n",
+        client,
+        clf_time(time),
+        escape(&request_line),
+        response.status(),
+        bytes,
+        escape(request.header("Referer").unwrap_or("-")),
+        escape(request.header("User-Agent").unwrap_or("-")),
+    )
+}
+
+/// 转义引号、反斜杠和控制字符,防止客户端伪造日志行
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// UTC时间,格式为`10/Oct/2000:13:55:36 +0000`
+fn clf_time(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let (year, month, day) = civil_from_days((secs / 86400) as i64);
+    let rem = secs % 86400;
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// 将1970-01-01以来的天数转换为公历的年月日(Howard Hinnant的算法)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::http::Method;
+
+    #[test]
+    fn combined_log_format() {
+        let request = Request::new(Method::Get, "/a%20b?x=1")
+            .with_header("User-Agent", "evil\"\nagent")
+            .with_peer_addr("192.0.2.1:4000".parse().unwrap());
+        let response = Response::text(200, "hello");
+        let time = UNIX_EPOCH + Duration::from_secs(971_186_136);
+
+        assert_eq!(
+            "192.0.2.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /a b?x=1 HTTP/1.1\" 200 5 \"-\" \"evil\\\"\\x0aagent\"\n",
+            format_line(&request, &response, time)
+        );
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!((1970, 1, 1), civil_from_days(0));
+        assert_eq!((2000, 2, 29), civil_from_days(11_016));
+        assert_eq!((2024, 12, 31), civil_from_days(20_088));
+    }
+}
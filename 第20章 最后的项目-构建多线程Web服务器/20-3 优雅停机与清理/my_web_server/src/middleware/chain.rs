@@ -0,0 +1,27 @@
+use std::sync::Arc;
+use crate::http::{Request, Response};
+
+/// 中间件
+/// 中间件可以在请求到达处理函数之前检查或修改请求,也可以不调用`next`直接返回响应
+pub trait Middleware: Send + Sync {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response;
+}
+
+/// 中间件链中剩余的部分,调用`run()`将请求交给下一个中间件或最终的处理函数
+pub struct Next<'a> {
+    middleware: &'a [Arc<dyn Middleware>],
+    endpoint: &'a dyn Fn(&mut Request) -> Response,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(middleware: &'a [Arc<dyn Middleware>], endpoint: &'a dyn Fn(&mut Request) -> Response) -> Next<'a> {
+        Next { middleware, endpoint }
+    }
+
+    pub fn run(self, request: &mut Request) -> Response {
+        match self.middleware.split_first() {
+            Some((first, rest)) => first.handle(request, Next::new(rest, self.endpoint)),
+            None => (self.endpoint)(request),
+        }
+    }
+}
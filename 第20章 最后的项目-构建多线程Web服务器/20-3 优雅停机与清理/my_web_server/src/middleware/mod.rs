@@ -0,0 +1,8 @@
+pub mod chain;
+pub use chain::{Middleware, Next};
+
+pub mod rate_limit;
+pub use rate_limit::{RateLimitConfig, RateLimiter};
+
+pub mod access_log;
+pub use access_log::AccessLog;
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::http::{HttpError, IntoResponse, Request, Response};
+use crate::middleware::{Middleware, Next};
+
+/// 限流配置
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    /// 每秒补充的令牌数,即长期允许的平均请求速率
+    rate: f64,
+    /// 令牌桶的容量,即允许的瞬时突发请求数
+    burst: u32,
+    /// 令牌桶闲置超过该时长后会被后台线程清理
+    idle_timeout: Duration,
+    /// 受信任的反向代理,只有来自这些地址的请求才会采信`X-Forwarded-For`
+    trusted_proxies: Vec<IpAddr>,
+}
+
+impl RateLimitConfig {
+    /// # Panics
+    /// `rate`不是正数或`burst`为0时会触发panic
+    pub fn new(rate: f64, burst: u32) -> RateLimitConfig {
+        assert!(rate > 0.0, "rate must be positive");
+        assert!(burst > 0, "burst must be positive");
+        RateLimitConfig {
+            rate,
+            burst,
+            idle_timeout: Duration::from_secs(60),
+            trusted_proxies: Vec::new(),
+        }
+    }
+
+    pub fn idle_timeout(mut self, idle_timeout: Duration) -> RateLimitConfig {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    pub fn trust_proxy(mut self, proxy: IpAddr) -> RateLimitConfig {
+        self.trusted_proxies.push(proxy);
+        self
+    }
+}
+
+/// 按客户端IP限流的令牌桶限流器
+/// 每个IP拥有一个容量为`burst`的令牌桶,每个请求消耗一个令牌,令牌以`rate`的速率补充.
+/// 令牌耗尽时返回`429 Too Many Requests`,并通过`Retry-After`告知客户端需要等待的秒数
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    /// 计算到`now`为止补充后的令牌数
+    fn tokens_at(&self, now: Instant, config: &RateLimitConfig) -> f64 {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * config.rate).min(config.burst as f64)
+    }
+
+    /// 按照经过的时间补充令牌
+    fn refill(&mut self, now: Instant, config: &RateLimitConfig) {
+        self.tokens = self.tokens_at(now, config);
+        self.updated = now;
+    }
+}
+
+impl RateLimiter {
+    /// 创建限流器,同时启动一个清理闲置令牌桶的后台线程
+    /// 后台线程只持有令牌桶的弱引用,限流器被丢弃后线程会自行退出
+    pub fn new(config: RateLimitConfig) -> RateLimiter {
+        let buckets = Arc::new(Mutex::new(HashMap::new()));
+        let weak = Arc::downgrade(&buckets);
+        let evict_config = config.clone();
+        thread::Builder::new()
+            .name("rate-limit-evictor".to_string())
+            .spawn(move || evict_loop(weak, evict_config))
+            .expect("failed to spawn rate limit evictor");
+
+        RateLimiter { config, buckets }
+    }
+
+    /// 为`ip`消耗一个令牌
+    /// 令牌不足时返回需要等待的时长
+    pub fn check(&self, ip: IpAddr) -> Result<(), Duration> {
+        self.check_at(ip, Instant::now())
+    }
+
+    fn check_at(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(ip).or_insert(Bucket {
+            tokens: self.config.burst as f64,
+            updated: now,
+        });
+        bucket.refill(now, &self.config);
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / self.config.rate))
+        }
+    }
+
+    /// 确定请求的客户端IP
+    /// 直连的对端是受信任的代理时,从右向左查找`X-Forwarded-For`中第一个不受信任的地址
+    pub fn client_ip(&self, request: &Request) -> Option<IpAddr> {
+        let peer = request.peer_addr()?.ip();
+        if !self.config.trusted_proxies.contains(&peer) {
+            return Some(peer);
+        }
+
+        let forwarded: Vec<IpAddr> = request
+            .headers()
+            .get_all("X-Forwarded-For")
+            .flat_map(|value| value.split(','))
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect();
+        let client = forwarded
+            .into_iter()
+            .rev()
+            .find(|ip| !self.config.trusted_proxies.contains(ip));
+        Some(client.unwrap_or(peer))
+    }
+
+    /// 当前正在跟踪的客户端数量
+    pub fn tracked_clients(&self) -> usize {
+        self.buckets.lock().unwrap().len()
+    }
+}
+
+impl Middleware for RateLimiter {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        let ip = match self.client_ip(request) {
+            Some(ip) => ip,
+            None => return next.run(request),
+        };
+
+        match self.check(ip) {
+            Ok(()) => next.run(request),
+            Err(wait) => {
+                // Retry-After只能是整数秒,向上取整以免客户端过早重试
+                let seconds = wait.as_secs() + u64::from(wait.subsec_nanos() > 0);
+                HttpError::new(429, "too many requests")
+                    .into_response()
+                    .with_header("Retry-After", seconds.max(1).to_string())
+            }
+        }
+    }
+}
+
+fn evict_loop(buckets: Weak<Mutex<HashMap<IpAddr, Bucket>>>, config: RateLimitConfig) {
+    let interval = (config.idle_timeout / 2).max(Duration::from_millis(100));
+    loop {
+        thread::sleep(interval);
+        match buckets.upgrade() {
+            Some(buckets) => evict(&mut buckets.lock().unwrap(), Instant::now(), &config),
+            None => break,
+        }
+    }
+}
+
+/// 清理闲置超时且令牌已经补满的令牌桶,被清理的客户端再次请求时会得到一个满的新桶,因此不影响限流结果
+fn evict(buckets: &mut HashMap<IpAddr, Bucket>, now: Instant, config: &RateLimitConfig) {
+    buckets.retain(|_, bucket| {
+        let idle = now.saturating_duration_since(bucket.updated) >= config.idle_timeout;
+        !(idle && bucket.tokens_at(now, config) >= config.burst as f64)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::Method;
+
+    #[test]
+    // 突发请求耗尽令牌后被拒绝,经过足够时间补充令牌后恢复
+    fn bucket_refills() {
+        let limiter = RateLimiter::new(RateLimitConfig::new(2.0, 3));
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at(ip, start).is_ok());
+        }
+        let wait = limiter.check_at(ip, start).unwrap_err();
+        assert_eq!(Duration::from_millis(500), wait);
+
+        assert!(limiter.check_at(ip, start + Duration::from_millis(500)).is_ok());
+        assert!(limiter.check_at(ip, start + Duration::from_millis(500)).is_err());
+        // 其他客户端不受影响
+        assert!(limiter.check_at("10.0.0.2".parse().unwrap(), start).is_ok());
+    }
+
+    #[test]
+    fn forwarded_for_only_from_trusted_proxy() {
+        let proxy: IpAddr = "127.0.0.1".parse().unwrap();
+        let limiter = RateLimiter::new(RateLimitConfig::new(1.0, 1).trust_proxy(proxy));
+        let request = |peer: &str| {
+            Request::new(Method::Get, "/")
+                .with_header("X-Forwarded-For", "203.0.113.9, 198.51.100.7, 127.0.0.1")
+                .with_peer_addr(peer.parse().unwrap())
+        };
+
+        assert_eq!(Some("198.51.100.7".parse().unwrap()), limiter.client_ip(&request("127.0.0.1:5000")));
+        assert_eq!(Some("192.0.2.1".parse().unwrap()), limiter.client_ip(&request("192.0.2.1:5000")));
+    }
+
+    #[test]
+    fn evicts_idle_full_buckets() {
+        let config = RateLimitConfig::new(1.0, 2).idle_timeout(Duration::from_secs(10));
+        let start = Instant::now();
+        let mut buckets = HashMap::new();
+        buckets.insert("10.0.0.1".parse().unwrap(), Bucket { tokens: 0.0, updated: start });
+
+        evict(&mut buckets, start + Duration::from_secs(1), &config);
+        evict(&mut buckets, start + Duration::from_secs(6), &config);
+        assert_eq!(1, buckets.len());
+        evict(&mut buckets, start + Duration::from_secs(10), &config);
+        assert!(buckets.is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 pub mod thread_pool;
 pub use thread_pool::ThreadPool;
 
+pub mod spawner;
+pub use spawner::Spawner;
+
 mod worker;
 use worker::Worker;
 
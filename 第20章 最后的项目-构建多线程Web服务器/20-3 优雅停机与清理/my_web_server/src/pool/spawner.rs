@@ -0,0 +1,20 @@
+use std::sync::mpsc;
+use crate::pool::Message;
+
+/// 向线程池提交任务的句柄,可以克隆并转移到其他线程
+/// 例如HTTP/2连接在自己的线程中读取帧,把每个流的请求作为任务交给线程池处理
+#[derive(Clone)]
+pub struct Spawner {
+    pub(crate) sender: mpsc::Sender<Message>
+}
+
+impl Spawner {
+    /// 提交任务,线程池已经停止时返回`false`
+    /// 线程池停止之前已经提交但还没有执行的任务会被直接丢弃
+    pub fn execute<F>(&self, f: F) -> bool
+    where
+        F: FnOnce() + Send + 'static
+    {
+        self.sender.send(Message::NewJob(Box::new(f))).is_ok()
+    }
+}
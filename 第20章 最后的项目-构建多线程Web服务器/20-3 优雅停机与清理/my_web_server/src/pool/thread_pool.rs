@@ -1,6 +1,7 @@
 use std::sync::{Arc, mpsc, Mutex};
 use crate::pool::Message;
 use crate::pool::Worker;
+use crate::pool::Spawner;
 
 pub struct ThreadPool {
     workers: Vec<Worker>,
@@ -35,6 +36,13 @@ impl ThreadPool {
         let job = Box::new(f);
         self.sender.send(Message::NewJob(job)).unwrap()
     }
+
+    /// 取得一个可以在其他线程中提交任务的句柄
+    pub fn spawner(&self) -> Spawner {
+        Spawner {
+            sender: self.sender.clone()
+        }
+    }
 }
 
 impl Drop for ThreadPool {
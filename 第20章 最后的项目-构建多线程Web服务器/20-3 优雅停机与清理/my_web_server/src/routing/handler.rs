@@ -0,0 +1,15 @@
+use std::sync::Arc;
+use crate::http::{IntoResponse, Request, Response};
+
+/// 请求处理函数
+/// 使用`Arc`是因为同一个处理函数会被线程池中的多个worker同时调用
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// 将返回值实现了`IntoResponse`的闭包包装为`Handler`
+pub fn handler<F, R>(f: F) -> Handler
+where
+    F: Fn(&Request) -> R + Send + Sync + 'static,
+    R: IntoResponse,
+{
+    Arc::new(move |request| f(request).into_response())
+}
@@ -0,0 +1,8 @@
+pub mod router;
+pub use router::Router;
+
+pub mod handler;
+pub use handler::{handler, Handler};
+
+mod route;
+use route::Route;
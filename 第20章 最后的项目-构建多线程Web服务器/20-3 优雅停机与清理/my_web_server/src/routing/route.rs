@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::http::Method;
+use crate::middleware::Middleware;
+use crate::routing::Handler;
+
+#[derive(Clone)]
+pub(crate) struct Route {
+    pub(crate) method: Method,
+    segments: Vec<Segment>,
+    pub(crate) handler: Handler,
+    /// 只作用于该路由的中间件,见`Router::route_layer()`
+    pub(crate) middleware: Vec<Arc<dyn Middleware>>,
+}
+
+#[derive(Clone)]
+enum Segment {
+    /// 必须完全相同的路径段
+    Static(String),
+    /// `:name`,匹配任意一个路径段
+    Param(String),
+    /// `*name`,匹配剩余的所有路径段,只能出现在模式的末尾
+    Rest(String),
+}
+
+impl Route {
+    /// 解析路由模式,例如`/api/todos/:id`、`/static/*path`
+    /// # Panics
+    /// `*name`不在模式末尾时会触发panic
+    pub(crate) fn new(method: Method, pattern: &str, handler: Handler) -> Route {
+        let parts: Vec<&str> = split_path(pattern).collect();
+        let segments = parts
+            .iter()
+            .enumerate()
+            .map(|(i, part)| {
+                if let Some(name) = part.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = part.strip_prefix('*') {
+                    assert!(i + 1 == parts.len(), "`*{}` must be the last segment of `{}`", name, pattern);
+                    Segment::Rest(name.to_string())
+                } else {
+                    Segment::Static(part.to_string())
+                }
+            })
+            .collect();
+        Route { method, segments, handler, middleware: Vec::new() }
+    }
+
+    /// 路径与模式匹配时返回提取出的路径参数
+    pub(crate) fn matches(&self, path: &str) -> Option<HashMap<String, String>> {
+        let parts: Vec<&str> = split_path(path).collect();
+        let mut params = HashMap::new();
+
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Static(expected) => {
+                    if parts.get(i) != Some(&expected.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.insert(name.clone(), parts.get(i)?.to_string());
+                }
+                Segment::Rest(name) => {
+                    params.insert(name.clone(), parts[i.min(parts.len())..].join("/"));
+                    return Some(params);
+                }
+            }
+        }
+
+        if parts.len() == self.segments.len() {
+            Some(params)
+        } else {
+            None
+        }
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|part| !part.is_empty())
+}
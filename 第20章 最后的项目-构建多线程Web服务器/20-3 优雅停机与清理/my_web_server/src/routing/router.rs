@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use crate::http::{IntoResponse, Method, Request, Response};
+use crate::middleware::{Middleware, Next};
+use crate::routing::{handler, Handler, Route};
+
+/// 根据请求方法和路径将请求分发给对应的处理函数
+/// 路由按注册顺序匹配,先注册的优先
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    fallback: Option<Handler>,
+    middleware: Vec<Arc<dyn Middleware>>,
+}
+
+impl Router {
+    pub fn new() -> Router {
+        Router::default()
+    }
+
+    /// 注册一个路由
+    /// 模式中的`:name`匹配一个路径段,`*name`匹配剩余的所有路径段,
+    /// 处理函数中可以通过`Request::param()`取得匹配到的值
+    pub fn route<F, R>(mut self, method: Method, pattern: &str, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.routes.push(Route::new(method, pattern, handler(f)));
+        self
+    }
+
+    /// 注册GET路由,HEAD请求也会由该路由处理
+    pub fn get<F, R>(self, pattern: &str, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::Get, pattern, f)
+    }
+
+    pub fn post<F, R>(self, pattern: &str, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::Post, pattern, f)
+    }
+
+    pub fn put<F, R>(self, pattern: &str, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::Put, pattern, f)
+    }
+
+    pub fn patch<F, R>(self, pattern: &str, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::Patch, pattern, f)
+    }
+
+    pub fn delete<F, R>(self, pattern: &str, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.route(Method::Delete, pattern, f)
+    }
+
+    /// 设置没有路由匹配时使用的处理函数,默认返回纯文本的404
+    pub fn fallback<F, R>(mut self, f: F) -> Router
+    where
+        F: Fn(&Request) -> R + Send + Sync + 'static,
+        R: IntoResponse,
+    {
+        self.fallback = Some(handler(f));
+        self
+    }
+
+    /// 添加一个作用于所有路由(包括fallback)的中间件
+    /// 先添加的中间件位于外层,会先于后添加的中间件看到请求
+    pub fn layer<M: Middleware + 'static>(mut self, middleware: M) -> Router {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// 给目前已经注册的路由添加中间件,之后注册的路由不受影响
+    /// 与`layer()`不同,该中间件只在路由匹配之后才运行,因此不存在的路径依然返回404而不会被中间件拦截.
+    /// 常用于只保护部分路由,例如把需要登录的路由注册在`route_layer(auth)`之前
+    pub fn route_layer<M: Middleware + 'static>(mut self, middleware: M) -> Router {
+        let middleware: Arc<dyn Middleware> = Arc::new(middleware);
+        for route in &mut self.routes {
+            route.middleware.push(middleware.clone());
+        }
+        self
+    }
+
+    /// 处理请求,请求依次经过各个中间件后再分发给路由
+    pub fn handle(&self, request: &mut Request) -> Response {
+        Next::new(&self.middleware, &|request| self.dispatch(request)).run(request)
+    }
+
+    /// 路径匹配但方法不匹配时返回405,并在`Allow`头部中列出允许的方法
+    fn dispatch(&self, request: &mut Request) -> Response {
+        let mut allowed: Vec<&str> = Vec::new();
+
+        for route in &self.routes {
+            if let Some(params) = route.matches(request.path()) {
+                let head_as_get = route.method == Method::Get && *request.method() == Method::Head;
+                if route.method == *request.method() || head_as_get {
+                    request.set_params(params);
+                    return Next::new(&route.middleware, &|request| (route.handler)(request)).run(request);
+                }
+                allowed.push(route.method.as_str());
+            }
+        }
+
+        if !allowed.is_empty() {
+            allowed.dedup();
+            return Response::text(405, "Method Not Allowed").with_header("Allow", allowed.join(", "));
+        }
+
+        match &self.fallback {
+            Some(fallback) => fallback(request),
+            None => Response::text(404, "Not Found"),
+        }
+    }
+}
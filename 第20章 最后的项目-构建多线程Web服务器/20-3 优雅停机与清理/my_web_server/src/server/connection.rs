@@ -0,0 +1,147 @@
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use crate::http::{HttpError, IntoResponse, Method, Request};
+use crate::http2::{self, ErrorCode, FrameType};
+use crate::pool::Spawner;
+use crate::routing::Router;
+use crate::server::Limits;
+
+/// 请求body默认的最大字节数
+pub const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+/// 读取请求默认的超时时间
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// 整个进程同时处理的HTTP/2连接的最大数量
+/// 每个HTTP/2连接占用一个线程池之外的线程读取帧,超出时回复GOAWAY,客户端可以稍后重试
+pub const MAX_HTTP2_CONNECTIONS: usize = 256;
+
+static HTTP2_CONNECTIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// 占用一个HTTP/2连接名额,被丢弃时归还
+struct Http2Slot;
+
+impl Http2Slot {
+    fn acquire() -> Option<Http2Slot> {
+        HTTP2_CONNECTIONS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| (count < MAX_HTTP2_CONNECTIONS).then_some(count + 1))
+            .ok()
+            .map(|_| Http2Slot)
+    }
+}
+
+impl Drop for Http2Slot {
+    fn drop(&mut self) {
+        HTTP2_CONNECTIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 本函数用于从TCP连接中读取一个请求,交给路由处理后将响应写回连接
+/// `&TcpStream`同样实现了`Read`和`Write`,因此读写可以共用同一个连接而不需要`mut stream`
+pub fn handle_connection(stream: TcpStream, router: &Router) {
+    handle_connection_with(stream, router, &Limits::default())
+}
+
+/// 与`handle_connection()`相同,但使用指定的资源限制
+pub fn handle_connection_with(stream: TcpStream, router: &Router, limits: &Limits) {
+    serve(stream, router, limits, None)
+}
+
+/// 与`handle_connection_with()`相同,但收到HTTP/2的连接前言(h2c先验知识)时改为按HTTP/2处理:
+/// 连接交给一个新线程读取帧,各个流上的请求作为任务通过`spawner`交回线程池.
+/// 这样的线程最多有`MAX_HTTP2_CONNECTIONS`个
+pub(crate) fn handle_connection_h2c(stream: TcpStream, router: Arc<Router>, limits: Limits, spawner: Spawner) {
+    let (http1_router, http1_limits) = (router.clone(), limits.clone());
+    let upgrade = move |stream: TcpStream, buffered: Vec<u8>| {
+        let slot = match Http2Slot::acquire() {
+            Some(slot) => slot,
+            None => {
+                refuse_http2(&stream);
+                return;
+            }
+        };
+        let spawned = thread::Builder::new().name("http2-connection".to_string()).spawn(move || {
+            let _slot = slot;
+            http2::connection::serve(stream, buffered, router, limits, spawner)
+        });
+        if let Err(e) = spawned {
+            eprintln!("failed to spawn HTTP/2 connection thread: {}", e);
+        }
+    };
+    serve(stream, &http1_router, &http1_limits, Some(Box::new(upgrade)))
+}
+
+/// 服务器的连接前言必须是SETTINGS帧,随后的GOAWAY表示没有处理任何流,客户端可以换一个连接重试
+fn refuse_http2(mut stream: &TcpStream) {
+    let mut payload = 0u32.to_be_bytes().to_vec();
+    payload.extend_from_slice(&u32::from(ErrorCode::NoError).to_be_bytes());
+    payload.extend_from_slice(b"too many HTTP/2 connections");
+    let _ = http2::frame::write_frame(&mut stream, FrameType::Settings, 0, 0, &[])
+        .and_then(|_| http2::frame::write_frame(&mut stream, FrameType::GoAway, 0, 0, &payload));
+}
+
+/// 把连接从HTTP/1.1切换到HTTP/2,参数是连接和读取前言时已经读入缓冲区的字节
+type Upgrade = Box<dyn FnOnce(TcpStream, Vec<u8>)>;
+
+fn serve(stream: TcpStream, router: &Router, limits: &Limits, http2: Option<Upgrade>) {
+    let _ = stream.set_read_timeout(Some(limits.read_timeout));
+    let mut reader = BufReader::new(&stream);
+    let mut writer = &stream;
+
+    let head = Request::read_head(&mut reader);
+    if let (Ok(request), Some(upgrade)) = (&head, http2) {
+        if http2::is_preface(request) {
+            let mut rest = [0; 6];
+            if reader.read_exact(&mut rest).is_ok() && rest == http2::PREFACE[18..] {
+                let buffered = reader.buffer().to_vec();
+                drop(reader);
+                upgrade(stream, buffered);
+            }
+            return;
+        }
+    }
+
+    let result = head.and_then(|request| read_rest(request, &mut reader, &mut writer, limits));
+    let (mut response, include_body) = match result {
+        Ok(mut request) => {
+            if let Ok(addr) = stream.peer_addr() {
+                request = request.with_peer_addr(addr);
+            }
+            (router.handle(&mut request), *request.method() != Method::Head)
+        }
+        Err(error) => (error.into_response(), true),
+    };
+
+    if let Err(e) = response.write_to(&mut writer, include_body) {
+        eprintln!("failed to write response: {}", e);
+        return;
+    }
+    drop(reader);
+    if include_body {
+        // 长连接响应(例如SSE)在这里接管连接,worker随即可以处理其他连接
+        response.take_over(stream);
+    }
+}
+
+/// 读取头部之后的部分,客户端发送了`Expect: 100-continue`时先回复`100 Continue`再读取body.
+/// `multipart/form-data`的body不读入内存,而是直接从连接中解析
+fn read_rest<R, W>(mut request: Request, reader: &mut BufReader<R>, writer: &mut W, limits: &Limits) -> Result<Request, HttpError>
+where
+    R: std::io::Read,
+    W: Write,
+{
+    let expect_continue = request
+        .header("Expect")
+        .is_some_and(|expect| expect.eq_ignore_ascii_case("100-continue"));
+    if expect_continue {
+        writer
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .map_err(|e| HttpError::bad_request(format!("failed to write 100 Continue: {}", e)))?;
+    }
+    if !request.read_multipart(reader, &limits.multipart)? {
+        request.read_body(reader, limits.max_body_size)?;
+    }
+    Ok(request)
+}
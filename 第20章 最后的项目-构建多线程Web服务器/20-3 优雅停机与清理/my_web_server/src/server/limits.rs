@@ -0,0 +1,21 @@
+use std::time::Duration;
+use crate::form::MultipartLimits;
+use crate::server::connection::{MAX_BODY_SIZE, READ_TIMEOUT};
+
+/// 每个连接的资源限制
+#[derive(Debug, Clone, PartialEq)]
+pub struct Limits {
+    /// 请求body的最大字节数,超出时返回413
+    pub max_body_size: usize,
+    /// 读取请求的超时时间,防止迟迟不发送数据的客户端一直占用worker
+    pub read_timeout: Duration,
+    /// 带`Content-Length`的`multipart/form-data`请求不先读入内存,而是在交给路由之前按这些限制边读边解析,
+    /// 较大的part直接写入临时文件,见`Request::multipart_with()`
+    pub multipart: MultipartLimits,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits { max_body_size: MAX_BODY_SIZE, read_timeout: READ_TIMEOUT, multipart: MultipartLimits::default() }
+    }
+}
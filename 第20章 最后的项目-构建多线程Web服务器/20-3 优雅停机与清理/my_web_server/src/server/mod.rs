@@ -0,0 +1,8 @@
+pub mod web_server;
+pub use web_server::{ReloadHandle, Server, ShutdownHandle};
+
+pub mod connection;
+pub use connection::{handle_connection, handle_connection_with};
+
+pub mod limits;
+pub use limits::Limits;
@@ -0,0 +1,130 @@
+use std::io;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use crate::pool::ThreadPool;
+use crate::routing::Router;
+use crate::server::connection::handle_connection_h2c;
+use crate::server::{handle_connection_with, Limits};
+
+/// 基于线程池的HTTP服务器
+/// 监听线程负责接受连接,每个连接作为一个任务交给线程池处理
+pub struct Server {
+    listener: TcpListener,
+    site: Arc<RwLock<Arc<Site>>>,
+    workers: usize,
+    http2: bool,
+    shutdown: Arc<AtomicBool>,
+}
+
+/// 可以在运行时替换的部分
+struct Site {
+    router: Arc<Router>,
+    limits: Limits,
+}
+
+/// 用于在服务器运行时替换路由和资源限制,监听的端口和线程池保持不变
+/// 替换之后新接受的连接使用新的路由,正在处理的连接继续使用旧的路由直到完成
+#[derive(Clone)]
+pub struct ReloadHandle {
+    site: Arc<RwLock<Arc<Site>>>,
+}
+
+impl ReloadHandle {
+    pub fn reload(&self, router: Router, limits: Limits) {
+        *self.site.write().unwrap() = Arc::new(Site { router: Arc::new(router), limits });
+    }
+}
+
+/// 用于从其他线程停止`Server::run()`
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    addr: SocketAddr,
+    shutdown: Arc<AtomicBool>,
+}
+
+impl ShutdownHandle {
+    /// 通知服务器停止接受新连接
+    /// 监听线程阻塞在`accept()`上,因此需要主动连接一次服务器把它唤醒
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+impl Server {
+    /// 绑定地址并创建服务器,默认使用4个worker
+    pub fn bind<A: ToSocketAddrs>(addr: A, router: Router) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+            site: Arc::new(RwLock::new(Arc::new(Site { router: Arc::new(router), limits: Limits::default() }))),
+            workers: 4,
+            http2: false,
+            shutdown: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// 设置线程池中worker的数量
+    pub fn workers(mut self, size: usize) -> Server {
+        self.workers = size;
+        self
+    }
+
+    /// 设置资源限制,默认为`Limits::default()`
+    pub fn limits(self, limits: Limits) -> Server {
+        let router = self.site.read().unwrap().router.clone();
+        *self.site.write().unwrap() = Arc::new(Site { router, limits });
+        self
+    }
+
+    /// 是否接受明文HTTP/2连接(h2c先验知识,即客户端直接发送HTTP/2连接前言),默认只使用HTTP/1.1
+    /// 启用后HTTP/1.1请求照常处理,不支持通过`Upgrade: h2c`升级
+    pub fn http2(mut self, enabled: bool) -> Server {
+        self.http2 = enabled;
+        self
+    }
+
+    pub fn reload_handle(&self) -> ReloadHandle {
+        ReloadHandle { site: self.site.clone() }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn shutdown_handle(&self) -> io::Result<ShutdownHandle> {
+        Ok(ShutdownHandle {
+            addr: self.local_addr()?,
+            shutdown: self.shutdown.clone(),
+        })
+    }
+
+    /// 开始接受连接,该方法会一直阻塞,直到通过`ShutdownHandle`停止服务器
+    /// 返回前会等待线程池中正在处理的请求全部完成
+    pub fn run(self) {
+        let pool = ThreadPool::new(self.workers);
+        for stream in self.listener.incoming() {
+            if self.shutdown.load(Ordering::SeqCst) {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+            let site = self.site.read().unwrap().clone();
+            if self.http2 {
+                let spawner = pool.spawner();
+                pool.execute(move || {
+                    handle_connection_h2c(stream, site.router.clone(), site.limits.clone(), spawner);
+                })
+            } else {
+                pool.execute(move || {
+                    handle_connection_with(stream, &site.router, &site.limits);
+                })
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::session::id::is_valid_id;
+use crate::session::{SessionData, SessionStore};
+
+/// 以文件保存会话,每个会话对应目录中的一个`<id>.json`文件,服务器重启后会话依然有效
+pub struct FileStore {
+    dir: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    /// 过期时间,UNIX时间戳(秒)
+    expires_at: u64,
+    data: SessionData,
+}
+
+impl FileStore {
+    /// 使用`dir`目录保存会话,目录不存在时会被创建
+    pub fn new<P: AsRef<Path>>(dir: P) -> io::Result<FileStore> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(FileStore { dir: dir.as_ref().to_path_buf() })
+    }
+
+    /// id不合法时返回`None`,从而不会访问目录之外的文件
+    fn path_of(&self, id: &str) -> Option<PathBuf> {
+        if is_valid_id(id) {
+            Some(self.dir.join(format!("{}.json", id)))
+        } else {
+            None
+        }
+    }
+
+    fn read_record(path: &Path) -> io::Result<Option<Record>> {
+        match fs::read(path) {
+            // 内容损坏的文件视为不存在
+            Ok(bytes) => Ok(serde_json::from_slice(&bytes).ok()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn remove_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl SessionStore for FileStore {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        let path = match self.path_of(id) {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        match FileStore::read_record(&path)? {
+            Some(record) if record.expires_at > now_secs() => Ok(Some(record.data)),
+            Some(_) => {
+                remove_if_exists(&path)?;
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// 先写入临时文件再重命名,保证并发读取时不会读到写了一半的文件
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        let path = self
+            .path_of(id)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid session id"))?;
+        let record = Record { expires_at: now_secs() + ttl.as_secs(), data: data.clone() };
+        let bytes = serde_json::to_vec(&record).map_err(io::Error::other)?;
+
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, bytes)?;
+        fs::rename(&temp, &path)
+    }
+
+    fn delete(&self, id: &str) -> io::Result<()> {
+        match self.path_of(id) {
+            Some(path) => remove_if_exists(&path),
+            None => Ok(()),
+        }
+    }
+
+    fn purge_expired(&self) -> io::Result<usize> {
+        let now = now_secs();
+        let mut purged = 0;
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let expired = match FileStore::read_record(&path)? {
+                Some(record) => record.expires_at <= now,
+                None => true,
+            };
+            if expired {
+                remove_if_exists(&path)?;
+                purged += 1;
+            }
+        }
+        Ok(purged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use crate::session::generate_id;
+
+    #[test]
+    // 会话写入文件后可以读回,过期的会话被清理,不合法的id被忽略
+    fn save_load_and_purge() {
+        let dir = env::temp_dir().join(format!("sessions-{}", generate_id()));
+        let store = FileStore::new(&dir).unwrap();
+        let mut data = SessionData::new();
+        data.insert("user".to_string(), "ferris".into());
+
+        let live = generate_id();
+        let expired = generate_id();
+        store.save(&live, &data, Duration::from_secs(60)).unwrap();
+        store.save(&expired, &data, Duration::ZERO).unwrap();
+        assert_eq!(Some(data), store.load(&live).unwrap());
+        assert_eq!(1, store.purge_expired().unwrap());
+        assert_eq!(None, store.load(&expired).unwrap());
+
+        assert_eq!(None, store.load("../secret").unwrap());
+        assert!(store.save("../secret", &SessionData::new(), Duration::from_secs(60)).is_err());
+
+        store.delete(&live).unwrap();
+        assert_eq!(None, store.load(&live).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
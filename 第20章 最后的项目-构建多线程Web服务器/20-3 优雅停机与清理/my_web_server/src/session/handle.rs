@@ -0,0 +1,96 @@
+use std::sync::{Arc, Mutex};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use crate::session::SessionData;
+
+/// 当前请求的会话
+/// 处理函数只能拿到`&Request`,因此会话内部使用`Mutex`实现可变性;
+/// 请求处理完后由`SessionLayer`根据会话的状态决定是否保存、删除或重新生成id
+#[derive(Debug, Clone)]
+pub struct Session {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    id: Option<String>,
+    data: SessionData,
+    modified: bool,
+    destroyed: bool,
+    regenerate: bool,
+}
+
+/// 请求处理完后会话需要进行的操作
+pub(crate) enum Outcome {
+    Unchanged,
+    Save { old_id: Option<String>, regenerate: bool, data: SessionData },
+    Destroy { old_id: Option<String> },
+}
+
+impl Session {
+    pub(crate) fn new(id: Option<String>, data: SessionData) -> Session {
+        Session {
+            state: Arc::new(Mutex::new(State {
+                id,
+                data,
+                modified: false,
+                destroyed: false,
+                regenerate: false,
+            })),
+        }
+    }
+
+    /// 会话id,新会话在第一次保存之前没有id
+    pub fn id(&self) -> Option<String> {
+        self.state.lock().unwrap().id.clone()
+    }
+
+    /// 取出`key`对应的值并反序列化为`T`,不存在或类型不符时返回`None`
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let value = self.state.lock().unwrap().data.get(key)?.clone();
+        serde_json::from_value(value).ok()
+    }
+
+    pub fn insert<V: Into<Value>>(&self, key: &str, value: V) {
+        let mut state = self.state.lock().unwrap();
+        state.data.insert(key.to_string(), value.into());
+        state.modified = true;
+        state.destroyed = false;
+    }
+
+    pub fn remove(&self, key: &str) -> Option<Value> {
+        let mut state = self.state.lock().unwrap();
+        let removed = state.data.remove(key);
+        state.modified |= removed.is_some();
+        removed
+    }
+
+    /// 清空会话并让客户端删除会话Cookie,通常用于退出登录
+    pub fn destroy(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.data.clear();
+        state.destroyed = true;
+    }
+
+    /// 保留数据但更换会话id,登录成功后调用以防止会话固定攻击
+    pub fn regenerate(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.regenerate = true;
+        state.modified = true;
+    }
+
+    pub(crate) fn outcome(&self) -> Outcome {
+        let state = self.state.lock().unwrap();
+        if state.destroyed {
+            Outcome::Destroy { old_id: state.id.clone() }
+        } else if state.modified && (state.id.is_some() || !state.data.is_empty()) {
+            Outcome::Save {
+                old_id: state.id.clone(),
+                regenerate: state.regenerate,
+                data: state.data.clone(),
+            }
+        } else {
+            Outcome::Unchanged
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 生成64个十六进制字符(256位)的会话id
+/// 优先使用操作系统的随机数源,无法读取时退化为标准库`RandomState`的随机种子
+pub(crate) fn generate_id() -> String {
+    let mut bytes = [0u8; 32];
+    let from_os = File::open("/dev/urandom").and_then(|mut file| file.read_exact(&mut bytes));
+    if from_os.is_err() {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or(0);
+        for chunk in bytes.chunks_mut(8) {
+            // 每个RandomState都带有不同的随机密钥
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u128(nanos);
+            chunk.copy_from_slice(&hasher.finish().to_le_bytes());
+        }
+    }
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// 会话id只能由`generate_id()`生成的字符组成,用来拒绝伪造的Cookie(例如包含`../`的路径)
+pub(crate) fn is_valid_id(id: &str) -> bool {
+    id.len() == 64 && id.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
@@ -0,0 +1,141 @@
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::Duration;
+use crate::http::{Cookie, Request, Response, SameSite};
+use crate::middleware::{Middleware, Next};
+use crate::session::handle::Outcome;
+use crate::session::{generate_id, Session, SessionData, SessionStore};
+
+/// 会话中间件
+/// 根据会话Cookie从存储中加载会话,放入请求的`extensions`中供处理函数使用(见`Request::session()`),
+/// 请求处理完后保存修改过的会话并设置Cookie.会话只在被修改时保存,因此过期时间从最后一次修改开始计算
+pub struct SessionLayer {
+    store: Arc<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+    same_site: SameSite,
+}
+
+impl SessionLayer {
+    /// 使用`store`保存会话,同时启动一个定期清理过期会话的后台线程
+    /// 后台线程只持有存储的弱引用,中间件被丢弃后线程会自行退出
+    pub fn new<S: SessionStore + 'static>(store: S) -> SessionLayer {
+        let store: Arc<dyn SessionStore> = Arc::new(store);
+        let weak = Arc::downgrade(&store);
+        thread::Builder::new()
+            .name("session-purger".to_string())
+            .spawn(move || purge_loop(weak))
+            .expect("failed to spawn session purger");
+
+        SessionLayer {
+            store,
+            cookie_name: "sid".to_string(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+            secure: false,
+            same_site: SameSite::Lax,
+        }
+    }
+
+    /// 会话Cookie的名称,默认为`sid`
+    pub fn cookie_name<N: Into<String>>(mut self, name: N) -> SessionLayer {
+        self.cookie_name = name.into();
+        self
+    }
+
+    /// 会话的有效期,同时也是Cookie的`Max-Age`,默认为1天
+    pub fn ttl(mut self, ttl: Duration) -> SessionLayer {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 是否给Cookie加上`Secure`属性,通过HTTPS提供服务时应当开启
+    pub fn secure(mut self, secure: bool) -> SessionLayer {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> SessionLayer {
+        self.same_site = same_site;
+        self
+    }
+
+    fn cookie(&self, id: &str) -> Cookie {
+        Cookie::new(self.cookie_name.as_str(), id)
+            .path("/")
+            .max_age(self.ttl)
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(self.same_site)
+    }
+
+    fn load(&self, id: &str) -> Option<SessionData> {
+        match self.store.load(id) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("failed to load session: {}", e);
+                None
+            }
+        }
+    }
+}
+
+impl Middleware for SessionLayer {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        let cookie_id = request.cookie(&self.cookie_name);
+        let session = match cookie_id.as_deref().and_then(|id| self.load(id).map(|data| (id, data))) {
+            Some((id, data)) => Session::new(Some(id.to_string()), data),
+            None => Session::new(None, SessionData::new()),
+        };
+        request.extensions_mut().insert(session.clone());
+
+        let mut response = next.run(request);
+
+        match session.outcome() {
+            Outcome::Unchanged => {
+                // Cookie指向的会话已经过期或不存在,让客户端删掉它
+                if cookie_id.is_some() && session.id().is_none() {
+                    response.set_cookie(Cookie::removal(self.cookie_name.as_str()).path("/"));
+                }
+            }
+            Outcome::Save { old_id, regenerate, data } => {
+                let id = match old_id {
+                    Some(old_id) if !regenerate => old_id,
+                    old_id => {
+                        if let Some(old_id) = old_id {
+                            let _ = self.store.delete(&old_id);
+                        }
+                        generate_id()
+                    }
+                };
+                match self.store.save(&id, &data, self.ttl) {
+                    Ok(()) => response.set_cookie(self.cookie(&id)),
+                    Err(e) => eprintln!("failed to save session: {}", e),
+                }
+            }
+            Outcome::Destroy { old_id } => {
+                if let Some(old_id) = old_id {
+                    let _ = self.store.delete(&old_id);
+                }
+                if cookie_id.is_some() {
+                    response.set_cookie(Cookie::removal(self.cookie_name.as_str()).path("/"));
+                }
+            }
+        }
+        response
+    }
+}
+
+fn purge_loop(store: Weak<dyn SessionStore>) {
+    loop {
+        thread::sleep(Duration::from_secs(60));
+        match store.upgrade() {
+            Some(store) => {
+                if let Err(e) = store.purge_expired() {
+                    eprintln!("failed to purge expired sessions: {}", e);
+                }
+            }
+            None => break,
+        }
+    }
+}
@@ -0,0 +1,17 @@
+pub mod handle;
+pub use handle::Session;
+
+pub mod store;
+pub use store::{SessionData, SessionStore};
+
+pub mod memory_store;
+pub use memory_store::MemoryStore;
+
+pub mod file_store;
+pub use file_store::FileStore;
+
+pub mod layer;
+pub use layer::SessionLayer;
+
+mod id;
+use id::generate_id;
@@ -0,0 +1,41 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 会话中保存的数据
+pub type SessionData = BTreeMap<String, serde_json::Value>;
+
+/// 会话的存储后端
+/// 实现需要是线程安全的,因为线程池中的多个worker会同时访问同一个存储
+pub trait SessionStore: Send + Sync {
+    /// 取得未过期的会话数据,会话不存在或已过期时返回`None`
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>>;
+
+    /// 保存会话数据,会话在`ttl`之后过期
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()>;
+
+    fn delete(&self, id: &str) -> io::Result<()>;
+
+    /// 清理所有已过期的会话,返回清理的数量
+    fn purge_expired(&self) -> io::Result<usize>;
+}
+
+/// 共享的存储,例如重新加载配置时新旧`SessionLayer`使用同一个存储以保留会话
+impl<S: SessionStore + ?Sized> SessionStore for Arc<S> {
+    fn load(&self, id: &str) -> io::Result<Option<SessionData>> {
+        (**self).load(id)
+    }
+
+    fn save(&self, id: &str, data: &SessionData, ttl: Duration) -> io::Result<()> {
+        (**self).save(id, data, ttl)
+    }
+
+    fn delete(&self, id: &str) -> io::Result<()> {
+        (**self).delete(id)
+    }
+
+    fn purge_expired(&self) -> io::Result<usize> {
+        (**self).purge_expired()
+    }
+}
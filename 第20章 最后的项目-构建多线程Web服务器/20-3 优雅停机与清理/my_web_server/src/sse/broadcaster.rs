@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Mutex;
+use crate::sse::Event;
+
+/// 每个订阅者最多积压的新事件数,超过时断开该订阅者
+pub const SUBSCRIBER_BACKLOG: usize = 64;
+
+/// 把事件广播给所有订阅者,并保留最近的事件供断线重连的客户端补发
+/// 没有id的事件会被分配一个递增的数字id.
+/// 每个订阅者的通道是有界的,接收得太慢、积压超过`SUBSCRIBER_BACKLOG`个事件的订阅者会被断开,
+/// 而不是让事件在内存中无限堆积;客户端重连时通过`Last-Event-ID`补发错过的事件
+pub struct Broadcaster {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    history: VecDeque<Event>,
+    capacity: usize,
+    next_id: u64,
+    subscribers: Vec<SyncSender<Event>>,
+}
+
+impl Broadcaster {
+    /// 最多保留`capacity`个最近的事件
+    pub fn new(capacity: usize) -> Broadcaster {
+        Broadcaster {
+            inner: Mutex::new(Inner {
+                history: VecDeque::with_capacity(capacity),
+                capacity,
+                next_id: 1,
+                subscribers: Vec::new(),
+            }),
+        }
+    }
+
+    /// 发送事件,已断开或者积压过多的订阅者会被移除
+    pub fn send(&self, event: Event) {
+        let mut inner = self.inner.lock().unwrap();
+        let event = match event.id() {
+            Some(_) => event,
+            None => {
+                let id = inner.next_id;
+                inner.next_id += 1;
+                event.with_id(id.to_string())
+            }
+        };
+        inner.subscribers.retain(|subscriber| match subscriber.try_send(event.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => false,
+        });
+        if inner.capacity > 0 {
+            if inner.history.len() == inner.capacity {
+                inner.history.pop_front();
+            }
+            inner.history.push_back(event);
+        }
+    }
+
+    /// 订阅之后发送的事件
+    /// `last_event_id`是客户端最后收到的事件id(来自`Last-Event-ID`头部),
+    /// 它之后的历史事件会先被补发;该id已经不在历史中时不补发
+    pub fn subscribe(&self, last_event_id: Option<&str>) -> Receiver<Event> {
+        let mut inner = self.inner.lock().unwrap();
+        // 补发的历史事件不超过`capacity`个,不会占用新事件的积压额度
+        let (sender, receiver) = mpsc::sync_channel(inner.capacity + SUBSCRIBER_BACKLOG);
+        if let Some(last_id) = last_event_id {
+            let position = inner.history.iter().position(|event| event.id() == Some(last_id.trim()));
+            if let Some(position) = position {
+                for event in inner.history.iter().skip(position + 1) {
+                    let _ = sender.send(event.clone());
+                }
+            }
+        }
+        inner.subscribers.push(sender);
+        receiver
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.inner.lock().unwrap().subscribers.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    // 重连的客户端只补发它没有收到的事件
+    fn replays_after_last_event_id() {
+        let broadcaster = Broadcaster::new(3);
+        for data in ["a", "b", "c", "d"] {
+            broadcaster.send(Event::new(data));
+        }
+
+        let resumed = broadcaster.subscribe(Some("2"));
+        let replayed: Vec<String> = resumed.try_iter().map(|event| event.data().to_string()).collect();
+        assert_eq!(vec!["c", "d"], replayed);
+
+        // id为1的事件已经不在历史中
+        assert!(broadcaster.subscribe(Some("1")).try_recv().is_err());
+
+        broadcaster.send(Event::new("e"));
+        assert_eq!(Some("5"), resumed.try_recv().unwrap().id());
+    }
+
+    #[test]
+    fn drops_closed_subscribers() {
+        let broadcaster = Broadcaster::new(0);
+        let kept = broadcaster.subscribe(None);
+        drop(broadcaster.subscribe(None));
+        broadcaster.send(Event::new("x"));
+        assert_eq!(1, broadcaster.subscriber_count());
+        assert_eq!("x", kept.recv().unwrap().data());
+    }
+
+    #[test]
+    // 不接收事件的订阅者积压满之后被断开,之前积压的事件仍然可以读出
+    fn drops_lagging_subscribers() {
+        let broadcaster = Broadcaster::new(0);
+        let lagging = broadcaster.subscribe(None);
+        for i in 0..SUBSCRIBER_BACKLOG {
+            broadcaster.send(Event::new(i.to_string()));
+        }
+        assert_eq!(1, broadcaster.subscriber_count());
+        broadcaster.send(Event::new("overflow"));
+        assert_eq!(0, broadcaster.subscriber_count());
+        assert_eq!(SUBSCRIBER_BACKLOG, lagging.iter().count());
+    }
+}
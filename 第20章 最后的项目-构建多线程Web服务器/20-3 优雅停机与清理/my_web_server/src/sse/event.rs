@@ -0,0 +1,88 @@
+use std::fmt;
+use std::time::Duration;
+
+/// 一个Server-Sent Event
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Event {
+    id: Option<String>,
+    event: Option<String>,
+    data: String,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    /// `data`可以包含多行,每行会作为一个`data:`字段发送
+    pub fn new<D: Into<String>>(data: D) -> Event {
+        Event { data: data.into(), ..Event::default() }
+    }
+
+    /// 事件id,客户端断线重连时通过`Last-Event-ID`头部带回最后收到的id
+    /// id中的换行符会被去掉
+    pub fn with_id<I: Into<String>>(mut self, id: I) -> Event {
+        self.id = Some(single_line(id.into()));
+        self
+    }
+
+    /// 事件类型,客户端用`addEventListener(type, ..)`接收,不设置时为`message`
+    pub fn with_event<E: Into<String>>(mut self, event: E) -> Event {
+        self.event = Some(single_line(event.into()));
+        self
+    }
+
+    /// 通知客户端断线后等待多久再重连
+    pub fn with_retry(mut self, retry: Duration) -> Event {
+        self.retry = Some(retry);
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    pub fn event(&self) -> Option<&str> {
+        self.event.as_deref()
+    }
+
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+fn single_line(s: String) -> String {
+    s.replace(['\r', '\n'], "")
+}
+
+/// 按`text/event-stream`格式输出,以空行结束
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(id) = &self.id {
+            writeln!(f, "id: {}", id)?;
+        }
+        if let Some(event) = &self.event {
+            writeln!(f, "event: {}", event)?;
+        }
+        if let Some(retry) = self.retry {
+            writeln!(f, "retry: {}", retry.as_millis())?;
+        }
+        // 规范中\r\n、\r和\n都是换行
+        for line in self.data.split("\r\n").flat_map(|line| line.split(['\r', '\n'])) {
+            writeln!(f, "data: {}", line)?;
+        }
+        writeln!(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_fields() {
+        let event = Event::new("first\nsecond")
+            .with_id("7\n")
+            .with_event("update")
+            .with_retry(Duration::from_secs(3));
+        assert_eq!("id: 7\nevent: update\nretry: 3000\ndata: first\ndata: second\n\n", event.to_string());
+        assert_eq!("data: \n\n", Event::new("").to_string());
+    }
+}
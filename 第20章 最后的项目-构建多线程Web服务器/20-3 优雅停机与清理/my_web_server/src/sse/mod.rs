@@ -0,0 +1,8 @@
+pub mod event;
+pub use event::Event;
+
+pub mod stream;
+pub use stream::Sse;
+
+pub mod broadcaster;
+pub use broadcaster::Broadcaster;
@@ -0,0 +1,111 @@
+use std::io::{self, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use crate::http::{HttpError, IntoResponse, Response};
+use crate::sse::Event;
+
+/// 发送事件时的写超时,客户端长时间不读取时断开它,而不是让发送线程一直阻塞
+const WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+/// 发送线程只做少量的格式化和写入,不需要默认大小的栈
+const STACK_SIZE: usize = 64 * 1024;
+/// 同时存在的事件流的最大数量,每个事件流占用一个发送线程,达到上限后新的请求得到`503 Service Unavailable`
+pub const MAX_STREAMS: usize = 256;
+
+static STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+/// 占用一个事件流名额,发送线程结束时归还
+struct StreamSlot;
+
+impl StreamSlot {
+    fn acquire() -> Option<StreamSlot> {
+        STREAMS
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| (count < MAX_STREAMS).then_some(count + 1))
+            .ok()
+            .map(|_| StreamSlot)
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Server-Sent Events响应,把从通道中收到的事件推送给客户端
+/// 写完响应头部后连接交给一个独立的发送线程,不会一直占用线程池中的worker,这样的线程最多有`MAX_STREAMS`个.
+/// 通道的发送端全部被丢弃或客户端断开时连接结束;没有事件时定期发送注释行作为心跳,
+/// 既防止代理因为空闲而断开连接,也能及时发现已经断开的客户端
+pub struct Sse {
+    receiver: Receiver<Event>,
+    heartbeat: Duration,
+    retry: Option<Duration>,
+}
+
+impl Sse {
+    pub fn new(receiver: Receiver<Event>) -> Sse {
+        Sse { receiver, heartbeat: Duration::from_secs(15), retry: None }
+    }
+
+    /// 心跳间隔,默认为15秒
+    pub fn heartbeat(mut self, interval: Duration) -> Sse {
+        self.heartbeat = interval;
+        self
+    }
+
+    /// 连接建立时通知客户端断线后的重连间隔
+    pub fn retry(mut self, retry: Duration) -> Sse {
+        self.retry = Some(retry);
+        self
+    }
+}
+
+impl IntoResponse for Sse {
+    fn into_response(self) -> Response {
+        let Some(slot) = StreamSlot::acquire() else {
+            return HttpError::new(503, "too many event streams").into_response().with_header("Retry-After", "5");
+        };
+        Response::ok()
+            .with_header("Content-Type", "text/event-stream")
+            .with_header("Cache-Control", "no-cache")
+            // 防止nginx等反向代理缓冲事件
+            .with_header("X-Accel-Buffering", "no")
+            .with_takeover(move |stream| {
+                let spawned = thread::Builder::new()
+                    .name("sse".to_string())
+                    .stack_size(STACK_SIZE)
+                    .spawn(move || {
+                        let _slot = slot;
+                        if let Err(e) = self.pump(stream) {
+                            // 客户端断开是正常情况,不需要记录
+                            if !matches!(e.kind(), io::ErrorKind::BrokenPipe | io::ErrorKind::ConnectionReset) {
+                                eprintln!("SSE stream ended: {}", e);
+                            }
+                        }
+                    });
+                if let Err(e) = spawned {
+                    eprintln!("failed to spawn SSE thread: {}", e);
+                }
+            })
+    }
+}
+
+impl Sse {
+    fn pump(self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+        if let Some(retry) = self.retry {
+            write!(stream, "retry: {}\n\n", retry.as_millis())?;
+        }
+        stream.flush()?;
+        loop {
+            match self.receiver.recv_timeout(self.heartbeat) {
+                Ok(event) => write!(stream, "{}", event)?,
+                Err(RecvTimeoutError::Timeout) => stream.write_all(b": heartbeat\n\n")?,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+            stream.flush()?;
+        }
+    }
+}
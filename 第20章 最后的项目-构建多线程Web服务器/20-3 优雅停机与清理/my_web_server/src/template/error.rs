@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// 加载或渲染模板时可能出现的错误
+#[derive(Debug)]
+pub enum TemplateError {
+    /// 模板文件无法读取
+    Io { path: PathBuf, source: io::Error },
+    /// 模板语法错误,`line`从1开始计数
+    Syntax { name: String, line: usize, message: String },
+    /// 模板名不合法(例如包含`..`)
+    InvalidName(String),
+    /// `include`或`extends`嵌套过深,通常是模板互相引用导致的
+    RecursionLimit(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::Io { path, source } => {
+                write!(f, "failed to read template {}: {}", path.display(), source)
+            }
+            TemplateError::Syntax { name, line, message } => {
+                write!(f, "syntax error in template {} at line {}: {}", name, line, message)
+            }
+            TemplateError::InvalidName(name) => write!(f, "invalid template name: {}", name),
+            TemplateError::RecursionLimit(name) => {
+                write!(f, "template nesting too deep while rendering {}", name)
+            }
+        }
+    }
+}
+
+impl Error for TemplateError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TemplateError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
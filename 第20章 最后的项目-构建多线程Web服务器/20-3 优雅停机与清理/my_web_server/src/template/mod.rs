@@ -0,0 +1,13 @@
+pub mod templates;
+pub use templates::Templates;
+
+pub mod value;
+pub use value::{Context, Value};
+
+pub mod error;
+pub use error::TemplateError;
+
+mod render;
+pub use render::escape_html;
+
+mod parser;
@@ -0,0 +1,248 @@
+use crate::template::TemplateError;
+
+/// 解析后的模板
+/// `parent`为`{% extends %}`指定的父模板,有父模板时只有`block`中的内容会被渲染
+#[derive(Debug)]
+pub(crate) struct Template {
+    pub(crate) parent: Option<String>,
+    pub(crate) nodes: Vec<Node>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Node {
+    Text(String),
+    /// `{{ path }}`,`safe`为真时不做HTML转义
+    Var { path: Vec<String>, safe: bool },
+    /// `{% if %}`/`{% elif %}`/`{% else %}`
+    If { branches: Vec<(Condition, Vec<Node>)>, otherwise: Vec<Node> },
+    /// `{% for var in path %}`,`otherwise`为`{% else %}`分支,在迭代对象为空时渲染
+    For { var: String, path: Vec<String>, body: Vec<Node>, otherwise: Vec<Node> },
+    Include(String),
+    Block { name: String, body: Vec<Node> },
+}
+
+#[derive(Debug)]
+pub(crate) struct Condition {
+    pub(crate) negate: bool,
+    pub(crate) path: Vec<String>,
+}
+
+/// 结束标签的内容及其所在行
+type EndTag = (String, usize);
+
+enum Token {
+    Text(String),
+    Var(String, usize),
+    Tag(String, usize),
+}
+
+/// 将模板源码解析为`Template`
+/// `name`仅用于在错误信息中标识模板
+pub(crate) fn parse(name: &str, source: &str) -> Result<Template, TemplateError> {
+    let tokens = tokenize(name, source)?;
+    let mut parser = Parser { name, tokens: tokens.into_iter(), parent: None, line: 1 };
+    let (nodes, end) = parser.parse_until(&[])?;
+    debug_assert!(end.is_none());
+    Ok(Template { parent: parser.parent, nodes })
+}
+
+fn syntax_error(name: &str, line: usize, message: impl Into<String>) -> TemplateError {
+    TemplateError::Syntax { name: name.to_string(), line, message: message.into() }
+}
+
+/// 将源码切分为文本、`{{ }}`和`{% %}`三类记号,`{# #}`注释会被直接丢弃
+fn tokenize(name: &str, source: &str) -> Result<Vec<Token>, TemplateError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut line = 1;
+
+    while !rest.is_empty() {
+        let start = match rest.find('{').and_then(|i| find_open(rest, i)) {
+            Some(i) => i,
+            None => {
+                tokens.push(Token::Text(rest.to_string()));
+                break;
+            }
+        };
+
+        if start > 0 {
+            tokens.push(Token::Text(rest[..start].to_string()));
+            line += rest[..start].matches('\n').count();
+        }
+
+        let close = match &rest[start..start + 2] {
+            "{{" => "}}",
+            "{%" => "%}",
+            _ => "#}",
+        };
+        let inner_start = start + 2;
+        let inner_len = match rest[inner_start..].find(close) {
+            Some(len) => len,
+            None => return Err(syntax_error(name, line, format!("unclosed `{}`", &rest[start..inner_start]))),
+        };
+        let inner = &rest[inner_start..inner_start + inner_len];
+        match close {
+            "}}" => tokens.push(Token::Var(inner.trim().to_string(), line)),
+            "%}" => tokens.push(Token::Tag(inner.trim().to_string(), line)),
+            _ => {}
+        }
+
+        line += inner.matches('\n').count();
+        rest = &rest[inner_start + inner_len + 2..];
+    }
+
+    Ok(tokens)
+}
+
+/// 从`from`开始查找下一个`{{`、`{%`或`{#`的位置
+fn find_open(s: &str, from: usize) -> Option<usize> {
+    let bytes = s.as_bytes();
+    (from..bytes.len().saturating_sub(1))
+        .find(|&i| bytes[i] == b'{' && matches!(bytes[i + 1], b'{' | b'%' | b'#'))
+}
+
+struct Parser<'a> {
+    name: &'a str,
+    tokens: std::vec::IntoIter<Token>,
+    parent: Option<String>,
+    /// 最近一个`{{ }}`或`{% %}`所在的行,用于报告"模板意外结束"的位置
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// 解析节点直到遇到`ends`中的某个标签
+    /// 返回解析出的节点以及遇到的结束标签(连同其行号),`ends`为空时解析到文件末尾
+    fn parse_until(&mut self, ends: &[&str]) -> Result<(Vec<Node>, Option<EndTag>), TemplateError> {
+        let mut nodes = Vec::new();
+
+        while let Some(token) = self.tokens.next() {
+            match token {
+                Token::Text(text) => nodes.push(Node::Text(text)),
+                Token::Var(expr, line) => {
+                    self.line = line;
+                    nodes.push(self.parse_var(&expr, line)?);
+                }
+                Token::Tag(tag, line) => {
+                    self.line = line;
+                    let keyword = tag.split_whitespace().next().unwrap_or("");
+                    if ends.contains(&keyword) {
+                        return Ok((nodes, Some((tag, line))));
+                    }
+                    if let Some(node) = self.parse_tag(&tag, line)? {
+                        nodes.push(node);
+                    }
+                }
+            }
+        }
+
+        if ends.is_empty() {
+            Ok((nodes, None))
+        } else {
+            Err(syntax_error(self.name, self.line, format!("unexpected end of template, expected `{}`", ends.join("` or `"))))
+        }
+    }
+
+    fn parse_var(&self, expr: &str, line: usize) -> Result<Node, TemplateError> {
+        let mut parts = expr.split('|').map(str::trim);
+        let path = self.parse_path(parts.next().unwrap_or(""), line)?;
+        let mut safe = false;
+        for filter in parts {
+            match filter {
+                "safe" => safe = true,
+                "escape" | "e" => safe = false,
+                _ => return Err(syntax_error(self.name, line, format!("unknown filter `{}`", filter))),
+            }
+        }
+        Ok(Node::Var { path, safe })
+    }
+
+    fn parse_path(&self, expr: &str, line: usize) -> Result<Vec<String>, TemplateError> {
+        let valid = |segment: &str| {
+            !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+        };
+        let path: Vec<String> = expr.split('.').map(str::to_string).collect();
+        if path.iter().all(|segment| valid(segment)) {
+            Ok(path)
+        } else {
+            Err(syntax_error(self.name, line, format!("invalid expression `{}`", expr)))
+        }
+    }
+
+    fn parse_condition(&self, expr: &str, line: usize) -> Result<Condition, TemplateError> {
+        match expr.strip_prefix("not ") {
+            Some(rest) => Ok(Condition { negate: true, path: self.parse_path(rest.trim(), line)? }),
+            None => Ok(Condition { negate: false, path: self.parse_path(expr, line)? }),
+        }
+    }
+
+    fn parse_name(&self, arg: &str, line: usize) -> Result<String, TemplateError> {
+        let arg = arg.trim();
+        let unquoted = arg
+            .strip_prefix('"').and_then(|s| s.strip_suffix('"'))
+            .or_else(|| arg.strip_prefix('\'').and_then(|s| s.strip_suffix('\'')));
+        match unquoted {
+            Some(name) if !name.is_empty() => Ok(name.to_string()),
+            _ => Err(syntax_error(self.name, line, format!("expected a quoted template name, got `{}`", arg))),
+        }
+    }
+
+    fn parse_tag(&mut self, tag: &str, line: usize) -> Result<Option<Node>, TemplateError> {
+        let (keyword, args) = match tag.split_once(char::is_whitespace) {
+            Some((keyword, args)) => (keyword, args.trim()),
+            None => (tag, ""),
+        };
+
+        match keyword {
+            "if" => {
+                let mut branches = Vec::new();
+                let mut condition = self.parse_condition(args, line)?;
+                loop {
+                    let (body, end) = self.parse_until(&["elif", "else", "endif"])?;
+                    branches.push((condition, body));
+                    let (end_tag, end_line) = end.unwrap();
+                    match end_tag.split_once(char::is_whitespace) {
+                        Some(("elif", expr)) => condition = self.parse_condition(expr.trim(), end_line)?,
+                        _ if end_tag == "else" => {
+                            let (otherwise, _) = self.parse_until(&["endif"])?;
+                            return Ok(Some(Node::If { branches, otherwise }));
+                        }
+                        _ if end_tag == "endif" => {
+                            return Ok(Some(Node::If { branches, otherwise: Vec::new() }));
+                        }
+                        _ => return Err(syntax_error(self.name, end_line, format!("malformed `{}`", end_tag))),
+                    }
+                }
+            }
+            "for" => {
+                let words: Vec<&str> = args.split_whitespace().collect();
+                let (var, path) = match words.as_slice() {
+                    [var, "in", path] => (var.to_string(), self.parse_path(path, line)?),
+                    _ => return Err(syntax_error(self.name, line, "expected `for <name> in <expr>`")),
+                };
+                let (body, end) = self.parse_until(&["else", "endfor"])?;
+                let otherwise = match end {
+                    Some((end_tag, _)) if end_tag == "else" => self.parse_until(&["endfor"])?.0,
+                    _ => Vec::new(),
+                };
+                Ok(Some(Node::For { var, path, body, otherwise }))
+            }
+            "block" => {
+                let name = args.to_string();
+                if name.is_empty() || name.contains(char::is_whitespace) {
+                    return Err(syntax_error(self.name, line, "expected `block <name>`"));
+                }
+                let (body, _) = self.parse_until(&["endblock"])?;
+                Ok(Some(Node::Block { name, body }))
+            }
+            "include" => Ok(Some(Node::Include(self.parse_name(args, line)?))),
+            "extends" => {
+                if self.parent.is_some() {
+                    return Err(syntax_error(self.name, line, "template already extends another template"));
+                }
+                self.parent = Some(self.parse_name(args, line)?);
+                Ok(None)
+            }
+            _ => Err(syntax_error(self.name, line, format!("unexpected tag `{}`", tag))),
+        }
+    }
+}
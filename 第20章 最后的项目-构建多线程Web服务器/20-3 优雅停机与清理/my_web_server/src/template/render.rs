@@ -0,0 +1,257 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::template::parser::{Condition, Node, Template};
+use crate::template::{Context, TemplateError, Value};
+
+/// `include`和`extends`允许的最大嵌套层数
+const MAX_DEPTH: usize = 32;
+
+/// 渲染时按名称取得已解析模板的途径
+/// 由`Templates`实现,这样渲染逻辑不需要关心模板是从磁盘还是缓存中得到的
+pub(crate) trait Loader {
+    fn load(&self, name: &str) -> Result<Arc<Template>, TemplateError>;
+}
+
+/// 对文本进行HTML转义
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub(crate) fn render(loader: &dyn Loader, name: &str, context: &Context) -> Result<String, TemplateError> {
+    let mut renderer = Renderer { loader, context, scopes: Vec::new(), depth: 0 };
+    let mut out = String::new();
+    renderer.render_template(name, &mut out)?;
+    Ok(out)
+}
+
+struct Renderer<'a> {
+    loader: &'a dyn Loader,
+    context: &'a Context,
+    /// `for`循环引入的局部变量,越靠后的作用域越内层
+    scopes: Vec<(String, Value)>,
+    depth: usize,
+}
+
+impl<'a> Renderer<'a> {
+    fn render_template(&mut self, name: &str, out: &mut String) -> Result<(), TemplateError> {
+        // 沿着extends找到最顶层的模板,子模板中的block优先于父模板中的同名block
+        let mut chain = vec![self.loader.load(name)?];
+        while let Some(parent) = chain.last().unwrap().parent.clone() {
+            if chain.len() + self.depth > MAX_DEPTH {
+                return Err(TemplateError::RecursionLimit(name.to_string()));
+            }
+            chain.push(self.loader.load(&parent)?);
+        }
+
+        let mut blocks = HashMap::new();
+        for template in &chain {
+            collect_blocks(&template.nodes, &mut blocks);
+        }
+
+        self.depth += chain.len();
+        let result = self.render_nodes(&chain.last().unwrap().nodes, &blocks, out);
+        self.depth -= chain.len();
+        result
+    }
+
+    fn render_nodes(&mut self, nodes: &[Node], blocks: &HashMap<&str, &[Node]>, out: &mut String) -> Result<(), TemplateError> {
+        for node in nodes {
+            match node {
+                Node::Text(text) => out.push_str(text),
+                Node::Var { path, safe } => {
+                    if let Some(value) = self.lookup(path) {
+                        let text = value.to_string();
+                        if *safe {
+                            out.push_str(&text);
+                        } else {
+                            out.push_str(&escape_html(&text));
+                        }
+                    }
+                }
+                Node::If { branches, otherwise } => {
+                    let body = branches
+                        .iter()
+                        .find(|(condition, _)| self.test(condition))
+                        .map(|(_, body)| body)
+                        .unwrap_or(otherwise);
+                    self.render_nodes(body, blocks, out)?;
+                }
+                Node::For { var, path, body, otherwise } => {
+                    let items = match self.lookup(path) {
+                        Some(Value::List(list)) => list.clone(),
+                        Some(Value::Map(map)) => map
+                            .iter()
+                            .map(|(key, value)| {
+                                Context::new().with("key", key.as_str()).with("value", value.clone()).into()
+                            })
+                            .collect(),
+                        _ => Vec::new(),
+                    };
+                    if items.is_empty() {
+                        self.render_nodes(otherwise, blocks, out)?;
+                        continue;
+                    }
+
+                    let length = items.len();
+                    for (index, item) in items.into_iter().enumerate() {
+                        let info = Context::new()
+                            .with("index", index + 1)
+                            .with("index0", index)
+                            .with("first", index == 0)
+                            .with("last", index + 1 == length)
+                            .with("length", length);
+                        self.scopes.push(("loop".to_string(), info.into()));
+                        self.scopes.push((var.clone(), item));
+                        let result = self.render_nodes(body, blocks, out);
+                        self.scopes.truncate(self.scopes.len() - 2);
+                        result?;
+                    }
+                }
+                Node::Include(name) => {
+                    if self.depth >= MAX_DEPTH {
+                        return Err(TemplateError::RecursionLimit(name.clone()));
+                    }
+                    self.render_template(name, out)?;
+                }
+                Node::Block { name, body } => {
+                    let body = blocks.get(name.as_str()).copied().unwrap_or(body);
+                    self.render_nodes(body, blocks, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn test(&self, condition: &Condition) -> bool {
+        let truthy = self.lookup(&condition.path).is_some_and(Value::is_truthy);
+        truthy != condition.negate
+    }
+
+    /// 按`a.b.c`形式的路径查找变量,先查循环变量再查上下文
+    /// 路径中的数字段可用来按下标访问列表
+    fn lookup(&self, path: &[String]) -> Option<&Value> {
+        let (first, rest) = path.split_first()?;
+        let mut value = self
+            .scopes
+            .iter()
+            .rev()
+            .find(|(name, _)| name == first)
+            .map(|(_, value)| value)
+            .or_else(|| self.context.get(first))?;
+
+        for segment in rest {
+            value = match value {
+                Value::List(list) => list.get(segment.parse::<usize>().ok()?)?,
+                _ => value.get(segment)?,
+            };
+        }
+        Some(value)
+    }
+}
+
+/// 收集模板中所有的block(包括嵌套的block),已存在的同名block不会被覆盖
+fn collect_blocks<'t>(nodes: &'t [Node], blocks: &mut HashMap<&'t str, &'t [Node]>) {
+    for node in nodes {
+        match node {
+            Node::Block { name, body } => {
+                blocks.entry(name.as_str()).or_insert(body.as_slice());
+                collect_blocks(body, blocks);
+            }
+            Node::If { branches, otherwise } => {
+                for (_, body) in branches {
+                    collect_blocks(body, blocks);
+                }
+                collect_blocks(otherwise, blocks);
+            }
+            Node::For { body, otherwise, .. } => {
+                collect_blocks(body, blocks);
+                collect_blocks(otherwise, blocks);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use crate::template::parser::parse;
+
+    struct MapLoader(BTreeMap<&'static str, &'static str>);
+
+    impl Loader for MapLoader {
+        fn load(&self, name: &str) -> Result<Arc<Template>, TemplateError> {
+            let source = self.0.get(name).ok_or_else(|| TemplateError::InvalidName(name.to_string()))?;
+            Ok(Arc::new(parse(name, source)?))
+        }
+    }
+
+    fn render_with(templates: &[(&'static str, &'static str)], context: &Context) -> Result<String, TemplateError> {
+        let loader = MapLoader(templates.iter().copied().collect());
+        render(&loader, templates[0].0, context)
+    }
+
+    #[test]
+    // 变量插值时应当转义HTML,使用safe过滤器时不转义
+    fn interpolation_escapes_html() {
+        let context = Context::new().with("name", "<b>Tom & \"Jerry\"</b>");
+        let out = render_with(&[("t", "{{ name }}|{{ name | safe }}")], &context).unwrap();
+        assert_eq!("&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;|<b>Tom & \"Jerry\"</b>", out);
+    }
+
+    #[test]
+    // 嵌套字段与列表下标
+    fn dotted_lookup() {
+        let user = Context::new().with("name", "ferris").with("langs", vec!["rust", "c"]);
+        let context = Context::new().with("user", user);
+        let out = render_with(&[("t", "{{ user.name }} {{ user.langs.1 }} {{ user.missing }}")], &context).unwrap();
+        assert_eq!("ferris c ", out);
+    }
+
+    #[test]
+    fn for_and_if() {
+        let context = Context::new().with("items", vec!["a", "b", "c"]).with("empty", Vec::<String>::new());
+        let source = "{% for x in items %}{% if loop.first %}[{% elif loop.last %}]{% else %},{% endif %}{{ x }}{% endfor %}\
+                      {% for x in empty %}{{ x }}{% else %}none{% endfor %}{% if not empty %}!{% endif %}";
+        let out = render_with(&[("t", source)], &context).unwrap();
+        assert_eq!("[a,b]cnone!", out);
+    }
+
+    #[test]
+    // 子模板覆盖父模板中的block,未覆盖的block使用父模板的默认内容
+    fn extends_and_include() {
+        let templates = [
+            ("page", "{% extends \"layout\" %}ignored{% block body %}Hi {% include \"name\" %}{% endblock %}"),
+            ("layout", "<title>{% block title %}Default{% endblock %}</title><main>{% block body %}{% endblock %}</main>"),
+            ("name", "{{ name }}"),
+        ];
+        let out = render_with(&templates, &Context::new().with("name", "Rust")).unwrap();
+        assert_eq!("<title>Default</title><main>Hi Rust</main>", out);
+    }
+
+    #[test]
+    fn include_cycle_is_an_error() {
+        let result = render_with(&[("a", "{% include \"b\" %}"), ("b", "{% include \"a\" %}")], &Context::new());
+        assert!(matches!(result, Err(TemplateError::RecursionLimit(_))));
+    }
+
+    #[test]
+    fn syntax_errors_report_line() {
+        let result = parse("t", "line 1\n{% if x %}\nno end");
+        assert!(matches!(result, Err(TemplateError::Syntax { line: 2, .. })));
+        assert!(parse("t", "{% frobnicate %}").is_err());
+        assert!(parse("t", "{{ a..b }}").is_err());
+    }
+}
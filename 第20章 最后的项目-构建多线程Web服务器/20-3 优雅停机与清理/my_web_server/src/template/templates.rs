@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+use crate::template::parser::{self, Template};
+use crate::template::render::{self, Loader};
+use crate::template::{Context, TemplateError};
+
+/// 从目录中加载模板并缓存解析结果
+/// 模板名即相对于模板目录的路径,例如`"hello.html"`、`"partials/nav.html"`
+/// 该类型可以通过`Arc`在线程池的多个worker之间共享
+pub struct Templates {
+    dir: PathBuf,
+    hot_reload: bool,
+    cache: RwLock<HashMap<String, Cached>>,
+}
+
+struct Cached {
+    template: Arc<Template>,
+    modified: Option<SystemTime>,
+}
+
+impl Templates {
+    /// 创建一个从`dir`目录加载模板的`Templates`
+    /// 模板在第一次使用时才会被读取和解析,之后一直使用缓存
+    pub fn new<P: AsRef<Path>>(dir: P) -> Templates {
+        Templates {
+            dir: dir.as_ref().to_path_buf(),
+            hot_reload: false,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 开启或关闭热重载
+    /// 开启后每次渲染都会检查模板文件的修改时间,文件有变化时重新解析.适合开发环境使用
+    pub fn hot_reload(mut self, enabled: bool) -> Templates {
+        self.hot_reload = enabled;
+        self
+    }
+
+    /// 使用`context`渲染名为`name`的模板
+    pub fn render(&self, name: &str, context: &Context) -> Result<String, TemplateError> {
+        render::render(self, name, context)
+    }
+
+    /// 模板名只允许是模板目录内的相对路径,防止通过`../`读取目录外的文件
+    fn path_of(&self, name: &str) -> Result<PathBuf, TemplateError> {
+        let relative = Path::new(name);
+        let valid = relative.components().all(|component| matches!(component, Component::Normal(_)));
+        if name.is_empty() || !valid {
+            return Err(TemplateError::InvalidName(name.to_string()));
+        }
+        Ok(self.dir.join(relative))
+    }
+}
+
+impl Loader for Templates {
+    fn load(&self, name: &str) -> Result<Arc<Template>, TemplateError> {
+        let path = self.path_of(name)?;
+        let modified = if self.hot_reload {
+            fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()
+        } else {
+            None
+        };
+
+        if let Some(cached) = self.cache.read().unwrap().get(name) {
+            if !self.hot_reload || cached.modified == modified {
+                return Ok(cached.template.clone());
+            }
+        }
+
+        let source = fs::read_to_string(&path).map_err(|source| TemplateError::Io { path, source })?;
+        let template = Arc::new(parser::parse(name, &source)?);
+        self.cache.write().unwrap().insert(
+            name.to_string(),
+            Cached { template: template.clone(), modified },
+        );
+        Ok(template)
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+use serde::de::DeserializeOwned;
+use crate::http::{Headers, Method};
+
+/// 一个简单的阻塞式HTTP/1.1客户端请求,仅用于测试
+pub struct TestRequest {
+    addr: SocketAddr,
+    method: Method,
+    target: String,
+    headers: Headers,
+    body: Vec<u8>,
+}
+
+impl TestRequest {
+    pub fn new(addr: SocketAddr, method: Method, target: &str) -> TestRequest {
+        let mut headers = Headers::new();
+        headers.insert("Host", addr.to_string());
+        TestRequest { addr, method, target: target.to_string(), headers, body: Vec::new() }
+    }
+
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, name: K, value: V) -> TestRequest {
+        self.headers.insert(name, value);
+        self
+    }
+
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> TestRequest {
+        self.body = body.into();
+        self
+    }
+
+    /// 将`value`序列化为JSON作为body,并设置`Content-Type`
+    pub fn json<T: serde::Serialize + ?Sized>(self, value: &T) -> TestRequest {
+        let body = serde_json::to_vec(value).expect("failed to serialize request body");
+        self.header("Content-Type", "application/json").body(body)
+    }
+
+    /// 发送请求并读取完整的响应
+    pub fn send(self) -> io::Result<TestResponse> {
+        let mut stream = TcpStream::connect(self.addr)?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+
+        let mut head = format!("{} {} HTTP/1.1\r\n", self.method, self.target);
+        for (name, value) in self.headers.iter() {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !self.headers.contains("Content-Length") {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        head.push_str("Connection: close\r\n\r\n");
+        stream.write_all(head.as_bytes())?;
+        stream.write_all(&self.body)?;
+        stream.flush()?;
+
+        TestResponse::read_from(&mut BufReader::new(stream), self.method == Method::Head)
+    }
+}
+
+/// 测试客户端收到的响应
+#[derive(Debug)]
+pub struct TestResponse {
+    pub status: u16,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl TestResponse {
+    /// 读取响应,`head_only`为真时不读取body(HEAD请求的响应没有body)
+    pub fn read_from<R: BufRead>(reader: &mut R, head_only: bool) -> io::Result<TestResponse> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        // 跳过`100 Continue`之类的临时响应
+        let (status, headers) = loop {
+            let status_line = read_line(reader)?;
+            let status: u16 = status_line
+                .split(' ')
+                .nth(1)
+                .and_then(|code| code.parse().ok())
+                .ok_or_else(|| invalid("malformed status line"))?;
+
+            let mut headers = Headers::new();
+            loop {
+                let line = read_line(reader)?;
+                if line.is_empty() {
+                    break;
+                }
+                let (name, value) = line.split_once(':').ok_or_else(|| invalid("malformed header"))?;
+                headers.append(name, value.trim());
+            }
+            if !(100..200).contains(&status) {
+                break (status, headers);
+            }
+        };
+
+        let mut body = Vec::new();
+        if !head_only && status != 204 && status != 304 {
+            match headers.get("Content-Length") {
+                Some(length) => {
+                    let length: usize = length.parse().map_err(|_| invalid("invalid Content-Length"))?;
+                    body.resize(length, 0);
+                    reader.read_exact(&mut body)?;
+                }
+                None => {
+                    reader.read_to_end(&mut body)?;
+                }
+            }
+        }
+
+        Ok(TestResponse { status, headers, body })
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// 将body按UTF-8解码为字符串
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> serde_json::Result<T> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> io::Result<String> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed"));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
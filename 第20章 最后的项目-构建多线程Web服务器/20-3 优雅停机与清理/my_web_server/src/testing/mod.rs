@@ -0,0 +1,5 @@
+pub mod test_server;
+pub use test_server::TestServer;
+
+pub mod client;
+pub use client::{TestRequest, TestResponse};
@@ -0,0 +1,90 @@
+use std::net::SocketAddr;
+use std::thread;
+use crate::http::Method;
+use crate::routing::Router;
+use crate::server::{ReloadHandle, Server, ShutdownHandle};
+use crate::testing::TestRequest;
+
+/// 用于集成测试的服务器
+/// 绑定到`127.0.0.1`的随机端口(端口0),在后台线程中使用真实的`Server`和线程池处理请求,
+/// 被丢弃时停止服务器并等待正在处理的请求完成
+pub struct TestServer {
+    addr: SocketAddr,
+    handle: ShutdownHandle,
+    reload: ReloadHandle,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl TestServer {
+    /// 使用4个worker启动服务器
+    pub fn start(router: Router) -> TestServer {
+        TestServer::with_workers(router, 4)
+    }
+
+    pub fn with_workers(router: Router, workers: usize) -> TestServer {
+        let server = Server::bind("127.0.0.1:0", router)
+            .expect("failed to bind test server")
+            .workers(workers);
+        TestServer::spawn(server)
+    }
+
+    /// 启动同时接受h2c连接的服务器,见`Server::http2()`
+    pub fn with_http2(router: Router, workers: usize) -> TestServer {
+        let server = Server::bind("127.0.0.1:0", router)
+            .expect("failed to bind test server")
+            .workers(workers)
+            .http2(true);
+        TestServer::spawn(server)
+    }
+
+    fn spawn(server: Server) -> TestServer {
+        let addr = server.local_addr().unwrap();
+        let handle = server.shutdown_handle().unwrap();
+        let reload = server.reload_handle();
+        let thread = thread::spawn(move || server.run());
+
+        TestServer { addr, handle, reload, thread: Some(thread) }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// 用于在测试中替换服务器的路由和资源限制
+    pub fn reload_handle(&self) -> ReloadHandle {
+        self.reload.clone()
+    }
+
+    /// 构造一个发往该服务器的请求,调用`send()`发送
+    pub fn request(&self, method: Method, target: &str) -> TestRequest {
+        TestRequest::new(self.addr, method, target)
+    }
+
+    pub fn get(&self, target: &str) -> TestRequest {
+        self.request(Method::Get, target)
+    }
+
+    pub fn post(&self, target: &str) -> TestRequest {
+        self.request(Method::Post, target)
+    }
+
+    pub fn put(&self, target: &str) -> TestRequest {
+        self.request(Method::Put, target)
+    }
+
+    pub fn delete(&self, target: &str) -> TestRequest {
+        self.request(Method::Delete, target)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.handle.shutdown();
+        if let Some(thread) = self.thread.take() {
+            // 测试已经失败而正在panic时,不再因为服务器线程的panic而二次panic
+            if thread.join().is_err() && !thread::panicking() {
+                panic!("test server thread panicked");
+            }
+        }
+    }
+}
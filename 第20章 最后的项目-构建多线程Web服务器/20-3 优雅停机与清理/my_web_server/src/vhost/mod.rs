@@ -0,0 +1,33 @@
+pub mod virtual_hosts;
+pub use virtual_hosts::{VirtualHost, VirtualHosts};
+
+/// 从`Host`头部中取出主机名,去掉端口和末尾的`.`并转为小写
+/// IPv6地址保留方括号,例如`[::1]:8080`得到`[::1]`
+pub fn host_name(header: &str) -> Option<String> {
+    let header = header.trim();
+    let host = if header.starts_with('[') {
+        &header[..=header.find(']')?]
+    } else {
+        header.rsplit_once(':').map_or(header, |(host, _)| host)
+    };
+    let host = host.trim_end_matches('.');
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_ascii_lowercase())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_names() {
+        assert_eq!(Some("example.com".to_string()), host_name("Example.COM:8080"));
+        assert_eq!(Some("example.com".to_string()), host_name("example.com."));
+        assert_eq!(Some("[::1]".to_string()), host_name("[::1]:7878"));
+        assert_eq!(None, host_name(":80"));
+        assert_eq!(None, host_name("[::1"));
+    }
+}
@@ -0,0 +1,104 @@
+use std::path::PathBuf;
+use crate::files::ServeDir;
+use crate::http::{Request, Response};
+use crate::middleware::{AccessLog, Middleware, Next};
+use crate::routing::Router;
+use crate::vhost::host_name;
+
+/// 一个虚拟主机,拥有自己的路由、文档根目录和访问日志
+pub struct VirtualHost {
+    names: Vec<String>,
+    router: Router,
+    access_log: Option<AccessLog>,
+}
+
+impl VirtualHost {
+    /// 名为`name`的虚拟主机,`*.example.com`形式的名称匹配所有子域名
+    pub fn new(name: &str, router: Router) -> VirtualHost {
+        VirtualHost {
+            names: vec![name.to_ascii_lowercase()],
+            router,
+            access_log: None,
+        }
+    }
+
+    /// 为该主机添加别名
+    pub fn alias(mut self, name: &str) -> VirtualHost {
+        self.names.push(name.to_ascii_lowercase());
+        self
+    }
+
+    /// 设置文档根目录,没有路由匹配的GET请求从该目录中提供文件
+    /// 文件服务被注册为路由的fallback,会替换已经设置的fallback
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> VirtualHost {
+        let files = ServeDir::new(root);
+        self.router = self.router.fallback(move |request| files.serve(request));
+        self
+    }
+
+    /// 该主机的请求写入单独的访问日志
+    pub fn access_log(mut self, log: AccessLog) -> VirtualHost {
+        self.access_log = Some(log);
+        self
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        self.names.iter().any(|name| match name.strip_prefix("*.") {
+            Some(domain) => host.strip_suffix(domain).is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            None => name == host,
+        })
+    }
+
+    fn handle(&self, request: &mut Request) -> Response {
+        let response = self.router.handle(request);
+        if let Some(log) = &self.access_log {
+            log.log(request, &response);
+        }
+        response
+    }
+}
+
+/// 根据`Host`头部把请求交给对应的虚拟主机
+/// 作为中间件添加到外层的`Router`上,并且应当是最后添加的中间件,这样外层的限流等中间件对所有主机生效.
+/// 没有主机匹配时交给默认主机;没有设置默认主机时交给外层`Router`自己的路由处理
+#[derive(Default)]
+pub struct VirtualHosts {
+    hosts: Vec<VirtualHost>,
+    default: Option<usize>,
+}
+
+impl VirtualHosts {
+    pub fn new() -> VirtualHosts {
+        VirtualHosts::default()
+    }
+
+    /// 添加一个虚拟主机,先添加的主机优先匹配
+    pub fn host(mut self, host: VirtualHost) -> VirtualHosts {
+        self.hosts.push(host);
+        self
+    }
+
+    /// 添加一个虚拟主机并将其作为默认主机
+    pub fn default_host(mut self, host: VirtualHost) -> VirtualHosts {
+        self.default = Some(self.hosts.len());
+        self.hosts.push(host);
+        self
+    }
+
+    fn find(&self, request: &Request) -> Option<&VirtualHost> {
+        let matched = request
+            .header("Host")
+            .and_then(host_name)
+            .and_then(|host| self.hosts.iter().find(|vhost| vhost.matches(&host)));
+        matched.or_else(|| self.hosts.get(self.default?))
+    }
+}
+
+impl Middleware for VirtualHosts {
+    fn handle(&self, request: &mut Request, next: Next<'_>) -> Response {
+        match self.find(request) {
+            Some(host) => host.handle(request),
+            None => next.run(request),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+#![cfg(unix)]
+
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use my_web_server::cgi::CgiHandler;
+use my_web_server::routing::Router;
+use my_web_server::testing::TestServer;
+
+/// 把`body`写成一个可执行的shell脚本
+fn script(name: &str, body: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("cgi-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{}", body)).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn cgi_server(cgi: CgiHandler) -> TestServer {
+    let post = cgi.clone();
+    let router = Router::new()
+        .get("/cgi-bin/script/*path", move |request| cgi.serve(request))
+        .post("/cgi-bin/script/*path", move |request| post.serve(request));
+    TestServer::start(router)
+}
+
+#[test]
+fn passes_environment_and_body() {
+    let program = script(
+        "env.sh",
+        r#"printf 'Content-Type: text/plain\r\nX-Method: %s\r\n\r\n' "$REQUEST_METHOD"
+echo "script=$SCRIPT_NAME"
+echo "path_info=$PATH_INFO"
+echo "query=$QUERY_STRING"
+echo "agent=$HTTP_USER_AGENT"
+echo "proxy=$HTTP_PROXY"
+echo "body=$(head -c "$CONTENT_LENGTH")"
+"#,
+    );
+    let server = cgi_server(CgiHandler::new(program));
+    let response = server
+        .post("/cgi-bin/script/reports/daily?format=csv")
+        .header("User-Agent", "test-agent")
+        .header("Proxy", "http://evil.example")
+        .body("hello cgi")
+        .send()
+        .unwrap();
+
+    assert_eq!(200, response.status);
+    assert_eq!(Some("POST"), response.header("X-Method"));
+    assert_eq!(
+        "script=/cgi-bin/script\npath_info=/reports/daily\nquery=format=csv\nagent=test-agent\nproxy=\nbody=hello cgi\n",
+        response.text()
+    );
+}
+
+#[test]
+fn status_header_sets_status() {
+    let program = script("status.sh", "printf 'Status: 404 Not Found\\nContent-Type: text/plain\\n\\nnope'\n");
+    let response = cgi_server(CgiHandler::new(program)).get("/cgi-bin/script").send().unwrap();
+    assert_eq!(404, response.status);
+    assert_eq!("nope", response.text());
+}
+
+#[test]
+fn malformed_output_is_502() {
+    let program = script("bad.sh", "echo 'not a header'\n");
+    let response = cgi_server(CgiHandler::new(program)).get("/cgi-bin/script").send().unwrap();
+    assert_eq!(502, response.status);
+}
+
+#[test]
+// 超时后程序和它启动的子进程都被杀死
+fn timeout_kills_program() {
+    let program = script("slow.sh", "sleep 30\necho 'Content-Type: text/plain'\necho\n");
+    let server = cgi_server(CgiHandler::new(program).timeout(Duration::from_millis(300)));
+
+    let start = Instant::now();
+    let response = server.get("/cgi-bin/script").send().unwrap();
+    assert_eq!(504, response.status);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
@@ -0,0 +1,115 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use my_web_server::files::ServeDir;
+use my_web_server::routing::Router;
+use my_web_server::testing::TestServer;
+
+/// 在临时目录中准备一个内容为`0123456789abcdefghij`的文件
+struct Fixture {
+    dir: PathBuf,
+    server: TestServer,
+}
+
+impl Fixture {
+    fn new(name: &str) -> Fixture {
+        let dir = env::temp_dir().join(format!("serve-dir-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("logs/app.log"), "0123456789abcdefghij").unwrap();
+        fs::write(dir.join("secret.txt"), "secret").unwrap();
+
+        let files = ServeDir::new(dir.join("logs"));
+        let router = Router::new().get("/files/*path", move |request| files.serve(request));
+        Fixture { dir, server: TestServer::start(router) }
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn full_file() {
+    let fixture = Fixture::new("full");
+    let response = fixture.server.get("/files/app.log").send().unwrap();
+
+    assert_eq!(200, response.status);
+    assert_eq!("0123456789abcdefghij", response.text());
+    assert_eq!(Some("bytes"), response.header("Accept-Ranges"));
+    assert_eq!(Some("text/plain; charset=utf-8"), response.header("Content-Type"));
+}
+
+#[test]
+fn single_range() {
+    let fixture = Fixture::new("single");
+    let response = fixture.server.get("/files/app.log").header("Range", "bytes=5-9").send().unwrap();
+    assert_eq!(206, response.status);
+    assert_eq!(Some("bytes 5-9/20"), response.header("Content-Range"));
+    assert_eq!("56789", response.text());
+
+    let suffix = fixture.server.get("/files/app.log").header("Range", "bytes=-3").send().unwrap();
+    assert_eq!("hij", suffix.text());
+}
+
+#[test]
+fn multiple_ranges() {
+    let fixture = Fixture::new("multi");
+    let response = fixture.server.get("/files/app.log").header("Range", "bytes=0-1, 18-").send().unwrap();
+    assert_eq!(206, response.status);
+
+    let content_type = response.header("Content-Type").unwrap();
+    let boundary = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+    let expected = format!(
+        "--{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 0-1/20\r\n\r\n01\r\n\
+         --{b}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Range: bytes 18-19/20\r\n\r\nij\r\n\
+         --{b}--\r\n",
+        b = boundary
+    );
+    assert_eq!(expected, response.text());
+}
+
+#[test]
+fn unsatisfiable_range_is_416() {
+    let fixture = Fixture::new("416");
+    let response = fixture.server.get("/files/app.log").header("Range", "bytes=20-").send().unwrap();
+    assert_eq!(416, response.status);
+    assert_eq!(Some("bytes */20"), response.header("Content-Range"));
+}
+
+#[test]
+// If-Range与当前ETag不符时返回完整内容,相符时返回范围
+fn if_range_and_etag() {
+    let fixture = Fixture::new("etag");
+    let etag = fixture.server.get("/files/app.log").send().unwrap().header("ETag").unwrap().to_string();
+
+    let stale = fixture
+        .server
+        .get("/files/app.log")
+        .header("Range", "bytes=0-0")
+        .header("If-Range", "\"stale\"")
+        .send()
+        .unwrap();
+    assert_eq!(200, stale.status);
+    let fresh = fixture
+        .server
+        .get("/files/app.log")
+        .header("Range", "bytes=0-0")
+        .header("If-Range", etag.as_str())
+        .send()
+        .unwrap();
+    assert_eq!(206, fresh.status);
+
+    let cached = fixture.server.get("/files/app.log").header("If-None-Match", etag.as_str()).send().unwrap();
+    assert_eq!(304, cached.status);
+    assert!(cached.body.is_empty());
+}
+
+#[test]
+fn rejects_paths_outside_root() {
+    let fixture = Fixture::new("traversal");
+    assert_eq!(404, fixture.server.get("/files/../secret.txt").send().unwrap().status);
+    assert_eq!(404, fixture.server.get("/files/%2e%2e/secret.txt").send().unwrap().status);
+    assert_eq!(404, fixture.server.get("/files/missing.log").send().unwrap().status);
+}
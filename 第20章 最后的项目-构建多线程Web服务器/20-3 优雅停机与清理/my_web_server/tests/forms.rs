@@ -0,0 +1,125 @@
+use std::io::Read;
+use serde_json::{json, Value};
+use my_web_server::form::{MultipartLimits, PartData};
+use my_web_server::http::{HttpError, Request, Response};
+use my_web_server::routing::Router;
+use my_web_server::server::Limits;
+use my_web_server::testing::TestServer;
+
+fn form_server() -> TestServer {
+    let router = Router::new()
+        .post("/form", |request: &Request| -> Result<Response, HttpError> {
+            let form = request.form()?;
+            let tags: Vec<&str> = form.get_all("tag").collect();
+            Ok(Response::json(&json!({ "name": form.get("name"), "tags": tags })))
+        })
+        .post("/upload", |request: &Request| -> Result<Response, HttpError> {
+            let multipart = request.multipart()?;
+            let file = multipart.part("file").ok_or_else(|| HttpError::bad_request("missing file"))?;
+            let mut contents = String::new();
+            file.reader()
+                .and_then(|mut reader| reader.read_to_string(&mut contents))
+                .map_err(|e| HttpError::internal(e.to_string()))?;
+            Ok(Response::json(&json!({
+                "title": multipart.field("title"),
+                "filename": file.filename(),
+                "size": file.size(),
+                "contents": contents,
+            })))
+        });
+    TestServer::start(router)
+}
+
+#[test]
+fn urlencoded_form() {
+    let server = form_server();
+    let response = server
+        .post("/form")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("name=Ferris+the+crab&tag=rust&tag=%F0%9F%A6%80")
+        .send()
+        .unwrap();
+
+    assert_eq!(200, response.status);
+    assert_eq!(json!({ "name": "Ferris the crab", "tags": ["rust", "🦀"] }), response.json::<Value>().unwrap());
+}
+
+#[test]
+fn multipart_upload() {
+    let server = form_server();
+    let body = "--boundary42\r\n\
+                Content-Disposition: form-data; name=\"title\"\r\n\r\n\
+                notes\r\n\
+                --boundary42\r\n\
+                Content-Disposition: form-data; name=\"file\"; filename=\"notes.txt\"\r\n\
+                Content-Type: text/plain\r\n\r\n\
+                line 1\r\nline 2\r\n\
+                --boundary42--\r\n";
+    let response = server
+        .post("/upload")
+        .header("Content-Type", "multipart/form-data; boundary=boundary42")
+        .body(body)
+        .send()
+        .unwrap();
+
+    assert_eq!(200, response.status);
+    assert_eq!(
+        json!({ "title": "notes", "filename": "notes.txt", "size": 14, "contents": "line 1\r\nline 2" }),
+        response.json::<Value>().unwrap()
+    );
+}
+
+#[test]
+fn wrong_content_type_is_415() {
+    let server = form_server();
+    let response = server.post("/upload").header("Content-Type", "text/plain").body("x").send().unwrap();
+    assert_eq!(415, response.status);
+}
+
+#[test]
+// multipart请求边读边解析,文件part直接写入临时文件,因此上传可以超过`max_body_size`
+fn multipart_streams_past_max_body_size() {
+    let router = Router::new().post("/upload", |request: &Request| -> Result<Response, HttpError> {
+        let multipart = request.multipart()?;
+        let file = multipart.part("file").ok_or_else(|| HttpError::bad_request("missing file"))?;
+        let mut contents = Vec::new();
+        file.reader()
+            .and_then(|mut reader| reader.read_to_end(&mut contents))
+            .map_err(|e| HttpError::internal(e.to_string()))?;
+        Ok(Response::json(&json!({
+            "buffered_body": request.body().len(),
+            "on_disk": matches!(file.data(), PartData::File(_)),
+            "checksum": contents.iter().map(|&b| b as u64).sum::<u64>(),
+            "size": file.size(),
+        })))
+    });
+    let server = TestServer::start(Router::new());
+    let limits = Limits {
+        max_body_size: 1024,
+        multipart: MultipartLimits { max_size: 1024 * 1024, memory_threshold: 4096, ..MultipartLimits::default() },
+        ..Limits::default()
+    };
+    server.reload_handle().reload(router, limits);
+
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let mut body = b"--boundary42\r\n\
+                     Content-Disposition: form-data; name=\"file\"; filename=\"big.bin\"\r\n\r\n"
+        .to_vec();
+    body.extend_from_slice(&data);
+    body.extend_from_slice(b"\r\n--boundary42--\r\n");
+    let response = server
+        .post("/upload")
+        .header("Content-Type", "multipart/form-data; boundary=boundary42")
+        .body(body)
+        .send()
+        .unwrap();
+
+    assert_eq!(200, response.status);
+    let checksum: u64 = data.iter().map(|&b| b as u64).sum();
+    assert_eq!(
+        json!({ "buffered_body": 0, "on_disk": true, "checksum": checksum, "size": 300_000 }),
+        response.json::<Value>().unwrap()
+    );
+    // 其他请求仍然受`max_body_size`限制
+    assert_eq!(413, server.post("/upload").header("Content-Type", "text/plain").body(vec![b'x'; 2048]).send().unwrap().status);
+}
@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use my_web_server::http::{Request, Response};
+use my_web_server::http2::frame::{self, write_frame, Frame, FrameType};
+use my_web_server::http2::hpack::{Decoder, Encoder};
+use my_web_server::http2::PREFACE;
+use my_web_server::routing::Router;
+use my_web_server::server::Limits;
+use my_web_server::testing::TestServer;
+
+fn router() -> Router {
+    Router::new()
+        .get("/slow", |_: &Request| {
+            thread::sleep(Duration::from_millis(300));
+            Response::text(200, "slow")
+        })
+        .post("/echo", |request: &Request| {
+            Response::text(200, String::from_utf8_lossy(request.body()).into_owned())
+                .with_header("X-Version", request.version())
+                .with_header("X-Host", request.header("Host").unwrap_or(""))
+        })
+        // 超过默认流量控制窗口(65535字节),需要客户端更新窗口才能发送完
+        .get("/big", |_: &Request| Response::text(200, "x".repeat(100_000)))
+}
+
+/// 收到的一个响应
+#[derive(Default)]
+struct Stream {
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+/// 手写的最小HTTP/2客户端,只用于测试
+struct Client {
+    stream: TcpStream,
+    encoder: Encoder,
+    decoder: Decoder,
+}
+
+impl Client {
+    fn connect(server: &TestServer) -> Client {
+        let mut stream = TcpStream::connect(server.addr()).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream.write_all(PREFACE).unwrap();
+        write_frame(&mut stream, FrameType::Settings, 0, 0, &[]).unwrap();
+        Client { stream, encoder: Encoder::new(), decoder: Decoder::new() }
+    }
+
+    fn request(&mut self, id: u32, method: &str, path: &str, body: &[u8]) {
+        self.headers(id, method, path, body.is_empty());
+        if !body.is_empty() {
+            self.data(id, body, true);
+        }
+    }
+
+    fn headers(&mut self, id: u32, method: &str, path: &str, end_stream: bool) {
+        let mut block = Vec::new();
+        let fields = [(":method", method), (":scheme", "http"), (":authority", "example.com"), (":path", path)];
+        self.encoder.encode(fields, &mut block);
+        let flags = frame::END_HEADERS | if end_stream { frame::END_STREAM } else { 0 };
+        write_frame(&mut self.stream, FrameType::Headers, flags, id, &block).unwrap();
+    }
+
+    fn data(&mut self, id: u32, data: &[u8], end_stream: bool) {
+        let flags = if end_stream { frame::END_STREAM } else { 0 };
+        write_frame(&mut self.stream, FrameType::Data, flags, id, data).unwrap();
+    }
+
+    /// 读取帧直到收到GOAWAY,返回其中的错误码
+    fn go_away(&mut self) -> u32 {
+        loop {
+            let frame = Frame::read_from(&mut self.stream, frame::DEFAULT_MAX_FRAME_SIZE).unwrap();
+            if frame.header.kind == FrameType::GoAway {
+                return frame::read_u31(&frame.payload[4..8]);
+            }
+        }
+    }
+
+    /// 读取帧直到`count`个流都结束,返回各个流以及它们结束的顺序
+    fn responses(&mut self, count: usize) -> (HashMap<u32, Stream>, Vec<u32>) {
+        let mut streams: HashMap<u32, Stream> = HashMap::new();
+        let mut finished = Vec::new();
+        while finished.len() < count {
+            let frame = Frame::read_from(&mut self.stream, frame::DEFAULT_MAX_FRAME_SIZE).unwrap();
+            let header = frame.header;
+            match header.kind {
+                FrameType::Settings if !header.has_flag(frame::ACK) => {
+                    write_frame(&mut self.stream, FrameType::Settings, frame::ACK, 0, &[]).unwrap();
+                }
+                FrameType::Headers => {
+                    assert!(header.has_flag(frame::END_HEADERS));
+                    let fields = self.decoder.decode(&frame.payload).unwrap();
+                    streams.entry(header.stream_id).or_default().headers = fields;
+                }
+                FrameType::Data => {
+                    streams.entry(header.stream_id).or_default().body.extend_from_slice(&frame.payload);
+                    if !frame.payload.is_empty() {
+                        let increment = (frame.payload.len() as u32).to_be_bytes();
+                        write_frame(&mut self.stream, FrameType::WindowUpdate, 0, 0, &increment).unwrap();
+                        write_frame(&mut self.stream, FrameType::WindowUpdate, 0, header.stream_id, &increment).unwrap();
+                    }
+                }
+                FrameType::RstStream | FrameType::GoAway => panic!("unexpected {:?}: {:?}", header.kind, frame.payload),
+                _ => {}
+            }
+            if matches!(header.kind, FrameType::Headers | FrameType::Data) && header.has_flag(frame::END_STREAM) {
+                finished.push(header.stream_id);
+            }
+        }
+        (streams, finished)
+    }
+}
+
+fn header<'a>(stream: &'a Stream, name: &str) -> Option<&'a str> {
+    stream.headers.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+}
+
+#[test]
+// 同一个连接上的多个流并发处理,慢请求不会阻塞之后的请求
+fn multiplexed_streams() {
+    let server = TestServer::with_http2(router(), 2);
+    let mut client = Client::connect(&server);
+    client.request(1, "GET", "/slow", b"");
+    client.request(3, "POST", "/echo", b"hello h2");
+    client.request(5, "GET", "/big", b"");
+
+    let (streams, finished) = client.responses(3);
+    assert_eq!(1, *finished.last().unwrap());
+
+    let echo = &streams[&3];
+    assert_eq!(Some("200"), header(echo, ":status"));
+    assert_eq!(Some("HTTP/2.0"), header(echo, "x-version"));
+    assert_eq!(Some("example.com"), header(echo, "x-host"));
+    assert_eq!(b"hello h2", &echo.body[..]);
+
+    let big = &streams[&5];
+    assert_eq!(Some("100000"), header(big, "content-length"));
+    assert_eq!(100_000, big.body.len());
+    assert_eq!(b"slow", &streams[&1].body[..]);
+}
+
+#[test]
+// 启用HTTP/2之后HTTP/1.1请求照常处理;未启用时连接前言只是一个普通的HTTP/1.1请求
+fn http1_remains_default() {
+    let server = TestServer::with_http2(router(), 2);
+    let response = server.post("/echo").body("plain").send().unwrap();
+    assert_eq!(200, response.status);
+    assert_eq!(Some("HTTP/1.1"), response.header("X-Version"));
+
+    let server = TestServer::start(router());
+    let mut stream = TcpStream::connect(server.addr()).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(PREFACE).unwrap();
+    let mut status = String::new();
+    BufReader::new(stream).read_line(&mut status).unwrap();
+    assert!(status.starts_with("HTTP/1.1 404"), "unexpected status line: {}", status);
+}
+
+#[test]
+// 使用curl作为真正的HTTP/2客户端,没有安装curl或curl不支持HTTP/2时跳过
+fn curl_prior_knowledge() {
+    let supports_http2 = Command::new("curl")
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains("HTTP2"));
+    if !supports_http2 {
+        eprintln!("curl with HTTP/2 support not found, skipping");
+        return;
+    }
+
+    let server = TestServer::with_http2(router(), 2);
+    let curl = |path: &str, args: &[&str]| {
+        let output = Command::new("curl")
+            .args(["--http2-prior-knowledge", "--silent", "--show-error", "--max-time", "10"])
+            .args(args)
+            .arg(format!("http://{}{}", server.addr(), path))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "curl failed: {}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let echo = curl("/echo", &["--include", "--data", "from curl"]);
+    assert!(echo.starts_with("HTTP/2 200"), "{}", echo);
+    assert!(echo.contains("x-version: HTTP/2.0"));
+    assert!(echo.ends_with("from curl"));
+
+    let big = curl("/big", &["--output", "/dev/null", "--write-out", "%{http_version} %{size_download}"]);
+    assert_eq!("2 100000", big);
+}
+
+#[test]
+// 解码后过大的头部只拒绝这个流,连接和动态表照常使用
+fn header_list_too_large() {
+    let server = TestServer::with_http2(router(), 2);
+    let mut client = Client::connect(&server);
+
+    // 一个4KB的表项被引用两万次,压缩后约24KB,解码后约80MB
+    let mut block = vec![0x82, 0x86, 0x84, 0x40, 0x01, b'x', 0x7f, 0xa1, 0x1e];
+    block.extend_from_slice(&[b'v'; 4000]);
+    block.extend_from_slice(&[0xbe; 20_000]);
+    let (first, rest) = block.split_at(frame::DEFAULT_MAX_FRAME_SIZE);
+    write_frame(&mut client.stream, FrameType::Headers, frame::END_STREAM, 1, first).unwrap();
+    write_frame(&mut client.stream, FrameType::Continuation, frame::END_HEADERS, 1, rest).unwrap();
+    client.request(3, "POST", "/echo", b"still here");
+
+    let (streams, _) = client.responses(2);
+    assert_eq!(Some("431"), header(&streams[&1], ":status"));
+    assert_eq!(b"still here", &streams[&3].body[..]);
+}
+
+#[test]
+// 请求body交给worker之后才归还连接窗口,所有流缓冲的body总共不超过窗口,超出窗口的客户端会被断开
+fn receive_window_bounds_buffered_bodies() {
+    let server = TestServer::with_http2(router(), 2);
+    server.reload_handle().reload(router(), Limits { max_body_size: 100_000, ..Limits::default() });
+    let mut client = Client::connect(&server);
+    let chunk = [b'a'; 10_000];
+
+    client.headers(1, "POST", "/echo", false);
+    client.headers(3, "POST", "/echo", false);
+    for _ in 0..6 {
+        client.data(1, &chunk, false);
+    }
+    for _ in 0..4 {
+        client.data(3, &chunk, false);
+    }
+    // 窗口已经用完,流1结束后它占用的60000字节被归还
+    client.data(1, &[], true);
+    assert_eq!(60_000, client.responses(1).0[&1].body.len());
+    for _ in 0..5 {
+        client.data(3, &chunk, false);
+    }
+    client.data(3, &[], true);
+    assert_eq!(90_000, client.responses(1).0[&3].body.len());
+
+    client.headers(5, "POST", "/echo", false);
+    client.headers(7, "POST", "/echo", false);
+    for _ in 0..5 {
+        client.data(5, &chunk, false);
+        client.data(7, &chunk, false);
+    }
+    client.data(7, b"!", false);
+    // FLOW_CONTROL_ERROR
+    assert_eq!(0x3, client.go_away());
+}
@@ -0,0 +1,81 @@
+use std::env;
+use std::fs;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use my_web_server::app::{self, AppState};
+use my_web_server::config::{Config, ConfigWatcher};
+use my_web_server::http::Response;
+use my_web_server::routing::Router;
+use my_web_server::server::Limits;
+use my_web_server::testing::TestServer;
+
+fn site(name: &'static str) -> Router {
+    Router::new()
+        .get("/", move |_| Response::text(200, name))
+        .post("/echo", |request| Response::ok().with_body(request.body().to_vec()))
+}
+
+#[test]
+fn reload_swaps_router_and_limits() {
+    let server = TestServer::start(site("old"));
+    assert_eq!("old", server.get("/").send().unwrap().text());
+    assert_eq!(200, server.post("/echo").body("0123456789").send().unwrap().status);
+
+    let limits = Limits { max_body_size: 4, ..Limits::default() };
+    server.reload_handle().reload(site("new"), limits);
+    assert_eq!("new", server.get("/").send().unwrap().text());
+    assert_eq!(413, server.post("/echo").body("0123456789").send().unwrap().status);
+}
+
+#[test]
+// 重新加载后信任的代理立即生效:代理转发的每个客户端各有一个令牌桶,而不是共用代理地址的令牌桶
+fn reload_applies_trusted_proxies() {
+    let config = |proxies: &str| {
+        Config::parse(&format!(
+            r#"{{"templates": "{}/templates", "rate_limit": {{"rate": 0.01, "burst": 1, "trusted_proxies": [{}]}}}}"#,
+            env!("CARGO_MANIFEST_DIR"),
+            proxies
+        ))
+        .unwrap()
+    };
+    let state = AppState::new();
+    let server = TestServer::start(app::configure(&config(""), &state));
+    let status = |client: &str| server.get("/").header("X-Forwarded-For", client).send().unwrap().status;
+    assert_eq!(200, status("203.0.113.1"));
+    assert_eq!(429, status("203.0.113.2"));
+
+    let config = config(r#""127.0.0.1""#);
+    server.reload_handle().reload(app::configure(&config, &state), config.limits());
+    assert_eq!(200, status("203.0.113.1"));
+    assert_eq!(429, status("203.0.113.1"));
+    assert_eq!(200, status("203.0.113.2"));
+}
+
+#[test]
+// 只有合法的新配置会交给回调,不合法的配置被忽略
+fn watcher_reloads_valid_configs() {
+    let dir = env::temp_dir().join(format!("config-watcher-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("server.json");
+    let templates = concat!(env!("CARGO_MANIFEST_DIR"), "/templates");
+    fs::write(&path, format!(r#"{{"templates": "{}"}}"#, templates)).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    ConfigWatcher::new(&path)
+        .interval(Duration::from_millis(20))
+        .spawn(move |config: Config| sender.send(config).unwrap())
+        .unwrap();
+
+    // 保证修改时间与初始文件不同
+    thread::sleep(Duration::from_millis(50));
+    fs::write(&path, r#"{"templates": "/does/not/exist"}"#).unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+
+    thread::sleep(Duration::from_millis(50));
+    fs::write(&path, format!(r#"{{"templates": "{}", "max_body_size": 1024}}"#, templates)).unwrap();
+    let config = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(1024, config.max_body_size);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use my_web_server::app;
+use my_web_server::http::Method;
+use my_web_server::template::Templates;
+use my_web_server::testing::TestServer;
+
+fn app_server() -> TestServer {
+    let templates = Templates::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates"));
+    TestServer::start(app::router(Arc::new(templates)))
+}
+
+#[test]
+fn home_page() {
+    let server = app_server();
+    let response = server.get("/").send().unwrap();
+
+    assert_eq!(200, response.status);
+    assert_eq!(Some("text/html; charset=utf-8"), response.header("Content-Type"));
+    assert!(response.text().contains("<p>Hi from Rust</p>"));
+}
+
+#[test]
+// HEAD请求只返回头部,Content-Length与GET相同
+fn head_request_has_no_body() {
+    let server = app_server();
+    let get = server.get("/").send().unwrap();
+    let head = server.request(Method::Head, "/").send().unwrap();
+
+    assert_eq!(200, head.status);
+    assert!(head.body.is_empty());
+    assert_eq!(get.header("Content-Length"), head.header("Content-Length"));
+}
+
+#[test]
+fn sleep_route() {
+    let server = app_server();
+    let start = Instant::now();
+    let response = server.get("/sleep").send().unwrap();
+
+    assert_eq!(200, response.status);
+    assert!(start.elapsed() >= Duration::from_secs(5));
+    assert!(response.text().contains("<p>Hi from Rust</p>"));
+}
+
+#[test]
+// 404页面中回显的路径需要被转义
+fn unknown_route_is_404() {
+    let server = app_server();
+    let response = server.get("/missing%3Cscript%3E").send().unwrap();
+
+    assert_eq!(404, response.status);
+    let body = response.text();
+    assert!(body.contains("Oops!"));
+    assert!(body.contains("/missing&lt;script&gt;"));
+}
+
+#[test]
+// 一个worker在处理/sleep时,其他worker仍然可以立即响应/
+fn sleep_does_not_block_home() {
+    let server = app_server();
+    let sleep_request = server.get("/sleep");
+    let sleeper = thread::spawn(move || sleep_request.send().unwrap());
+    thread::sleep(Duration::from_millis(200));
+
+    let start = Instant::now();
+    let responses: Vec<_> = (0..3)
+        .map(|_| {
+            let request = server.get("/");
+            thread::spawn(move || request.send().unwrap())
+        })
+        .collect();
+    for response in responses {
+        assert_eq!(200, response.join().unwrap().status);
+    }
+
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert!(!sleeper.is_finished());
+    assert_eq!(200, sleeper.join().unwrap().status);
+}
+
+#[test]
+fn todo_crud() {
+    let server = app_server();
+
+    let created = server.post("/api/todos").json(&json!({ "title": "write tests" })).send().unwrap();
+    assert_eq!(201, created.status);
+    assert_eq!(Some("/api/todos/1"), created.header("Location"));
+    assert_eq!(json!({ "id": 1, "title": "write tests", "done": false }), created.json::<Value>().unwrap());
+
+    let updated = server.put("/api/todos/1").json(&json!({ "done": true })).send().unwrap();
+    assert_eq!(true, updated.json::<Value>().unwrap()["done"]);
+
+    let list = server.get("/api/todos").send().unwrap();
+    assert_eq!(1, list.json::<Vec<Value>>().unwrap().len());
+
+    assert_eq!(204, server.delete("/api/todos/1").send().unwrap().status);
+    assert_eq!(404, server.get("/api/todos/1").send().unwrap().status);
+}
+
+#[test]
+// JSON格式错误时返回400及错误信息
+fn malformed_json_is_400() {
+    let server = app_server();
+    let response = server
+        .post("/api/todos")
+        .header("Content-Type", "application/json")
+        .body("{\"title\":")
+        .send()
+        .unwrap();
+
+    assert_eq!(400, response.status);
+    assert!(response.json::<Value>().unwrap()["error"].as_str().unwrap().starts_with("invalid JSON"));
+}
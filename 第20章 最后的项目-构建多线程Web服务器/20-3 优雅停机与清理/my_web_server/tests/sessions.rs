@@ -0,0 +1,104 @@
+use std::sync::Arc;
+use my_web_server::app;
+use my_web_server::http::{Request, Response};
+use my_web_server::routing::Router;
+use my_web_server::session::{MemoryStore, SessionLayer};
+use my_web_server::template::Templates;
+use my_web_server::testing::{TestResponse, TestServer};
+
+fn counter_server() -> TestServer {
+    let router = Router::new()
+        .get("/count", |request: &Request| {
+            let session = request.session().unwrap();
+            let count = session.get::<u64>("count").unwrap_or(0) + 1;
+            session.insert("count", count);
+            Response::text(200, count.to_string())
+        })
+        .get("/peek", |request: &Request| {
+            let count = request.session().and_then(|session| session.get::<u64>("count"));
+            Response::text(200, format!("{:?}", count))
+        })
+        .get("/reset", |request: &Request| {
+            request.session().unwrap().destroy();
+            Response::text(200, "reset")
+        })
+        .layer(SessionLayer::new(MemoryStore::new()).cookie_name("test_sid"));
+    TestServer::start(router)
+}
+
+/// 从`Set-Cookie`中取出`name=value`部分,作为下一次请求的`Cookie`头部
+fn session_cookie(response: &TestResponse) -> String {
+    let set_cookie = response.header("Set-Cookie").expect("missing Set-Cookie");
+    set_cookie.split(';').next().unwrap().to_string()
+}
+
+#[test]
+fn session_persists_between_requests() {
+    let server = counter_server();
+    let first = server.get("/count").send().unwrap();
+    assert_eq!("1", first.text());
+    let set_cookie = first.header("Set-Cookie").unwrap();
+    assert!(set_cookie.starts_with("test_sid="));
+    assert!(set_cookie.contains("HttpOnly"));
+    assert!(set_cookie.contains("SameSite=Lax"));
+
+    let cookie = session_cookie(&first);
+    let second = server.get("/count").header("Cookie", cookie.as_str()).send().unwrap();
+    assert_eq!("2", second.text());
+    // 没有Cookie的客户端得到新的会话
+    assert_eq!("1", server.get("/count").send().unwrap().text());
+}
+
+#[test]
+// 只读取会话的请求不会创建会话
+fn untouched_session_sets_no_cookie() {
+    let server = counter_server();
+    let response = server.get("/peek").send().unwrap();
+    assert_eq!("None", response.text());
+    assert_eq!(None, response.header("Set-Cookie"));
+}
+
+#[test]
+fn destroyed_session_clears_cookie() {
+    let server = counter_server();
+    let cookie = session_cookie(&server.get("/count").send().unwrap());
+
+    let reset = server.get("/reset").header("Cookie", cookie.as_str()).send().unwrap();
+    assert!(reset.header("Set-Cookie").unwrap().contains("Max-Age=0"));
+    let peek = server.get("/peek").header("Cookie", cookie.as_str()).send().unwrap();
+    assert_eq!("None", peek.text());
+}
+
+#[test]
+// 未知的会话id不会被采用,避免会话固定攻击
+fn unknown_session_id_is_replaced() {
+    let server = counter_server();
+    let forged = format!("test_sid={}", "a".repeat(64));
+    let response = server.get("/count").header("Cookie", forged.as_str()).send().unwrap();
+    assert_eq!("1", response.text());
+    assert_ne!(forged, session_cookie(&response));
+}
+
+#[test]
+fn login_and_logout() {
+    let templates = Templates::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates"));
+    let server = TestServer::start(app::router(Arc::new(templates)));
+
+    let login = server
+        .post("/login")
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("name=ferris")
+        .send()
+        .unwrap();
+    assert_eq!(303, login.status);
+    assert_eq!(Some("/"), login.header("Location"));
+    let cookie = session_cookie(&login);
+
+    let home = server.get("/").header("Cookie", cookie.as_str()).send().unwrap();
+    assert!(home.text().contains("Logged in as ferris"));
+
+    let logout = server.post("/logout").header("Cookie", cookie.as_str()).send().unwrap();
+    assert_eq!(303, logout.status);
+    let home = server.get("/").header("Cookie", cookie.as_str()).send().unwrap();
+    assert!(!home.text().contains("Logged in as"));
+}
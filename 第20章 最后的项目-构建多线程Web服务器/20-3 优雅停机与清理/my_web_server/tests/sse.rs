@@ -0,0 +1,106 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use serde_json::json;
+use my_web_server::app;
+use my_web_server::http::Response;
+use my_web_server::routing::Router;
+use my_web_server::sse::{Event, Sse};
+use my_web_server::template::Templates;
+use my_web_server::testing::TestServer;
+
+/// 建立SSE连接,读完响应头部后返回连接,之后可以逐行读取事件流
+fn connect(server: &TestServer, target: &str, last_event_id: Option<&str>) -> BufReader<TcpStream> {
+    let mut stream = TcpStream::connect(server.addr()).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let mut request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n", target);
+    if let Some(id) = last_event_id {
+        request.push_str(&format!("Last-Event-ID: {}\r\n", id));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut reader = BufReader::new(stream);
+    let status = read_line(&mut reader);
+    assert!(status.starts_with("HTTP/1.1 200"), "unexpected status line: {}", status);
+    let mut content_type = None;
+    loop {
+        let line = read_line(&mut reader);
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Type: ") {
+            content_type = Some(value.to_string());
+        }
+        assert!(!line.starts_with("Content-Length"));
+    }
+    assert_eq!(Some("text/event-stream".to_string()), content_type);
+    reader
+}
+
+fn read_line(reader: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    line.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// 读取一个以空行结束的事件
+fn read_event(reader: &mut BufReader<TcpStream>) -> Vec<String> {
+    let mut lines = Vec::new();
+    loop {
+        let line = read_line(reader);
+        if line.is_empty() {
+            return lines;
+        }
+        lines.push(line);
+    }
+}
+
+#[test]
+// 只有一个worker时,SSE连接不会占住它,其他请求照常处理
+fn streams_events_without_holding_a_worker() {
+    let (senders, receivers) = mpsc::channel();
+    let receivers = Arc::new(Mutex::new(receivers));
+    let router = Router::new()
+        .get("/events", move |_| {
+            let receiver = receivers.lock().unwrap().recv().unwrap();
+            Sse::new(receiver).heartbeat(Duration::from_millis(100))
+        })
+        .get("/ping", |_| Response::text(200, "pong"));
+    let server = TestServer::with_workers(router, 1);
+
+    let (sender, receiver) = mpsc::channel();
+    senders.send(receiver).unwrap();
+    let mut stream = connect(&server, "/events", None);
+
+    assert_eq!("pong", server.get("/ping").send().unwrap().text());
+
+    sender.send(Event::new("hello\nworld").with_event("greeting").with_id("1")).unwrap();
+    assert_eq!(vec!["id: 1", "event: greeting", "data: hello", "data: world"], read_event(&mut stream));
+    assert_eq!(vec![": heartbeat"], read_event(&mut stream));
+
+    // 发送端全部丢弃后连接关闭
+    drop(sender);
+    let mut rest = String::new();
+    while stream.read_line(&mut rest).unwrap() > 0 {}
+}
+
+#[test]
+fn todo_events_resume_from_last_event_id() {
+    let templates = Templates::new(concat!(env!("CARGO_MANIFEST_DIR"), "/templates"));
+    let server = TestServer::start(app::router(Arc::new(templates)));
+    for title in ["first", "second"] {
+        assert_eq!(201, server.post("/api/todos").json(&json!({ "title": title })).send().unwrap().status);
+    }
+
+    let mut stream = connect(&server, "/api/todos/events", Some("1"));
+    assert_eq!(
+        vec!["id: 2", "event: created", r#"data: {"id":2,"title":"second","done":false}"#],
+        read_event(&mut stream)
+    );
+
+    server.delete("/api/todos/1").send().unwrap();
+    let deleted = read_event(&mut stream);
+    assert_eq!(vec!["id: 3", "event: deleted"], deleted[..2]);
+}
@@ -0,0 +1,47 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use my_web_server::routing::Router;
+use my_web_server::sse::stream::MAX_STREAMS;
+use my_web_server::sse::{Broadcaster, Sse};
+use my_web_server::testing::TestServer;
+
+/// 请求事件流,返回状态码和连接,连接保持打开直到被丢弃
+fn open(server: &TestServer) -> (u16, TcpStream) {
+    let mut stream = TcpStream::connect(server.addr()).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"GET /events HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    let mut status = String::new();
+    BufReader::new(&stream).read_line(&mut status).unwrap();
+    (status.split(' ').nth(1).unwrap().parse().unwrap(), stream)
+}
+
+#[test]
+// 事件流的数量达到上限后返回503,断开的客户端归还名额
+// 上限是整个进程共享的,因此单独放在一个测试文件中,不和其他打开事件流的测试同时运行
+fn limits_concurrent_streams() {
+    let events = Arc::new(Broadcaster::new(0));
+    let router = Router::new().get("/events", move |_| {
+        Sse::new(events.subscribe(None)).heartbeat(Duration::from_millis(50))
+    });
+    let server = TestServer::start(router);
+
+    let mut streams: Vec<TcpStream> = (0..MAX_STREAMS)
+        .map(|_| {
+            let (status, stream) = open(&server);
+            assert_eq!(200, status);
+            stream
+        })
+        .collect();
+    assert_eq!(503, open(&server).0);
+
+    // 发送线程在下一次心跳时发现连接已经断开
+    streams.pop();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while open(&server).0 == 503 {
+        assert!(Instant::now() < deadline, "slot was not released");
+        thread::sleep(Duration::from_millis(20));
+    }
+}
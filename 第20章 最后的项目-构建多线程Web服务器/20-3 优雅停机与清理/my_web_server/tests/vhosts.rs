@@ -0,0 +1,95 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use my_web_server::app::{self, AppState};
+use my_web_server::config::Config;
+use my_web_server::http::Response;
+use my_web_server::middleware::AccessLog;
+use my_web_server::routing::Router;
+use my_web_server::testing::TestServer;
+use my_web_server::vhost::{VirtualHost, VirtualHosts};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("vhosts-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn site(name: &'static str) -> Router {
+    Router::new().get("/", move |_| Response::text(200, name))
+}
+
+#[test]
+fn routes_by_host_header() {
+    let hosts = VirtualHosts::new()
+        .host(VirtualHost::new("docs.internal", site("docs")).alias("wiki.internal"))
+        .host(VirtualHost::new("*.builds.internal", site("builds")))
+        .default_host(VirtualHost::new("www.internal", site("www")));
+    let server = TestServer::start(Router::new().layer(hosts));
+    let body = |host: &str| server.get("/").header("Host", host).send().unwrap().text();
+
+    assert_eq!("docs", body("docs.internal"));
+    assert_eq!("docs", body("WIKI.internal:7878"));
+    assert_eq!("builds", body("linux.builds.internal"));
+    assert_eq!("www", body("builds.internal"));
+    assert_eq!("www", body("unknown.example"));
+}
+
+#[test]
+// 没有默认主机时交给外层路由处理
+fn falls_through_without_default() {
+    let hosts = VirtualHosts::new().host(VirtualHost::new("docs.internal", site("docs")));
+    let server = TestServer::start(site("outer").layer(hosts));
+
+    assert_eq!("docs", server.get("/").header("Host", "docs.internal").send().unwrap().text());
+    assert_eq!("outer", server.get("/").header("Host", "other.internal").send().unwrap().text());
+}
+
+#[test]
+fn document_root_and_access_log() {
+    let dir = temp_dir("root");
+    fs::create_dir_all(dir.join("public")).unwrap();
+    fs::write(dir.join("public/readme.txt"), "read me").unwrap();
+    let log_path = dir.join("docs.log");
+
+    let docs = VirtualHost::new("docs.internal", site("docs"))
+        .root(dir.join("public"))
+        .access_log(AccessLog::open(&log_path).unwrap());
+    let hosts = VirtualHosts::new().host(docs).default_host(VirtualHost::new("www.internal", site("www")));
+    let server = TestServer::start(Router::new().layer(hosts));
+
+    let file = server.get("/readme.txt").header("Host", "docs.internal").send().unwrap();
+    assert_eq!("read me", file.text());
+    assert_eq!(404, server.get("/readme.txt").header("Host", "www.internal").send().unwrap().status);
+
+    let log = fs::read_to_string(&log_path).unwrap();
+    let lines: Vec<&str> = log.lines().collect();
+    assert_eq!(1, lines.len());
+    assert!(lines[0].contains("\"GET /readme.txt HTTP/1.1\" 200 7"));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+// 配置文件中的虚拟主机:各自的文档根目录和访问日志,其他主机仍由应用的路由处理
+fn configured_from_json() {
+    let dir = temp_dir("config");
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join("docs/index.html"), "docs home").unwrap();
+    let config = Config::parse(&format!(
+        r#"{{"templates": "{}/templates", "vhosts": [{{"names": ["docs.internal", "*.docs.internal"], "root": "{}", "access_log": "{}"}}]}}"#,
+        env!("CARGO_MANIFEST_DIR"),
+        dir.join("docs").display(),
+        dir.join("docs.log").display(),
+    ))
+    .unwrap();
+    let server = TestServer::start(app::configure(&config, &AppState::new()));
+
+    let docs = server.get("/index.html").header("Host", "v2.docs.internal").send().unwrap();
+    assert_eq!("docs home", docs.text());
+    assert_eq!(404, server.get("/index.html").header("Host", "localhost").send().unwrap().status);
+    assert_eq!(200, server.get("/").header("Host", "localhost").send().unwrap().status);
+    assert_eq!(1, fs::read_to_string(dir.join("docs.log")).unwrap().lines().count());
+
+    fs::remove_dir_all(&dir).unwrap();
+}